@@ -0,0 +1,324 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::error::Error;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use simulator::scenario::{load_scenario, Scenario, ScenarioError, BUILTIN_SCENARIOS};
+use simulator::sim::{Simulator, SimulatorKind};
+use simulator::sim::checkpoint::resume_checkpoint;
+use simulator::sim::diagnostics::DiagnosticsRecorder;
+use simulator::sim::recorder::VtkRecorder;
+
+// Command line interface. Scenarios are given by the name of a built-in scenario or a TOML file,
+// see scenario.rs, and everything but `view` runs without a display.
+#[derive(Parser)]
+#[command(name = "simulator", about = "2D hyperelastic FVM and FEM simulator")]
+pub struct Cli {
+    // opens the Bevy viewer of the default beam if no command is given
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run a scenario headlessly, writing VTK snapshots and metrics to disk
+    Run(RunArgs),
+    /// Measure the update rate of a scenario
+    Bench(BenchArgs),
+    /// Build the mesh of a scenario, print its statistics and optionally write it to a file
+    Mesh(MeshArgs),
+    /// Open a viewer that runs a scenario
+    View(ViewArgs),
+}
+
+#[derive(Args)]
+pub struct ScenarioArgs {
+    /// Built-in scenario (beam, beam_example1, ball_example1, beam_example2) or scenario file
+    #[arg(default_value = "beam")]
+    pub scenario: String,
+    /// Simulator replacing the one of the scenario: fvm, fvm_seq or fem
+    #[arg(long)]
+    pub simulator: Option<String>,
+}
+
+impl ScenarioArgs {
+    pub fn load(&self) -> Result<Scenario, ScenarioError> {
+        let mut scenario = load_scenario(&self.scenario)?;
+        if let Some(simulator) = &self.simulator {
+            scenario.simulator = simulator.clone();
+        }
+        Ok(scenario)
+    }
+
+    // name of the output files, the name in the scenario or else its file name
+    pub fn name(&self, scenario: &Scenario) -> String {
+        scenario.name.clone().unwrap_or_else(|| {
+            Path::new(&self.scenario).file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "scenario".to_string())
+        })
+    }
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub scenario: ScenarioArgs,
+    /// Simulated time to run until, the duration of the scenario by default
+    #[arg(long, short)]
+    pub duration: Option<f64>,
+    /// Directory of the snapshots and metrics
+    #[arg(long, short, default_value = "output")]
+    pub output: PathBuf,
+    /// Write a snapshot and a row of metrics every N updates
+    #[arg(long, default_value_t = 100)]
+    pub every: usize,
+    /// Only write the metrics, no VTK snapshots
+    #[arg(long)]
+    pub no_snapshots: bool,
+    /// Also write the energies and momenta of every N updates
+    #[arg(long)]
+    pub diagnostics: bool,
+    /// Stop when the energy drift exceeds this fraction of the largest energy, implies --diagnostics
+    #[arg(long)]
+    pub max_energy_drift: Option<f64>,
+    /// Write a checkpoint of the final state, which --resume continues from
+    #[arg(long)]
+    pub checkpoint: Option<PathBuf>,
+    /// Continue from a checkpoint of the scenario instead of its initial state. Its events after the
+    /// time of the checkpoint still apply. The output directory must not hold an earlier run.
+    #[arg(long, conflicts_with = "simulator")]
+    pub resume: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct BenchArgs {
+    #[command(flatten)]
+    pub scenario: ScenarioArgs,
+    /// Number of timed updates
+    #[arg(long, default_value_t = 1000)]
+    pub steps: usize,
+    /// Number of updates before timing
+    #[arg(long, default_value_t = 10)]
+    pub warmup: usize,
+}
+
+#[derive(Args)]
+pub struct MeshArgs {
+    #[command(flatten)]
+    pub scenario: ScenarioArgs,
+    /// Output file, written as .vtk, .obj or .png depending on its extension
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ViewArgs {
+    #[command(flatten)]
+    pub scenario: ScenarioArgs,
+    #[arg(long, value_enum, default_value_t = Viewer::Bevy)]
+    pub viewer: Viewer,
+    /// Field coloring the mesh in the Bevy viewer: von_mises, strain_energy, speed or displacement
+    #[arg(long, default_value = "von_mises")]
+    pub field: String,
+    /// Colormap of the field: viridis, plasma, coolwarm, jet or grayscale
+    #[arg(long, default_value = "viridis")]
+    pub colormap: String,
+    /// Draw filled triangles instead of a wireframe in the Bevy viewer
+    #[arg(long)]
+    pub filled: bool,
+}
+
+impl Default for ViewArgs {
+    fn default() -> ViewArgs {
+        ViewArgs {
+            scenario: ScenarioArgs { scenario: BUILTIN_SCENARIOS[0].0.to_string(), simulator: None },
+            viewer: Viewer::Bevy,
+            field: "von_mises".to_string(),
+            colormap: "viridis".to_string(),
+            filled: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Viewer {
+    Bevy,
+    Gtk,
+}
+
+// Metrics of the current state, one row of the metrics file per snapshot
+struct Metrics {
+    max_displacement: f64,
+    max_speed: f64,
+    max_von_mises: f64,
+}
+
+impl Metrics {
+    const HEADER: &'static str = "time,wall_time,updates,max_displacement,max_speed,max_von_mises";
+
+    fn new(sim: &dyn Simulator) -> Metrics {
+        let norms = |vectors: ndarray::Array2<f64>| vectors.outer_iter()
+            .map(|v| v.dot(&v).sqrt())
+            .fold(0.0, f64::max);
+        Metrics {
+            max_displacement: norms(sim.positions() - sim.material_coords()),
+            max_speed: norms(sim.velocities().clone()),
+            max_von_mises: sim.triangle_stresses().iter().map(|s| s.von_mises).fold(0.0, f64::max),
+        }
+    }
+}
+
+// an explicit timestep above the stable one is allowed, e.g. to watch the instability grow
+pub fn warn_unstable_timestep(sim: &dyn Simulator) -> () {
+    if let Err(e) = sim.check_timestep() {
+        eprintln!("Warning: {}", e);
+    }
+}
+
+pub fn run(args: &RunArgs) -> Result<(), Box<dyn Error>> {
+    let scenario = args.scenario.load()?;
+    let name = args.scenario.name(&scenario);
+    let duration = args.duration.or(scenario.duration)
+        .ok_or("The scenario has no duration, set one with --duration")?;
+    let every = args.every.max(1);
+    if args.resume.is_some() {
+        // a resumed run would restart the snapshot numbering and truncate the metrics of the earlier run
+        let outputs = [format!("{}.pvd", name), format!("{}_metrics.csv", name), format!("{}_diagnostics.csv", name)];
+        if let Some(existing) = outputs.iter().map(|file| args.output.join(file)).find(|path| path.exists()) {
+            return Err(format!("{} exists, resume into another --output to keep the earlier run",
+                existing.display()).into());
+        }
+    }
+    let (mut sim, mut schedule) = scenario.build()?;
+    if let Some(path) = &args.resume {
+        // the checkpoint holds the effects of the events before its time
+        let kind = scenario.simulator.parse::<SimulatorKind>()?;
+        sim = resume_checkpoint(path, kind, sim.as_ref())?;
+        schedule.skip_events_until(sim.time());
+    }
+    warn_unstable_timestep(sim.as_ref());
+
+    fs::create_dir_all(&args.output)?;
+    let mut recorder = match args.no_snapshots {
+        true => None,
+        false => Some(VtkRecorder::new(&args.output, &name, 1)?),
+    };
+    let metrics_path = args.output.join(format!("{}_metrics.csv", name));
+    let mut metrics_file = BufWriter::new(fs::File::create(&metrics_path)?);
+    writeln!(metrics_file, "{}", Metrics::HEADER)?;
+    let mut diagnostics = match args.diagnostics || args.max_energy_drift.is_some() {
+        true => Some(DiagnosticsRecorder::new(args.output.join(format!("{}_diagnostics.csv", name)), every)?),
+        false => None,
+    };
+    // the diagnostics record every update to integrate the external work
+    let mut check_diagnostics = |sim: &dyn Simulator| -> Result<(), Box<dyn Error>> {
+        if let Some(diagnostics) = diagnostics.as_mut() {
+            diagnostics.record(sim)?;
+            if let Some(tolerance) = args.max_energy_drift {
+                if let Err(e) = diagnostics.check_energy_drift(tolerance) {
+                    diagnostics.finish()?;
+                    return Err(e.into());
+                }
+            }
+        }
+        Ok(())
+    };
+
+    let start = Instant::now();
+    let mut num_updates = 0;
+    let mut record = |sim: &dyn Simulator, num_updates: usize| -> Result<(), Box<dyn Error>> {
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.write_snapshot(sim)?;
+        }
+        let metrics = Metrics::new(sim);
+        writeln!(metrics_file, "{},{},{},{},{},{}", sim.time(), start.elapsed().as_secs_f64(), num_updates,
+            metrics.max_displacement, metrics.max_speed, metrics.max_von_mises)?;
+        metrics_file.flush()?;
+        Ok(())
+    };
+
+    record(sim.as_ref(), num_updates)?;
+    check_diagnostics(sim.as_ref())?;
+    while sim.time() < duration {
+        if let Err(e) = schedule.step(sim.as_mut()) {
+            // the failed state is kept for inspection
+            record(sim.as_ref(), num_updates)?;
+            return Err(e.into());
+        }
+        num_updates += 1;
+        if let Err(e) = check_diagnostics(sim.as_ref()) {
+            record(sim.as_ref(), num_updates)?;
+            return Err(e);
+        }
+        if num_updates % every == 0 {
+            record(sim.as_ref(), num_updates)?;
+        }
+    }
+    if num_updates % every != 0 {
+        record(sim.as_ref(), num_updates)?;
+    }
+    if let Some(diagnostics) = diagnostics.as_mut() {
+        diagnostics.finish()?;
+    }
+
+    if let Some(path) = &args.checkpoint {
+        sim.save_checkpoint(path)?;
+    }
+    println!("Simulated {} s in {} updates and {:.2} s, wrote {} to {}",
+        sim.time(), num_updates, start.elapsed().as_secs_f64(), metrics_path.display(),
+        args.output.display());
+    Ok(())
+}
+
+pub fn bench(args: &BenchArgs) -> Result<(), Box<dyn Error>> {
+    let scenario = args.scenario.load()?;
+    let (mut sim, mut schedule) = scenario.build()?;
+    warn_unstable_timestep(sim.as_ref());
+    for _ in 0..args.warmup {
+        schedule.step(sim.as_mut())?;
+    }
+
+    let start_time = sim.time();
+    let start = Instant::now();
+    for _ in 0..args.steps {
+        schedule.step(sim.as_mut())?;
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    println!("{}: {} updates in {:.3} s, {:.1} updates/s, {:.4} simulated s per s",
+        args.scenario.name(&scenario), args.steps, elapsed, args.steps as f64 / elapsed,
+        (sim.time() - start_time) / elapsed);
+    Ok(())
+}
+
+pub fn mesh(args: &MeshArgs) -> Result<(), Box<dyn Error>> {
+    let scenario = args.scenario.load()?;
+    let mesh = scenario.build_mesh()?;
+    let topology = &mesh.topology;
+    println!("vertices: {}", topology.num_vertices());
+    println!("triangles: {}", topology.num_triangles());
+    println!("boundary edges: {}", topology.boundary_edges.len());
+    println!("components: {}, holes: {}", topology.num_components(), topology.num_holes());
+    println!("triangle areas: {:e} to {:e}",
+        mesh.areas.fold(f64::INFINITY, |a, &b| a.min(b)),
+        mesh.areas.fold(0.0_f64, |a, &b| a.max(b)));
+    let mut tags: Vec<(&String, usize)> = mesh.edge_tags.iter().map(|(tag, edges)| (tag, edges.len())).collect();
+    tags.sort();
+    for (tag, num_edges) in tags {
+        println!("edge tag '{}': {} edges", tag, num_edges);
+    }
+
+    if let Some(path) = &args.output {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("vtk") => mesh.write_vtk(path)?,
+            Some("obj") => mesh.write_obj(path)?,
+            #[cfg(feature = "plotting")]
+            Some("png") => simulator::plotting::plot_triangle_mesh(&mesh, &path.to_string_lossy())?,
+            _ => return Err(format!("Unknown mesh output format of {}, use .vtk, .obj or .png \
+                (with the plotting feature)", path.display()).into()),
+        }
+        println!("wrote {}", path.display());
+    }
+    Ok(())
+}
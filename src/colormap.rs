@@ -0,0 +1,84 @@
+use std::str::FromStr;
+
+// Colormaps for scalar fields, each given by evenly spaced sRGB colors that are interpolated linearly
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Colormap {
+    Viridis,   // perceptually uniform, dark blue to yellow
+    Plasma,    // perceptually uniform, dark blue to yellow over magenta
+    Coolwarm,  // diverging blue to red over light gray, for fields around a center value
+    Jet,       // the classic rainbow map, not perceptually uniform
+    Grayscale,
+}
+
+impl FromStr for Colormap {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "viridis" => Ok(Colormap::Viridis),
+            "plasma" => Ok(Colormap::Plasma),
+            "coolwarm" => Ok(Colormap::Coolwarm),
+            "jet" => Ok(Colormap::Jet),
+            "grayscale" => Ok(Colormap::Grayscale),
+            _ => Err(format!("Unknown colormap '{}'! Colormaps: \
+                {{ 'viridis', 'plasma', 'coolwarm', 'jet', 'grayscale' }}", name)),
+        }
+    }
+}
+
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84], [71, 45, 123], [59, 82, 139], [44, 114, 142], [33, 145, 140],
+    [40, 174, 128], [94, 201, 98], [173, 220, 48], [253, 231, 37],
+];
+const PLASMA: [[u8; 3]; 9] = [
+    [13, 8, 135], [76, 2, 161], [126, 3, 168], [169, 35, 149], [204, 71, 120],
+    [229, 107, 93], [248, 149, 64], [253, 195, 40], [240, 249, 33],
+];
+const COOLWARM: [[u8; 3]; 9] = [
+    [59, 76, 192], [98, 130, 234], [141, 176, 254], [184, 208, 249], [221, 221, 221],
+    [245, 196, 173], [244, 154, 123], [222, 96, 77], [180, 4, 38],
+];
+const JET: [[u8; 3]; 9] = [
+    [0, 0, 128], [0, 0, 255], [0, 128, 255], [0, 255, 255], [128, 255, 128],
+    [255, 255, 0], [255, 128, 0], [255, 0, 0], [128, 0, 0],
+];
+const GRAYSCALE: [[u8; 3]; 2] = [[0, 0, 0], [255, 255, 255]];
+
+impl Colormap {
+    // sRGB color in [0, 1] of a value t in [0, 1], clamping values outside
+    pub fn color(&self, t: f64) -> [f32; 3] {
+        let colors: &[[u8; 3]] = match self {
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Plasma => &PLASMA,
+            Colormap::Coolwarm => &COOLWARM,
+            Colormap::Jet => &JET,
+            Colormap::Grayscale => &GRAYSCALE,
+        };
+        // NaN is drawn with the lowest color
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let x = t * (colors.len() - 1) as f64;
+        let idx = (x.floor() as usize).min(colors.len() - 2);
+        let w = (x - idx as f64) as f32;
+        let (a, b) = (colors[idx], colors[idx + 1]);
+        [0, 1, 2].map(|c| ((1.0 - w) * a[c] as f32 + w * b[c] as f32) / 255.0)
+    }
+
+    // color of a value in the range [min, max]
+    pub fn color_in_range(&self, value: f64, min: f64, max: f64) -> [f32; 3] {
+        match max > min {
+            true => self.color((value - min) / (max - min)),
+            false => self.color(0.5),
+        }
+    }
+
+    // the following colormap, for cycling through all of them
+    pub fn next(&self) -> Colormap {
+        match self {
+            Colormap::Viridis => Colormap::Plasma,
+            Colormap::Plasma => Colormap::Coolwarm,
+            Colormap::Coolwarm => Colormap::Jet,
+            Colormap::Jet => Colormap::Grayscale,
+            Colormap::Grayscale => Colormap::Viridis,
+        }
+    }
+}
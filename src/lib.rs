@@ -0,0 +1,20 @@
+// Core of the simulator: meshes, control volumes, the FVM and FEM solvers and scenarios. The viewers
+// and plotting are behind the `gtk`, `bevy` and `plotting` features, so the core builds headless.
+
+pub mod mesh;
+pub mod topology;
+pub mod mesher;
+pub mod mesh_io;
+pub mod cv;
+pub mod sim;
+pub mod scenario;
+pub mod colormap;
+
+#[cfg(feature = "plotting")]
+pub mod plotting;
+#[cfg(feature = "gtk")]
+pub mod window;
+#[cfg(feature = "gtk")]
+pub mod examples;
+#[cfg(feature = "bevy")]
+pub mod viewer;
@@ -1,9 +1,9 @@
 use ndarray::prelude::*;
 use ndarray::{Array1, Array2};
-use plotters::prelude::*;
 use std::f64::consts::PI;
-use std::collections::HashMap;
-//use delaunator::{triangulate, Point};
+use std::collections::{HashMap, HashSet};
+use crate::topology::MeshTopology;
+use crate::mesher::{self, MeshError, MeshQuality};
 
 #[derive(Clone)]
 pub struct TriangleMesh {
@@ -13,6 +13,12 @@ pub struct TriangleMesh {
     // Mesh info properties
     pub areas: Array1<f64>,         // (M)
     pub vertex_neighbor_tris: Vec<Vec<usize>>, // (N) 
+
+    // boundary edges (vertex index pairs) grouped by a tag, e.g. the sides of the beam
+    pub edge_tags: HashMap<String, Vec<[usize; 2]>>,
+
+    // edges, adjacency and boundary loops of the triangles
+    pub topology: MeshTopology,
 }
 
 impl TriangleMesh {
@@ -27,8 +33,10 @@ impl TriangleMesh {
         }
         
         let vertex_neighbor_tris = Self::compute_vertex_triangle_adjacency(&vertices, &triangles);
+        let edge_tags = Self::make_beam_edge_tags(shape);
+        let topology = MeshTopology::new(vertices.nrows(), &triangles);
 
-        TriangleMesh {vertices, triangles, areas, vertex_neighbor_tris}
+        TriangleMesh {vertices, triangles, areas, vertex_neighbor_tris, edge_tags, topology}
     }
     
     pub fn new_ball(res: usize) -> TriangleMesh {
@@ -43,7 +51,93 @@ impl TriangleMesh {
         
         let vertex_neighbor_tris = Self::compute_vertex_triangle_adjacency(&vertices, &triangles);
 
-        TriangleMesh {vertices, triangles, areas, vertex_neighbor_tris}
+        // the outermost circle is the boundary of the ball
+        let outer = res as isize - 1;
+        let boundary_edges = (0..res*6)
+            .map(|point| [Self::get_point_index(outer, point), Self::get_point_index(outer, point+1)])
+            .collect();
+        let edge_tags = HashMap::from([("boundary".to_string(), boundary_edges)]);
+        let topology = MeshTopology::new(vertices.nrows(), &triangles);
+
+        TriangleMesh {vertices, triangles, areas, vertex_neighbor_tris, edge_tags, topology}
+    }
+
+    pub fn new_polygon(outer: &[[f64; 2]], holes: &[Vec<[f64; 2]>], quality: &MeshQuality) -> Result<TriangleMesh, MeshError> {
+        // quality triangulation of the area inside the outer polygon and outside the holes. Its
+        // boundary edges are tagged "outer" and "hole_0", "hole_1", ... Fails if the refinement
+        // reaches max_vertices, use mesher::triangulate_polygon to keep such a mesh.
+        let polygon_mesh = mesher::triangulate_polygon(outer, holes, quality)?;
+        if polygon_mesh.reached_vertex_limit {
+            return Err(MeshError::VertexLimitReached { max_vertices: quality.max_vertices });
+        }
+
+        let vertices = Array2::from_shape_vec((polygon_mesh.vertices.len(), 2),
+            polygon_mesh.vertices.iter().flatten().copied().collect())
+            .unwrap();
+        let triangles = Array2::from_shape_vec((polygon_mesh.triangles.len(), 3),
+            polygon_mesh.triangles.iter().flatten().copied().collect())
+            .unwrap();
+
+        let mut edge_tags = HashMap::<String, Vec<[usize; 2]>>::new();
+        for (&edge, &polygon_idx) in polygon_mesh.segments.iter() {
+            let tag = if polygon_idx == 0 { "outer".to_string() } else { format!("hole_{}", polygon_idx - 1) };
+            edge_tags.entry(tag).or_default().push(edge);
+        }
+        for edges in edge_tags.values_mut() {
+            edges.sort();
+        }
+
+        Self::from_triangles(vertices, triangles, edge_tags)
+    }
+
+    pub fn from_triangles(vertices: Array2<f64>, triangles: Array2<usize>, edge_tags: HashMap<String, Vec<[usize; 2]>>) -> Result<TriangleMesh, MeshError> {
+        // mesh of given triangles, which are made counter-clockwise. Tagged boundary edges are
+        // oriented like their triangle, as tractions expect.
+        let mut triangles = triangles;
+        for mut tri in triangles.outer_iter_mut() {
+            if Self::signed_area(&vertices, tri[0], tri[1], tri[2]) < 0.0 {
+                tri.swap(1, 2);
+            }
+        }
+
+        let areas = Self::compute_triangle_areas(&vertices, &triangles);
+
+        // verify that all areas are positive
+        if let Some(triangle) = areas.iter().position(|&a| a.is_nan() || a <= 0.0) {
+            return Err(MeshError::DegenerateTriangle { triangle });
+        }
+
+        let vertex_neighbor_tris = Self::compute_vertex_triangle_adjacency(&vertices, &triangles);
+        let topology = MeshTopology::new(vertices.nrows(), &triangles);
+
+        let boundary_edges: HashSet<[usize; 2]> = topology.boundary_edges.iter().copied().collect();
+        let mut edge_tags = edge_tags;
+        for edges in edge_tags.values_mut() {
+            for edge in edges.iter_mut() {
+                if boundary_edges.contains(&[edge[1], edge[0]]) {
+                    edge.swap(0, 1);
+                }
+            }
+        }
+
+        Ok(TriangleMesh {vertices, triangles, areas, vertex_neighbor_tris, edge_tags, topology})
+    }
+
+    pub fn tag_boundary_edges<F: Fn(f64, f64) -> bool>(&mut self, tag: &str, predicate: F) -> usize {
+        // adds the boundary edges whose midpoint satisfies the predicate to a tag, returning how many
+        // edges were tagged
+        let midpoint = |[a, b]: [usize; 2]| (0.5 * (self.vertices[[a, 0]] + self.vertices[[b, 0]]),
+            0.5 * (self.vertices[[a, 1]] + self.vertices[[b, 1]]));
+        let edges: Vec<[usize; 2]> = self.topology.boundary_edges.iter()
+            .copied()
+            .filter(|&edge| {
+                let (x, y) = midpoint(edge);
+                predicate(x, y)
+            })
+            .collect();
+        let num_edges = edges.len();
+        self.edge_tags.entry(tag.to_string()).or_default().extend(edges);
+        num_edges
     }
 
     fn make_circle_mesh(res: usize) -> (Array2<f64>, Array2<usize>) {
@@ -183,6 +277,20 @@ impl TriangleMesh {
         (v, t)
     }
     
+    fn make_beam_edge_tags(shape : (usize, usize)) -> HashMap<String, Vec<[usize; 2]>> {
+        // tags the four sides of the beam, with vertex k = i + j*(i_max+1) as in make_beam_mesh
+        let i_max = shape.0;
+        let j_max = shape.1;
+        let k = |i: usize, j: usize| i + j*(i_max+1);
+
+        HashMap::from([
+            ("down".to_string(), (0..i_max).map(|i| [k(i, 0), k(i+1, 0)]).collect()),
+            ("right".to_string(), (0..j_max).map(|j| [k(i_max, j), k(i_max, j+1)]).collect()),
+            ("up".to_string(), (0..i_max).map(|i| [k(i+1, j_max), k(i, j_max)]).collect()),
+            ("left".to_string(), (0..j_max).map(|j| [k(0, j+1), k(0, j)]).collect()),
+        ])
+    }
+    
     fn compute_triangle_areas(vertices: &Array2<f64>, triangles: &Array2<usize>) -> Array1<f64> {
         triangles
             .outer_iter()
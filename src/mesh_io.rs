@@ -0,0 +1,611 @@
+use ndarray::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use crate::mesh::TriangleMesh;
+
+#[derive(Debug)]
+pub enum MeshIoError {
+    Io(io::Error),
+    // malformed file content
+    Parse(String),
+    // valid content this reader does not handle, e.g. binary MSH files
+    Unsupported(String),
+    // the file does not describe a usable triangle mesh
+    InvalidMesh(String),
+}
+
+impl fmt::Display for MeshIoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshIoError::Io(e) => write!(f, "Could not access mesh file: {}", e),
+            MeshIoError::Parse(message) => write!(f, "Could not parse mesh file: {}", message),
+            MeshIoError::Unsupported(message) => write!(f, "Unsupported mesh file: {}", message),
+            MeshIoError::InvalidMesh(message) => write!(f, "Invalid mesh: {}", message),
+        }
+    }
+}
+
+impl Error for MeshIoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MeshIoError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MeshIoError {
+    fn from(e: io::Error) -> MeshIoError {
+        MeshIoError::Io(e)
+    }
+}
+
+type Line<'a> = Vec<&'a str>;
+
+fn parse<T: FromStr>(token: Option<&&str>, what: &str) -> Result<T, MeshIoError> {
+    let token = token.ok_or_else(|| MeshIoError::Parse(format!("missing {}", what)))?;
+    token.parse().map_err(|_| MeshIoError::Parse(format!("invalid {} '{}'", what, token)))
+}
+
+fn next_line<'a, 'b>(lines: &mut impl Iterator<Item = &'b Line<'a>>, what: &str) -> Result<&'b Line<'a>, MeshIoError>
+where 'a: 'b {
+    lines.next().ok_or_else(|| MeshIoError::Parse(format!("file ends before {}", what)))
+}
+
+fn node_index(node_indices: &HashMap<usize, usize>, node: usize) -> Result<usize, MeshIoError> {
+    node_indices.get(&node).copied().ok_or_else(|| MeshIoError::Parse(format!("element refers to unknown node {}", node)))
+}
+
+// Gmsh element types read as triangles and boundary lines, with their number of nodes. Quadratic
+// elements only keep their corner nodes.
+fn msh_element(element_type: usize) -> Option<(usize, usize)> {
+    // (dimension, number of nodes)
+    match element_type {
+        1 => Some((1, 2)),
+        8 => Some((1, 3)),
+        2 => Some((2, 3)),
+        9 => Some((2, 6)),
+        _ => None,
+    }
+}
+
+fn assemble(vertices: Vec<[f64; 2]>,
+    triangles: Vec<[usize; 3]>,
+    tagged_edges: Vec<(String, [usize; 2])>) -> Result<TriangleMesh, MeshIoError> {
+    // builds the mesh from the triangles, dropping vertices of no triangle (e.g. geometry points or
+    // midside nodes) and checking that tagged edges are triangle edges
+    if triangles.is_empty() {
+        return Err(MeshIoError::InvalidMesh("no triangles found".to_string()));
+    }
+
+    let mut new_index = vec![usize::MAX; vertices.len()];
+    let mut used_vertices = Vec::<[f64; 2]>::new();
+    let triangles: Vec<[usize; 3]> = triangles.iter()
+        .map(|tri| tri.map(|v| {
+            if new_index[v] == usize::MAX {
+                new_index[v] = used_vertices.len();
+                used_vertices.push(vertices[v]);
+            }
+            new_index[v]
+        }))
+        .collect();
+
+    for (triangle_idx, tri) in triangles.iter().enumerate() {
+        let [a, b, c] = tri.map(|v| used_vertices[v]);
+        let area = 0.5 * ((b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1]));
+        if area == 0.0 || !area.is_finite() {
+            return Err(MeshIoError::InvalidMesh(format!("triangle {} has zero area", triangle_idx)));
+        }
+    }
+
+    let triangle_edges: HashSet<[usize; 2]> = triangles.iter()
+        .flat_map(|tri| (0..3).map(move |i| [tri[i].min(tri[(i + 1) % 3]), tri[i].max(tri[(i + 1) % 3])]))
+        .collect();
+    let mut edge_tags = HashMap::<String, Vec<[usize; 2]>>::new();
+    for (tag, [a, b]) in tagged_edges {
+        let edge = [new_index[a], new_index[b]];
+        if !triangle_edges.contains(&[edge[0].min(edge[1]), edge[0].max(edge[1])]) {
+            return Err(MeshIoError::InvalidMesh(format!("edge tagged '{}' is not a triangle edge", tag)));
+        }
+        edge_tags.entry(tag).or_default().push(edge);
+    }
+
+    let vertices = Array2::from_shape_vec((used_vertices.len(), 2), used_vertices.into_iter().flatten().collect())
+        .unwrap();
+    let triangles = Array2::from_shape_vec((triangles.len(), 3), triangles.into_iter().flatten().collect())
+        .unwrap();
+    TriangleMesh::from_triangles(vertices, triangles, edge_tags).map_err(|e| MeshIoError::InvalidMesh(e.to_string()))
+}
+
+fn msh_sections(content: &str) -> Result<HashMap<String, Vec<Line<'_>>>, MeshIoError> {
+    // the tokenized lines between $Name and $EndName of every section
+    let mut sections = HashMap::<String, Vec<Line>>::new();
+    let mut current: Option<(&str, Vec<Line>)> = None;
+    for line in content.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        match (line.strip_prefix('$'), current.take()) {
+            (Some(name), Some((open, lines))) if name.strip_prefix("End") == Some(open) => {
+                sections.insert(open.to_string(), lines);
+            },
+            (Some(name), None) => current = Some((name, Vec::new())),
+            (_, Some((open, mut lines))) => {
+                lines.push(line.split_whitespace().collect());
+                current = Some((open, lines));
+            },
+            (None, None) => return Err(MeshIoError::Parse(format!("content outside of a section: '{}'", line))),
+        }
+    }
+    match current {
+        Some((open, _)) => Err(MeshIoError::Parse(format!("section ${} is not closed", open))),
+        None => Ok(sections),
+    }
+}
+
+fn triangle_lines(content: &str) -> Vec<Line<'_>> {
+    // tokenized lines of a Triangle file without comments and blank lines
+    content.lines()
+        .map(|line| line.split('#').next().unwrap_or("").split_whitespace().collect::<Line>())
+        .filter(|tokens| !tokens.is_empty())
+        .collect()
+}
+
+impl TriangleMesh {
+    // Reads an ASCII Gmsh MSH file of version 2, 4.0 or 4.1. Triangles make up the mesh, and line
+    // elements in a physical group tag their edges with the group name (or "physical_<tag>").
+    pub fn read_msh<P: AsRef<Path>>(path: P) -> Result<TriangleMesh, MeshIoError> {
+        let bytes = fs::read(path)?;
+        let content = String::from_utf8_lossy(&bytes);
+        let sections = msh_sections(&content)?;
+
+        let format = sections.get("MeshFormat")
+            .and_then(|lines| lines.first())
+            .ok_or_else(|| MeshIoError::Parse("missing $MeshFormat".to_string()))?;
+        let version: f64 = parse(format.first(), "MSH version")?;
+        let file_type: usize = parse(format.get(1), "MSH file type")?;
+        if file_type != 0 {
+            return Err(MeshIoError::Unsupported("binary MSH files, save the mesh as ASCII".to_string()));
+        }
+
+        // names of the physical groups of dimension 1, i.e. boundary curves
+        let mut physical_names = HashMap::<usize, String>::new();
+        for line in sections.get("PhysicalNames").map_or(&[][..], |lines| &lines[1.min(lines.len())..]) {
+            let dim: usize = parse(line.first(), "physical group dimension")?;
+            let tag: usize = parse(line.get(1), "physical group tag")?;
+            if dim == 1 {
+                physical_names.insert(tag, line[2..].join(" ").trim_matches('"').to_string());
+            }
+        }
+        let tag_name = |physical: usize| physical_names.get(&physical).cloned()
+            .unwrap_or_else(|| format!("physical_{}", physical));
+
+        let nodes = sections.get("Nodes").ok_or_else(|| MeshIoError::Parse("missing $Nodes".to_string()))?;
+        let elements = sections.get("Elements").ok_or_else(|| MeshIoError::Parse("missing $Elements".to_string()))?;
+        let mut vertices = Vec::<[f64; 2]>::new();
+        let mut node_indices = HashMap::<usize, usize>::new();
+        let mut triangles = Vec::<[usize; 3]>::new();
+        let mut tagged_edges = Vec::<(String, [usize; 2])>::new();
+
+        if (2.0..3.0).contains(&version) {
+            for line in nodes.iter().skip(1) {
+                node_indices.insert(parse(line.first(), "node tag")?, vertices.len());
+                vertices.push([parse(line.get(1), "node x")?, parse(line.get(2), "node y")?]);
+            }
+
+            // id type num_tags physical elementary ... nodes
+            for line in elements.iter().skip(1) {
+                let element_type: usize = parse(line.get(1), "element type")?;
+                let Some((dim, num_nodes)) = msh_element(element_type) else { continue };
+                let num_tags: usize = parse(line.get(2), "element tag count")?;
+                let element_nodes = line.get(3 + num_tags..3 + num_tags + num_nodes)
+                    .ok_or_else(|| MeshIoError::Parse("element with too few nodes".to_string()))?
+                    .iter()
+                    .map(|token| node_index(&node_indices, parse(Some(token), "element node")?))
+                    .collect::<Result<Vec<usize>, MeshIoError>>()?;
+                if dim == 2 {
+                    triangles.push([element_nodes[0], element_nodes[1], element_nodes[2]]);
+                } else if num_tags > 0 {
+                    let physical: usize = parse(line.get(3), "physical tag")?;
+                    if physical > 0 {
+                        tagged_edges.push((tag_name(physical), [element_nodes[0], element_nodes[1]]));
+                    }
+                }
+            }
+        } else if (4.0..5.0).contains(&version) {
+            // version 4.0 differs in the node blocks and the element block headers
+            let legacy = version < 4.1;
+
+            // physical groups of the curve entities, whose lines are the same in 4.0 and 4.1
+            let mut curve_physicals = HashMap::<usize, Vec<usize>>::new();
+            if let Some(entities) = sections.get("Entities") {
+                let mut lines = entities.iter();
+                let counts = next_line(&mut lines, "entity counts")?;
+                let num_points: usize = parse(counts.first(), "point count")?;
+                let num_curves: usize = parse(counts.get(1), "curve count")?;
+                for line in lines.skip(num_points).take(num_curves) {
+                    let num_physicals: usize = parse(line.get(7), "curve physical count")?;
+                    let physicals = (0..num_physicals)
+                        .map(|i| parse::<i64>(line.get(8 + i), "curve physical tag").map(|tag| tag.unsigned_abs() as usize))
+                        .collect::<Result<Vec<usize>, MeshIoError>>()?;
+                    curve_physicals.insert(parse(line.first(), "curve tag")?, physicals);
+                }
+            }
+
+            // blocks of node tags followed by their coordinates, or of lines with both in 4.0
+            let mut lines = nodes.iter().skip(1);
+            while let Some(header) = lines.next() {
+                let parametric: usize = parse(header.get(2), "node block parametric flag")?;
+                let num_nodes: usize = parse(header.get(3), "node block size")?;
+                if parametric != 0 && num_nodes > 0 {
+                    return Err(MeshIoError::Unsupported("parametric node coordinates".to_string()));
+                }
+                if legacy {
+                    for _ in 0..num_nodes {
+                        let line = next_line(&mut lines, "nodes")?;
+                        node_indices.insert(parse(line.first(), "node tag")?, vertices.len());
+                        vertices.push([parse(line.get(1), "node x")?, parse(line.get(2), "node y")?]);
+                    }
+                    continue;
+                }
+                let tags = (0..num_nodes)
+                    .map(|_| parse(next_line(&mut lines, "node tags")?.first(), "node tag"))
+                    .collect::<Result<Vec<usize>, MeshIoError>>()?;
+                for tag in tags {
+                    let line = next_line(&mut lines, "node coordinates")?;
+                    node_indices.insert(tag, vertices.len());
+                    vertices.push([parse(line.first(), "node x")?, parse(line.get(1), "node y")?]);
+                }
+            }
+
+            let mut lines = elements.iter().skip(1);
+            while let Some(header) = lines.next() {
+                // the entity tag comes before its dimension in 4.0 and after it in 4.1
+                let entity_tag: usize = parse(header.get(if legacy { 0 } else { 1 }), "element block entity")?;
+                let element_type: usize = parse(header.get(2), "element type")?;
+                let num_elements: usize = parse(header.get(3), "element block size")?;
+                let block: Vec<&Line> = (0..num_elements)
+                    .map(|_| next_line(&mut lines, "elements"))
+                    .collect::<Result<Vec<&Line>, MeshIoError>>()?;
+                let Some((dim, num_nodes)) = msh_element(element_type) else { continue };
+                for line in block {
+                    let element_nodes = line.get(1..1 + num_nodes)
+                        .ok_or_else(|| MeshIoError::Parse("element with too few nodes".to_string()))?
+                        .iter()
+                        .map(|token| node_index(&node_indices, parse(Some(token), "element node")?))
+                        .collect::<Result<Vec<usize>, MeshIoError>>()?;
+                    if dim == 2 {
+                        triangles.push([element_nodes[0], element_nodes[1], element_nodes[2]]);
+                    } else {
+                        for &physical in curve_physicals.get(&entity_tag).map_or(&[][..], |p| p.as_slice()) {
+                            tagged_edges.push((tag_name(physical), [element_nodes[0], element_nodes[1]]));
+                        }
+                    }
+                }
+            }
+        } else {
+            return Err(MeshIoError::Unsupported(format!("MSH version {}, only 2, 4.0 and 4.1 are read", version)));
+        }
+
+        assemble(vertices, triangles, tagged_edges)
+    }
+
+    // Reads the .node and .ele files of Triangle (https://www.cs.cmu.edu/~quake/triangle.html) given
+    // the path of either one. Segments of a .poly file of the same name, such as the one written by
+    // `triangle -p`, tag their edges by boundary marker as "marker_<m>".
+    pub fn read_triangle<P: AsRef<Path>>(path: P) -> Result<TriangleMesh, MeshIoError> {
+        let path = path.as_ref();
+        let read_lines = |extension: &str| -> Result<Option<String>, MeshIoError> {
+            match fs::read_to_string(path.with_extension(extension)) {
+                Ok(content) => Ok(Some(content)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound && extension == "poly" => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        };
+        let node_content = read_lines("node")?.unwrap_or_default();
+        let ele_content = read_lines("ele")?.unwrap_or_default();
+        let poly_content = read_lines("poly")?;
+
+        let node_lines = triangle_lines(&node_content);
+        let mut lines = node_lines.iter();
+        let header = next_line(&mut lines, ".node header")?;
+        let num_nodes: usize = parse(header.first(), "node count")?;
+        let dim: usize = parse(header.get(1), "node dimension")?;
+        if dim != 2 {
+            return Err(MeshIoError::Unsupported(format!("{}-dimensional nodes", dim)));
+        }
+        let mut vertices = Vec::<[f64; 2]>::with_capacity(num_nodes);
+        let mut node_indices = HashMap::<usize, usize>::new();
+        for _ in 0..num_nodes {
+            let line = next_line(&mut lines, "nodes")?;
+            node_indices.insert(parse(line.first(), "node number")?, vertices.len());
+            vertices.push([parse(line.get(1), "node x")?, parse(line.get(2), "node y")?]);
+        }
+
+        let ele_lines = triangle_lines(&ele_content);
+        let mut lines = ele_lines.iter();
+        let header = next_line(&mut lines, ".ele header")?;
+        let num_triangles: usize = parse(header.first(), "triangle count")?;
+        let mut triangles = Vec::<[usize; 3]>::with_capacity(num_triangles);
+        for _ in 0..num_triangles {
+            let line = next_line(&mut lines, "triangles")?;
+            let corner = |i: usize| node_index(&node_indices, parse(line.get(i), "triangle node")?);
+            triangles.push([corner(1)?, corner(2)?, corner(3)?]);
+        }
+
+        let mut tagged_edges = Vec::<(String, [usize; 2])>::new();
+        if let Some(poly_content) = poly_content {
+            let poly_lines = triangle_lines(&poly_content);
+            let mut lines = poly_lines.iter();
+            // vertices listed in the .poly file keep their numbers in the .node file
+            let header = next_line(&mut lines, ".poly header")?;
+            let num_poly_nodes: usize = parse(header.first(), "poly node count")?;
+            let mut lines = lines.skip(num_poly_nodes);
+            let header = next_line(&mut lines, "segment header")?;
+            let num_segments: usize = parse(header.first(), "segment count")?;
+            let has_markers = parse::<usize>(header.get(1), "segment marker flag")? != 0;
+            for _ in 0..num_segments {
+                let line = next_line(&mut lines, "segments")?;
+                let a = node_index(&node_indices, parse(line.get(1), "segment node")?)?;
+                let b = node_index(&node_indices, parse(line.get(2), "segment node")?)?;
+                let marker: i64 = if has_markers { parse(line.get(3), "segment marker")? } else { 1 };
+                tagged_edges.push((format!("marker_{}", marker), [a, b]));
+            }
+        }
+
+        assemble(vertices, triangles, tagged_edges)
+    }
+
+    // Writes the mesh as a Wavefront OBJ file at z = 0, with every edge tag as a group of lines
+    pub fn write_obj<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = BufWriter::new(fs::File::create(path)?);
+        for vertex in self.vertices.outer_iter() {
+            writeln!(file, "v {} {} 0", vertex[0], vertex[1])?;
+        }
+        writeln!(file, "g triangles")?;
+        for tri in self.triangles.outer_iter() {
+            writeln!(file, "f {} {} {}", tri[0] + 1, tri[1] + 1, tri[2] + 1)?;
+        }
+        let mut tags: Vec<&String> = self.edge_tags.keys().collect();
+        tags.sort();
+        for tag in tags {
+            writeln!(file, "g {}", tag.replace(char::is_whitespace, "_"))?;
+            for edge in self.edge_tags[tag].iter() {
+                writeln!(file, "l {} {}", edge[0] + 1, edge[1] + 1)?;
+            }
+        }
+        file.flush()
+    }
+
+    // Writes the mesh as a legacy ASCII VTK unstructured grid at z = 0, with the triangle areas as
+    // cell data, e.g. for ParaView
+    pub fn write_vtk<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = BufWriter::new(fs::File::create(path)?);
+        writeln!(file, "# vtk DataFile Version 3.0")?;
+        writeln!(file, "TriangleMesh")?;
+        writeln!(file, "ASCII")?;
+        writeln!(file, "DATASET UNSTRUCTURED_GRID")?;
+        writeln!(file, "POINTS {} double", self.vertices.nrows())?;
+        for vertex in self.vertices.outer_iter() {
+            writeln!(file, "{} {} 0", vertex[0], vertex[1])?;
+        }
+        let num_triangles = self.triangles.nrows();
+        writeln!(file, "CELLS {} {}", num_triangles, 4 * num_triangles)?;
+        for tri in self.triangles.outer_iter() {
+            writeln!(file, "3 {} {} {}", tri[0], tri[1], tri[2])?;
+        }
+        // 5 is VTK_TRIANGLE
+        writeln!(file, "CELL_TYPES {}", num_triangles)?;
+        for _ in 0..num_triangles {
+            writeln!(file, "5")?;
+        }
+        writeln!(file, "CELL_DATA {}", num_triangles)?;
+        writeln!(file, "SCALARS area double 1")?;
+        writeln!(file, "LOOKUP_TABLE default")?;
+        for area in self.areas.iter() {
+            writeln!(file, "{}", area)?;
+        }
+        file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // unit square of the triangles (1, 2, 3) and (1, 3, 4) with its bottom edge in the physical group 7
+    const MSH_2: &str = "$MeshFormat
+2.2 0 8
+$EndMeshFormat
+$PhysicalNames
+1
+1 7 \"bottom\"
+$EndPhysicalNames
+$Nodes
+4
+1 0 0 0
+2 1 0 0
+3 1 1 0
+4 0 1 0
+$EndNodes
+$Elements
+4
+1 15 2 0 1 1
+2 1 2 7 5 1 2
+3 2 2 0 1 1 2 3
+4 2 2 0 1 1 3 4
+$EndElements
+";
+
+    const MSH_40: &str = "$MeshFormat
+4 0 8
+$EndMeshFormat
+$PhysicalNames
+1
+1 7 \"bottom\"
+$EndPhysicalNames
+$Entities
+4 1 1 0
+1 0 0 0 0 0 0 0
+2 1 0 0 1 0 0 0
+3 1 1 0 1 1 0 0
+4 0 1 0 0 1 0 0
+5 0 0 0 1 0 0 1 7 2 1 -2
+1 0 0 0 1 1 0 0 0
+$EndEntities
+$Nodes
+2 4
+5 1 0 2
+1 0 0 0
+2 1 0 0
+1 2 0 2
+3 1 1 0
+4 0 1 0
+$EndNodes
+$Elements
+2 3
+5 1 1 1
+1 1 2
+1 2 2 2
+2 1 2 3
+3 1 3 4
+$EndElements
+";
+
+    const MSH_41: &str = "$MeshFormat
+4.1 0 8
+$EndMeshFormat
+$PhysicalNames
+1
+1 7 \"bottom\"
+$EndPhysicalNames
+$Entities
+4 1 1 0
+1 0 0 0 0
+2 1 0 0 0
+3 1 1 0 0
+4 0 1 0 0
+5 0 0 0 1 0 0 1 7 2 1 -2
+1 0 0 0 1 1 0 0 0
+$EndEntities
+$Nodes
+2 4 1 4
+1 5 0 2
+1
+2
+0 0 0
+1 0 0
+2 1 0 2
+3
+4
+1 1 0
+0 1 0
+$EndNodes
+$Elements
+2 3 1 3
+1 5 1 1
+1 1 2
+2 1 2 2
+2 1 2 3
+3 1 3 4
+$EndElements
+";
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("simulator_mesh_io_{}_{}", std::process::id(), name))
+    }
+
+    fn read_msh_str(name: &str, content: &str) -> Result<TriangleMesh, MeshIoError> {
+        let path = temp_path(name).with_extension("msh");
+        fs::write(&path, content).unwrap();
+        let mesh = TriangleMesh::read_msh(&path);
+        fs::remove_file(&path).unwrap();
+        mesh
+    }
+
+    #[test]
+    fn msh_versions_read_the_same_mesh() {
+        for (name, content) in [("v2", MSH_2), ("v40", MSH_40), ("v41", MSH_41)] {
+            let mesh = read_msh_str(name, content).unwrap();
+            assert_eq!(mesh.vertices, array![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]], "{}", name);
+            assert_eq!(mesh.triangles, array![[0, 1, 2], [0, 2, 3]], "{}", name);
+            assert_eq!(mesh.edge_tags.len(), 1, "{}", name);
+            assert_eq!(mesh.edge_tags["bottom"], vec![[0, 1]], "{}", name);
+        }
+    }
+
+    #[test]
+    fn invalid_msh_files_are_rejected() {
+        let binary = MSH_41.replace("4.1 0 8", "4.1 1 8");
+        assert!(matches!(read_msh_str("binary", &binary), Err(MeshIoError::Unsupported(_))));
+        let unknown_node = MSH_2.replace("3 2 2 0 1 1 2 3", "3 2 2 0 1 1 2 9");
+        assert!(matches!(read_msh_str("unknown_node", &unknown_node), Err(MeshIoError::Parse(_))));
+        let zero_area = MSH_2.replace("3 1 1 0\n", "3 2 0 0\n");
+        assert!(matches!(read_msh_str("zero_area", &zero_area), Err(MeshIoError::InvalidMesh(_))));
+        let non_edge = MSH_2.replace("2 1 2 7 5 1 2", "2 1 2 7 5 2 4");
+        assert!(matches!(read_msh_str("non_edge", &non_edge), Err(MeshIoError::InvalidMesh(_))));
+        let version = MSH_2.replace("2.2 0 8", "3.0 0 8");
+        assert!(matches!(read_msh_str("version", &version), Err(MeshIoError::Unsupported(_))));
+    }
+
+    #[test]
+    fn triangle_files_tag_poly_segments_by_marker() {
+        // unit square around a center node, as written by `triangle -p` with a comment
+        let base = temp_path("square");
+        fs::write(base.with_extension("node"), "# square\n5 2 0 1\n1 0 0 1\n2 1 0 1\n3 1 1 1\n4 0 1 1\n5 0.5 0.5 0\n").unwrap();
+        fs::write(base.with_extension("ele"), "4 3 0\n1 1 2 5\n2 2 3 5\n3 3 4 5\n4 4 1 5\n").unwrap();
+        let mesh = TriangleMesh::read_triangle(base.with_extension("ele")).unwrap();
+        assert_eq!(mesh.vertices.nrows(), 5);
+        assert_eq!(mesh.triangles.nrows(), 4);
+        assert!(mesh.edge_tags.is_empty());
+
+        fs::write(base.with_extension("poly"), "0 2 0 1\n4 1\n1 1 2 2\n2 2 3 3\n3 3 4 1\n4 4 1 1\n0\n").unwrap();
+        let mesh = TriangleMesh::read_triangle(base.with_extension("node")).unwrap();
+        // vertices are numbered in the order the triangles use them, so the center is vertex 2
+        assert_eq!(mesh.vertices.row(2), array![0.5, 0.5]);
+        assert_eq!(mesh.edge_tags["marker_1"], vec![[3, 4], [4, 0]]);
+        assert_eq!(mesh.edge_tags["marker_2"], vec![[0, 1]]);
+        assert_eq!(mesh.edge_tags["marker_3"], vec![[1, 3]]);
+
+        fs::write(base.with_extension("ele"), "4 3 0\n1 1 2 5\n2 2 3 5\n3 3 4 5\n4 4 1 6\n").unwrap();
+        assert!(matches!(TriangleMesh::read_triangle(&base), Err(MeshIoError::Parse(_))));
+        for extension in ["node", "ele", "poly"] {
+            fs::remove_file(base.with_extension(extension)).unwrap();
+        }
+    }
+
+    #[test]
+    fn obj_and_vtk_files_hold_the_mesh() {
+        let mesh = TriangleMesh::new_beam(2.0, 1.0, (2, 1));
+        let num_vertices = mesh.vertices.nrows();
+        let num_triangles = mesh.triangles.nrows();
+        let num_tagged_edges: usize = mesh.edge_tags.values().map(Vec::len).sum();
+
+        let path = temp_path("mesh.obj");
+        mesh.write_obj(&path).unwrap();
+        let obj = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let count = |prefix: &str| obj.lines().filter(|line| line.starts_with(prefix)).count();
+        assert_eq!(count("v "), num_vertices);
+        assert_eq!(count("f "), num_triangles);
+        assert_eq!(count("l "), num_tagged_edges);
+        let first_face: Vec<usize> = obj.lines().find(|line| line.starts_with("f ")).unwrap()
+            .split_whitespace().skip(1).map(|index| index.parse().unwrap()).collect();
+        assert_eq!(first_face, mesh.triangles.row(0).iter().map(|v| v + 1).collect::<Vec<usize>>());
+
+        let path = temp_path("mesh.vtk");
+        mesh.write_vtk(&path).unwrap();
+        let vtk = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = vtk.lines().collect();
+        let section = |header: &str| lines.iter().position(|line| line.starts_with(header)).unwrap();
+        assert_eq!(lines[section("POINTS")], format!("POINTS {} double", num_vertices));
+        assert_eq!(lines[section("CELLS")], format!("CELLS {} {}", num_triangles, 4 * num_triangles));
+        assert_eq!(lines[section("CELL_TYPES") + 1..section("CELL_DATA")], vec!["5"; num_triangles]);
+        let areas: Vec<f64> = lines[section("LOOKUP_TABLE") + 1..].iter().map(|line| line.parse().unwrap()).collect();
+        assert_eq!(areas, mesh.areas.to_vec());
+    }
+}
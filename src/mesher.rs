@@ -0,0 +1,781 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+// Quality constraints of the triangles made by the mesher
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MeshQuality {
+    pub max_area: Option<f64>, // largest triangle area, unbounded if None
+    pub min_angle: f64,        // smallest triangle angle in degrees, at most MAX_MIN_ANGLE
+    pub max_vertices: usize,   // refinement stops beyond this many vertices, see PolygonMesh
+}
+
+// Ruppert's refinement provably terminates for minimum angles up to about 20.7 degrees, and in
+// practice up to about 33 degrees
+pub const MAX_MIN_ANGLE: f64 = 33.0;
+
+impl Default for MeshQuality {
+    fn default() -> MeshQuality {
+        MeshQuality { max_area: None, min_angle: 20.0, max_vertices: 100_000 }
+    }
+}
+
+// Invalid mesher input. Polygon 0 is the outer boundary and polygon i > 0 is hole i - 1.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeshError {
+    // polygon with fewer than 3 vertices
+    TooFewVertices { polygon: usize },
+    // polygon with a NaN or infinite coordinate
+    NonFiniteVertex { polygon: usize },
+    // two polygon edges that cross or touch
+    IntersectingSegments { polygons: [usize; 2] },
+    // min_angle outside of [0, MAX_MIN_ANGLE] or a non-positive max_area
+    InvalidQuality,
+    // a vertex could not be inserted, e.g. because it lies on another polygon edge
+    DegenerateInput,
+    // the polygons do not enclose any area
+    EmptyDomain,
+    // hole that is not inside the outer polygon
+    HoleOutsideOuter { polygon: usize },
+    // hole inside of another hole
+    NestedHoles { polygons: [usize; 2] },
+    // refinement that cannot split a bad triangle or the segments it encroaches
+    RefinementStalled,
+    // refinement stopped at max_vertices before reaching the quality
+    VertexLimitReached { max_vertices: usize },
+    // triangle with zero or negative area given to TriangleMesh::from_triangles
+    DegenerateTriangle { triangle: usize },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshError::TooFewVertices { polygon } =>
+                write!(f, "Polygon {} has fewer than 3 vertices", polygon),
+            MeshError::NonFiniteVertex { polygon } =>
+                write!(f, "Polygon {} has a non-finite vertex", polygon),
+            MeshError::IntersectingSegments { polygons } =>
+                write!(f, "Edges of polygons {} and {} intersect", polygons[0], polygons[1]),
+            MeshError::InvalidQuality =>
+                write!(f, "Mesh quality needs 0 <= min_angle <= {} and max_area > 0", MAX_MIN_ANGLE),
+            MeshError::DegenerateInput =>
+                write!(f, "Polygon vertex could not be inserted into the triangulation"),
+            MeshError::EmptyDomain =>
+                write!(f, "Polygons do not enclose any area"),
+            MeshError::HoleOutsideOuter { polygon } =>
+                write!(f, "Hole polygon {} is not inside the outer polygon", polygon),
+            MeshError::NestedHoles { polygons } =>
+                write!(f, "Hole polygon {} lies inside hole polygon {}", polygons[0], polygons[1]),
+            MeshError::RefinementStalled =>
+                write!(f, "Mesh refinement stalled, a bad triangle could not be split"),
+            MeshError::VertexLimitReached { max_vertices } =>
+                write!(f, "Mesh refinement stopped at the limit of {} vertices", max_vertices),
+            MeshError::DegenerateTriangle { triangle } =>
+                write!(f, "Triangle {} has zero area", triangle),
+        }
+    }
+}
+
+impl Error for MeshError {}
+
+// Triangulation of a polygon with holes
+pub struct PolygonMesh {
+    pub vertices: Vec<[f64; 2]>,
+    pub triangles: Vec<[usize; 3]>,          // counter-clockwise
+    pub segments: HashMap<[usize; 2], usize>, // boundary edges (a, b) with a < b and their polygon
+    pub reached_vertex_limit: bool,          // refinement stopped at max_vertices, some triangles are still bad
+}
+
+// Makes a quality constrained Delaunay triangulation of the area inside the outer polygon and outside
+// of all holes, with Ruppert's Delaunay refinement. The polygons may be oriented either way.
+pub fn triangulate_polygon(outer: &[[f64; 2]],
+    holes: &[Vec<[f64; 2]>],
+    quality: &MeshQuality) -> Result<PolygonMesh, MeshError> {
+    let polygons: Vec<&[[f64; 2]]> = std::iter::once(outer)
+        .chain(holes.iter().map(|hole| hole.as_slice()))
+        .collect();
+    validate_input(&polygons, quality)?;
+
+    let mut triangulation = Triangulation::new(&polygons);
+
+    // insert the polygon vertices and their edges as segments
+    let mut segments = Vec::<([usize; 2], usize)>::new();
+    for (polygon_idx, polygon) in polygons.iter().enumerate() {
+        let indices = polygon.iter()
+            .map(|&p| triangulation.insert_input(p).ok_or(MeshError::DegenerateInput))
+            .collect::<Result<Vec<usize>, MeshError>>()?;
+        for i in 0..indices.len() {
+            let (a, b) = (indices[i], indices[(i + 1) % indices.len()]);
+            if a != b {
+                segments.push(([a, b], polygon_idx));
+            }
+        }
+    }
+    triangulation.set_corner_angles(&segments);
+
+    triangulation.recover_segments(segments, quality.max_vertices)?;
+    triangulation.classify();
+    let reached_vertex_limit = triangulation.refine(quality)?;
+
+    let mut mesh = triangulation.into_polygon_mesh();
+    mesh.reached_vertex_limit = reached_vertex_limit;
+    if mesh.triangles.is_empty() {
+        return Err(MeshError::EmptyDomain);
+    }
+    Ok(mesh)
+}
+
+fn validate_input(polygons: &[&[[f64; 2]]], quality: &MeshQuality) -> Result<(), MeshError> {
+    if !(0.0..=MAX_MIN_ANGLE).contains(&quality.min_angle) || quality.max_area.is_some_and(|area| area.is_nan() || area <= 0.0) {
+        return Err(MeshError::InvalidQuality);
+    }
+    for (polygon_idx, polygon) in polygons.iter().enumerate() {
+        if polygon.len() < 3 {
+            return Err(MeshError::TooFewVertices { polygon: polygon_idx });
+        }
+        if polygon.iter().any(|p| !p[0].is_finite() || !p[1].is_finite()) {
+            return Err(MeshError::NonFiniteVertex { polygon: polygon_idx });
+        }
+    }
+
+    // every pair of polygon edges, except neighbors sharing a vertex, must be disjoint
+    let edges: Vec<(usize, usize, [f64; 2], [f64; 2])> = polygons.iter()
+        .enumerate()
+        .flat_map(|(polygon_idx, polygon)| (0..polygon.len())
+            .map(move |i| (polygon_idx, i, polygon[i], polygon[(i + 1) % polygon.len()])))
+        .collect();
+    for (k, &(poly_a, i, p0, p1)) in edges.iter().enumerate() {
+        for &(poly_b, j, q0, q1) in edges[k + 1..].iter() {
+            let n = polygons[poly_a].len();
+            let neighbors = poly_a == poly_b && (j == (i + 1) % n || i == (j + 1) % n);
+            if !neighbors && segments_intersect(p0, p1, q0, q1) {
+                return Err(MeshError::IntersectingSegments { polygons: [poly_a, poly_b] });
+            }
+        }
+    }
+
+    // As no edges intersect, a polygon lies inside another one if any of its vertices does. Every
+    // hole has to be inside the outer polygon and outside of the other holes.
+    for (polygon_idx, polygon) in polygons.iter().enumerate().skip(1) {
+        if !point_in_polygon(polygon[0], polygons[0]) {
+            return Err(MeshError::HoleOutsideOuter { polygon: polygon_idx });
+        }
+        if let Some(other_idx) = (1..polygons.len()).find(|&j| j != polygon_idx && point_in_polygon(polygon[0], polygons[j])) {
+            return Err(MeshError::NestedHoles { polygons: [polygon_idx, other_idx] });
+        }
+    }
+    Ok(())
+}
+
+fn point_in_polygon(p: [f64; 2], polygon: &[[f64; 2]]) -> bool {
+    // even-odd rule, counting the polygon edges crossed by a ray from p in +x direction
+    let mut inside = false;
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+        if (a[1] > p[1]) != (b[1] > p[1]) {
+            let x = a[0] + (p[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0]);
+            if x > p[0] {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+fn orient(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> f64 {
+    // twice the signed area of the triangle abc, positive if it is counter-clockwise
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+fn in_circle(a: [f64; 2], b: [f64; 2], c: [f64; 2], d: [f64; 2]) -> f64 {
+    // positive if d lies inside the circumcircle of the counter-clockwise triangle abc
+    let (adx, ady) = (a[0] - d[0], a[1] - d[1]);
+    let (bdx, bdy) = (b[0] - d[0], b[1] - d[1]);
+    let (cdx, cdy) = (c[0] - d[0], c[1] - d[1]);
+    (adx * adx + ady * ady) * (bdx * cdy - cdx * bdy)
+        - (bdx * bdx + bdy * bdy) * (adx * cdy - cdx * ady)
+        + (cdx * cdx + cdy * cdy) * (adx * bdy - bdx * ady)
+}
+
+fn circumcenter(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> [f64; 2] {
+    let (bx, by) = (b[0] - a[0], b[1] - a[1]);
+    let (cx, cy) = (c[0] - a[0], c[1] - a[1]);
+    let d = 2.0 * (bx * cy - by * cx);
+    let b2 = bx * bx + by * by;
+    let c2 = cx * cx + cy * cy;
+    [a[0] + (cy * b2 - by * c2) / d, a[1] + (bx * c2 - cx * b2) / d]
+}
+
+fn distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt()
+}
+
+fn segments_intersect(p0: [f64; 2], p1: [f64; 2], q0: [f64; 2], q1: [f64; 2]) -> bool {
+    // true if the closed segments p0p1 and q0q1 share a point
+    let on_segment = |a: [f64; 2], b: [f64; 2], p: [f64; 2]| {
+        p[0] >= a[0].min(b[0]) && p[0] <= a[0].max(b[0]) && p[1] >= a[1].min(b[1]) && p[1] <= a[1].max(b[1])
+    };
+    let (o1, o2) = (orient(p0, p1, q0), orient(p0, p1, q1));
+    let (o3, o4) = (orient(q0, q1, p0), orient(q0, q1, p1));
+    if o1 * o2 < 0.0 && o3 * o4 < 0.0 {
+        return true;
+    }
+    (o1 == 0.0 && on_segment(p0, p1, q0)) || (o2 == 0.0 && on_segment(p0, p1, q1))
+        || (o3 == 0.0 && on_segment(q0, q1, p0)) || (o4 == 0.0 && on_segment(q0, q1, p1))
+}
+
+// whether p lies strictly inside the diametral circle of the segment ab
+fn encroaches(p: [f64; 2], a: [f64; 2], b: [f64; 2]) -> bool {
+    (a[0] - p[0]) * (b[0] - p[0]) + (a[1] - p[1]) * (b[1] - p[1]) < 0.0
+}
+
+fn segment_key(a: usize, b: usize) -> [usize; 2] {
+    [a.min(b), a.max(b)]
+}
+
+#[derive(Clone, Copy)]
+struct Triangle {
+    v: [usize; 3],         // counter-clockwise vertices
+    n: [Option<usize>; 3], // neighbor across the edge (v[i], v[i+1])
+    alive: bool,
+    inside: bool,          // whether the triangle belongs to the meshed domain
+}
+
+enum Walk {
+    Found(usize),          // triangle containing the point
+    Blocked([usize; 2]),   // segment between the start and the point
+    Lost,                  // the point is outside of the triangulation or could not be reached
+}
+
+// the first 3 points form a super triangle around the input
+const NUM_SUPER_VERTICES: usize = 3;
+
+struct Triangulation {
+    points: Vec<[f64; 2]>,
+    is_input: Vec<bool>,     // whether a point is a polygon vertex
+    corner_angle: Vec<f64>,  // smallest angle between the segments at each polygon vertex
+    triangles: Vec<Triangle>,
+    segments: HashMap<[usize; 2], usize>, // constrained subsegments and their polygon
+    constrained: bool,       // whether cavities and walks stop at segments
+    last: usize,             // recently created triangle to start walks from
+    scale: f64,              // size of the input, for tolerances
+}
+
+impl Triangulation {
+    fn new(polygons: &[&[[f64; 2]]]) -> Triangulation {
+        let (mut min, mut max) = ([f64::INFINITY; 2], [f64::NEG_INFINITY; 2]);
+        for p in polygons.iter().flat_map(|polygon| polygon.iter()) {
+            for axis in 0..2 {
+                min[axis] = min[axis].min(p[axis]);
+                max[axis] = max[axis].max(p[axis]);
+            }
+        }
+        let center = [0.5 * (min[0] + max[0]), 0.5 * (min[1] + max[1])];
+        let scale = (max[0] - min[0]).max(max[1] - min[1]).max(f64::MIN_POSITIVE);
+
+        // a super triangle far around the input, whose vertices are removed in the end
+        let d = 100.0 * scale;
+        let points = vec![
+            [center[0] - d, center[1] - d],
+            [center[0] + d, center[1] - d],
+            [center[0], center[1] + d],
+        ];
+        Triangulation {
+            points,
+            is_input: vec![false; NUM_SUPER_VERTICES],
+            corner_angle: vec![std::f64::consts::PI; NUM_SUPER_VERTICES],
+            triangles: vec![Triangle { v: [0, 1, 2], n: [None; 3], alive: true, inside: false }],
+            segments: HashMap::new(),
+            constrained: false,
+            last: 0,
+            scale,
+        }
+    }
+
+    fn insert_input(&mut self, p: [f64; 2]) -> Option<usize> {
+        let t = match self.walk(self.last, p, false) {
+            Walk::Found(t) => t,
+            _ => return None,
+        };
+        let vertex_idx = self.insert(p, t)?.0;
+        self.is_input[vertex_idx] = true;
+        Some(vertex_idx)
+    }
+
+    fn set_corner_angles(&mut self, segments: &[([usize; 2], usize)]) -> () {
+        let mut directions = HashMap::<usize, Vec<[f64; 2]>>::new();
+        for &([a, b], _) in segments.iter() {
+            let (pa, pb) = (self.points[a], self.points[b]);
+            directions.entry(a).or_default().push([pb[0] - pa[0], pb[1] - pa[1]]);
+            directions.entry(b).or_default().push([pa[0] - pb[0], pa[1] - pb[1]]);
+        }
+        for (vertex_idx, directions) in directions.iter() {
+            for (i, u) in directions.iter().enumerate() {
+                for w in directions[i + 1..].iter() {
+                    let angle = (u[0] * w[1] - u[1] * w[0]).abs().atan2(u[0] * w[0] + u[1] * w[1]);
+                    self.corner_angle[*vertex_idx] = self.corner_angle[*vertex_idx].min(angle);
+                }
+            }
+        }
+    }
+
+    fn walk(&self, start: usize, p: [f64; 2], stop_at_segments: bool) -> Walk {
+        // visibility walk from the start triangle towards p, falling back to a linear search when
+        // it does not terminate
+        let mut t = if self.triangles[start].alive { start } else { self.any_alive() };
+        for _ in 0..self.triangles.len() {
+            let triangle = &self.triangles[t];
+            let edge = (0..3).find(|&i| {
+                orient(self.points[triangle.v[i]], self.points[triangle.v[(i + 1) % 3]], p) < 0.0
+            });
+            match edge {
+                None => return Walk::Found(t),
+                Some(i) => {
+                    let key = segment_key(triangle.v[i], triangle.v[(i + 1) % 3]);
+                    if stop_at_segments && self.segments.contains_key(&key) {
+                        return Walk::Blocked(key);
+                    }
+                    match triangle.n[i] {
+                        Some(next) => t = next,
+                        None => return Walk::Lost,
+                    }
+                },
+            }
+        }
+        if stop_at_segments {
+            return Walk::Lost;
+        }
+        (0..self.triangles.len())
+            .find(|&t| {
+                let triangle = &self.triangles[t];
+                triangle.alive && (0..3).all(|i| {
+                    orient(self.points[triangle.v[i]], self.points[triangle.v[(i + 1) % 3]], p) >= 0.0
+                })
+            })
+            .map_or(Walk::Lost, Walk::Found)
+    }
+
+    fn any_alive(&self) -> usize {
+        self.triangles.iter().rposition(|triangle| triangle.alive).expect("Triangulation without triangles")
+    }
+
+    fn cavity(&self, p: [f64; 2], t0: usize) -> (Vec<usize>, Vec<(usize, usize)>) {
+        // Bowyer-Watson cavity of the triangles whose circumcircle contains p, grown from the
+        // triangle t0 containing p and not crossing segments. Returns the cavity triangles and
+        // its boundary as (triangle, edge) pairs.
+        let mut cavity = vec![t0];
+        let mut in_cavity = HashSet::from([t0]);
+        let mut boundary = Vec::<(usize, usize)>::new();
+        let mut k = 0;
+        while k < cavity.len() {
+            let t = cavity[k];
+            k += 1;
+            let triangle = self.triangles[t];
+            for i in 0..3 {
+                let (a, b) = (triangle.v[i], triangle.v[(i + 1) % 3]);
+                let blocked = self.constrained && self.segments.contains_key(&segment_key(a, b));
+                match triangle.n[i] {
+                    Some(nb) if in_cavity.contains(&nb) => {},
+                    Some(nb) if !blocked && {
+                        let v = self.triangles[nb].v;
+                        in_circle(self.points[v[0]], self.points[v[1]], self.points[v[2]], p) > 0.0
+                    } => {
+                        in_cavity.insert(nb);
+                        cavity.push(nb);
+                    },
+                    _ => boundary.push((t, i)),
+                }
+            }
+        }
+        // edges already visited from the other side were added as boundary before the neighbor
+        // joined the cavity
+        boundary.retain(|&(t, i)| self.triangles[t].n[i].is_none_or(|nb| !in_cavity.contains(&nb)));
+        (cavity, boundary)
+    }
+
+    fn insert(&mut self, p: [f64; 2], t0: usize) -> Option<(usize, Vec<usize>)> {
+        // inserts p into the triangle t0 containing it, returning the new vertex and triangles. A
+        // point on an existing vertex returns that vertex and no triangles.
+        let tolerance = 1e-12 * self.scale;
+        if let Some(&v) = self.triangles[t0].v.iter().find(|&&v| distance(self.points[v], p) <= tolerance) {
+            return Some((v, Vec::new()));
+        }
+
+        let (cavity, boundary) = self.cavity(p, t0);
+        // the cavity has to be star-shaped from p
+        let area_tolerance = 1e-14 * self.scale * self.scale;
+        if boundary.iter().any(|&(t, i)| {
+            let v = self.triangles[t].v;
+            orient(self.points[v[i]], self.points[v[(i + 1) % 3]], p) <= area_tolerance
+        }) {
+            return None;
+        }
+
+        let vertex_idx = self.points.len();
+        self.points.push(p);
+        self.is_input.push(false);
+        self.corner_angle.push(std::f64::consts::PI);
+
+        // fan of new triangles (a, b, p) around p, one for every boundary edge (a, b)
+        let mut new_triangles = Vec::<usize>::with_capacity(boundary.len());
+        let mut starting_at = HashMap::<usize, usize>::new();
+        let mut ending_at = HashMap::<usize, usize>::new();
+        for &(t, i) in boundary.iter() {
+            let old = self.triangles[t];
+            let (a, b) = (old.v[i], old.v[(i + 1) % 3]);
+            let new_idx = self.triangles.len();
+            self.triangles.push(Triangle { v: [a, b, vertex_idx], n: [old.n[i], None, None], alive: true, inside: old.inside });
+            if let Some(nb) = old.n[i] {
+                let j = (0..3).find(|&j| self.triangles[nb].n[j] == Some(t)).expect("Inconsistent triangle neighbors");
+                self.triangles[nb].n[j] = Some(new_idx);
+            }
+            starting_at.insert(a, new_idx);
+            ending_at.insert(b, new_idx);
+            new_triangles.push(new_idx);
+        }
+        for &new_idx in new_triangles.iter() {
+            let [a, b, _] = self.triangles[new_idx].v;
+            // edge (b, p) borders the triangle starting at b, and edge (p, a) the one ending at a
+            self.triangles[new_idx].n[1] = starting_at.get(&b).copied();
+            self.triangles[new_idx].n[2] = ending_at.get(&a).copied();
+        }
+        for &t in cavity.iter() {
+            self.triangles[t].alive = false;
+        }
+        self.last = *new_triangles.last().unwrap_or(&self.last);
+        Some((vertex_idx, new_triangles))
+    }
+
+    fn split_point(&self, a: usize, b: usize) -> [f64; 2] {
+        // Midpoint of the segment, except that segments with one polygon vertex are split at a power
+        // of two distance from it. Then segments meeting at a small angle are split on concentric
+        // circles, which keeps them from encroaching each other forever.
+        let (pa, pb) = (self.points[a], self.points[b]);
+        let length = distance(pa, pb);
+        let (origin, target) = match (self.is_input[a], self.is_input[b]) {
+            (true, false) => (pa, pb),
+            (false, true) => (pb, pa),
+            _ => return [0.5 * (pa[0] + pb[0]), 0.5 * (pa[1] + pb[1])],
+        };
+        let shell = 2f64.powf((0.5 * length).log2().round());
+        let s = (shell / length).clamp(0.25, 0.75);
+        [origin[0] + s * (target[0] - origin[0]), origin[1] + s * (target[1] - origin[1])]
+    }
+
+    fn recover_segments(&mut self, segments: Vec<([usize; 2], usize)>, max_vertices: usize) -> Result<(), MeshError> {
+        // splits the segments missing from the Delaunay triangulation until all of their pieces are
+        // edges, which gives a conforming Delaunay triangulation
+        let mut segments = segments;
+        loop {
+            let edges: HashSet<[usize; 2]> = self.triangles.iter()
+                .filter(|triangle| triangle.alive)
+                .flat_map(|triangle| (0..3).map(move |i| segment_key(triangle.v[i], triangle.v[(i + 1) % 3])))
+                .collect();
+            let (present, missing): (Vec<_>, Vec<_>) = segments.into_iter()
+                .partition(|([a, b], _)| edges.contains(&segment_key(*a, *b)));
+            segments = present;
+            if missing.is_empty() {
+                break;
+            }
+            if self.points.len() > max_vertices {
+                return Err(MeshError::VertexLimitReached { max_vertices });
+            }
+            for ([a, b], polygon_idx) in missing {
+                let m = self.split_point(a, b);
+                let t = match self.walk(self.last, m, false) {
+                    Walk::Found(t) => t,
+                    _ => return Err(MeshError::DegenerateInput),
+                };
+                let (m_idx, _) = self.insert(m, t).ok_or(MeshError::DegenerateInput)?;
+                if m_idx == a || m_idx == b {
+                    return Err(MeshError::DegenerateInput);
+                }
+                segments.push(([a, m_idx], polygon_idx));
+                segments.push(([m_idx, b], polygon_idx));
+            }
+        }
+        self.segments = segments.into_iter()
+            .map(|([a, b], polygon_idx)| (segment_key(a, b), polygon_idx))
+            .collect();
+        self.constrained = true;
+        Ok(())
+    }
+
+    fn classify(&mut self) -> () {
+        // A triangle is inside the domain if any path to the outside of the super triangle crosses an
+        // odd number of segments. Flood fills from a triangle at a super vertex, which is outside.
+        let start = (0..self.triangles.len())
+            .find(|&t| self.triangles[t].alive && self.triangles[t].v.iter().any(|&v| v < NUM_SUPER_VERTICES))
+            .expect("Super triangle vertex without triangles");
+        let mut visited = vec![false; self.triangles.len()];
+        visited[start] = true;
+        self.triangles[start].inside = false;
+        let mut stack = vec![start];
+        while let Some(t) = stack.pop() {
+            let triangle = self.triangles[t];
+            for i in 0..3 {
+                if let Some(nb) = triangle.n[i].filter(|&nb| !visited[nb]) {
+                    let crosses = self.segments.contains_key(&segment_key(triangle.v[i], triangle.v[(i + 1) % 3]));
+                    self.triangles[nb].inside = triangle.inside ^ crosses;
+                    visited[nb] = true;
+                    stack.push(nb);
+                }
+            }
+        }
+    }
+
+    fn encroached_segments(&self, new_triangles: &[usize]) -> Vec<[usize; 2]> {
+        // segments on the edges of the given triangles whose opposite vertex encroaches them
+        new_triangles.iter()
+            .flat_map(|&t| {
+                let v = self.triangles[t].v;
+                (0..3).filter_map(move |i| {
+                    let key = segment_key(v[i], v[(i + 1) % 3]);
+                    let apex = self.points[v[(i + 2) % 3]];
+                    (self.segments.contains_key(&key)
+                        && encroaches(apex, self.points[key[0]], self.points[key[1]])).then_some(key)
+                })
+            })
+            .collect()
+    }
+
+    fn needs_refinement(&self, t: usize, quality: &MeshQuality) -> bool {
+        let v = self.triangles[t].v;
+        let p = v.map(|v| self.points[v]);
+        let area = 0.5 * orient(p[0], p[1], p[2]);
+        if quality.max_area.is_some_and(|max_area| area > max_area) {
+            return true;
+        }
+
+        // smallest angle, opposite of the shortest edge
+        let lengths = [distance(p[1], p[2]), distance(p[2], p[0]), distance(p[0], p[1])];
+        let k = (0..3).min_by(|&i, &j| lengths[i].total_cmp(&lengths[j])).unwrap_or(0);
+        let min_angle = (2.0 * area).atan2(
+            (p[(k + 1) % 3][0] - p[k][0]) * (p[(k + 2) % 3][0] - p[k][0])
+            + (p[(k + 1) % 3][1] - p[k][1]) * (p[(k + 2) % 3][1] - p[k][1]));
+        // small angles between the input segments cannot be improved, so triangles whose smallest
+        // angle sits in such a corner are left alone
+        if self.corner_angle[v[k]] < std::f64::consts::FRAC_PI_3 {
+            return false;
+        }
+        min_angle < quality.min_angle.to_radians()
+    }
+
+    fn split_segment(&mut self, key: [usize; 2]) -> Option<Vec<usize>> {
+        let polygon_idx = self.segments.remove(&key)?;
+        let m = self.split_point(key[0], key[1]);
+        let inserted = match self.walk(self.last, m, false) {
+            Walk::Found(t) => self.insert(m, t),
+            _ => None,
+        };
+        match inserted {
+            Some((m_idx, new_triangles)) if !new_triangles.is_empty() => {
+                self.segments.insert(segment_key(key[0], m_idx), polygon_idx);
+                self.segments.insert(segment_key(m_idx, key[1]), polygon_idx);
+                Some(new_triangles)
+            },
+            _ => {
+                self.segments.insert(key, polygon_idx);
+                None
+            },
+        }
+    }
+
+    fn refine(&mut self, quality: &MeshQuality) -> Result<bool, MeshError> {
+        // Ruppert's algorithm: encroached segments are split first, then bad triangles are split at
+        // their circumcenter, unless it encroaches a segment, which is then split instead. Returns
+        // whether refinement stopped at max_vertices.
+        let all_triangles: Vec<usize> = (0..self.triangles.len()).filter(|&t| self.triangles[t].alive).collect();
+        let mut encroached = self.encroached_segments(&all_triangles);
+        let mut bad = all_triangles;
+
+        // number of points when a bad triangle was put back on the queue. Being put back again
+        // without a new point means that splitting its segments failed, which would loop forever.
+        let mut deferred = HashMap::<usize, usize>::new();
+        let mut defer = |t: usize, num_points: usize, bad: &mut Vec<usize>| {
+            if deferred.insert(t, num_points) == Some(num_points) {
+                return Err(MeshError::RefinementStalled);
+            }
+            bad.push(t);
+            Ok(())
+        };
+
+        loop {
+            if self.points.len() >= quality.max_vertices {
+                return Ok(true);
+            }
+
+            if let Some(key) = encroached.pop() {
+                if let Some(new_triangles) = self.split_segment(key) {
+                    encroached.extend(self.encroached_segments(&new_triangles));
+                    bad.extend(new_triangles);
+                }
+                continue;
+            }
+
+            let Some(t) = bad.pop() else { break };
+            if !self.triangles[t].alive || !self.triangles[t].inside || !self.needs_refinement(t, quality) {
+                continue;
+            }
+            let v = self.triangles[t].v;
+            let c = circumcenter(self.points[v[0]], self.points[v[1]], self.points[v[2]]);
+            match self.walk(t, c, true) {
+                Walk::Found(tc) => {
+                    let (_, boundary) = self.cavity(c, tc);
+                    let encroached_by_c: Vec<[usize; 2]> = boundary.iter()
+                        .map(|&(bt, i)| {
+                            let bv = self.triangles[bt].v;
+                            segment_key(bv[i], bv[(i + 1) % 3])
+                        })
+                        .filter(|key| self.segments.contains_key(key)
+                            && encroaches(c, self.points[key[0]], self.points[key[1]]))
+                        .collect();
+                    if !encroached_by_c.is_empty() {
+                        encroached.extend(encroached_by_c);
+                        defer(t, self.points.len(), &mut bad)?;
+                    } else if let Some((_, new_triangles)) = self.insert(c, tc) {
+                        encroached.extend(self.encroached_segments(&new_triangles));
+                        bad.extend(new_triangles);
+                    }
+                },
+                Walk::Blocked(key) => {
+                    encroached.push(key);
+                    defer(t, self.points.len(), &mut bad)?;
+                },
+                Walk::Lost => {},
+            }
+        }
+        Ok(false)
+    }
+
+    fn into_polygon_mesh(self) -> PolygonMesh {
+        // keeps the triangles inside the domain and renumbers their vertices
+        let mut new_index = vec![usize::MAX; self.points.len()];
+        let mut vertices = Vec::<[f64; 2]>::new();
+        let mut triangles = Vec::<[usize; 3]>::new();
+        for triangle in self.triangles.iter().filter(|triangle| triangle.alive && triangle.inside) {
+            triangles.push(triangle.v.map(|v| {
+                if new_index[v] == usize::MAX {
+                    new_index[v] = vertices.len();
+                    vertices.push(self.points[v]);
+                }
+                new_index[v]
+            }));
+        }
+        let segments = self.segments.iter()
+            .filter(|(key, _)| new_index[key[0]] != usize::MAX && new_index[key[1]] != usize::MAX)
+            .map(|(key, &polygon_idx)| (segment_key(new_index[key[0]], new_index[key[1]]), polygon_idx))
+            .collect();
+        PolygonMesh { vertices, triangles, segments, reached_vertex_limit: false }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::TriangleMesh;
+
+    fn square(center: [f64; 2], size: f64) -> Vec<[f64; 2]> {
+        let h = 0.5 * size;
+        vec![[center[0] - h, center[1] - h], [center[0] + h, center[1] - h],
+             [center[0] + h, center[1] + h], [center[0] - h, center[1] + h]]
+    }
+
+    fn polygon_area(polygon: &[[f64; 2]]) -> f64 {
+        (0..polygon.len())
+            .map(|i| {
+                let (p, q) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+                0.5 * (p[0] * q[1] - q[0] * p[1])
+            })
+            .sum::<f64>()
+            .abs()
+    }
+
+    fn triangle_points(mesh: &PolygonMesh, triangle: &[usize; 3]) -> [[f64; 2]; 3] {
+        triangle.map(|v| mesh.vertices[v])
+    }
+
+    // checks the quality bounds, the Delaunay property, the area and the boundary of a mesh
+    fn check_mesh(mesh: &PolygonMesh, outer: &[[f64; 2]], holes: &[Vec<[f64; 2]>], quality: &MeshQuality) -> () {
+        assert!(!mesh.reached_vertex_limit);
+        let scale = outer.iter().flatten().fold(0.0_f64, |max, x| max.max(x.abs()));
+
+        let mut total_area = 0.0;
+        for triangle in mesh.triangles.iter() {
+            let p = triangle_points(mesh, triangle);
+            let area = 0.5 * orient(p[0], p[1], p[2]);
+            assert!(area > 0.0, "triangle {:?} is not counter-clockwise", triangle);
+            assert!(area <= quality.max_area.unwrap() * (1.0 + 1e-9), "area {} above the bound", area);
+            total_area += area;
+
+            for i in 0..3 {
+                let (a, b, c) = (p[i], p[(i + 1) % 3], p[(i + 2) % 3]);
+                let cos = ((b[0] - a[0]) * (c[0] - a[0]) + (b[1] - a[1]) * (c[1] - a[1]))
+                    / (distance(a, b) * distance(a, c));
+                let angle = cos.clamp(-1.0, 1.0).acos().to_degrees();
+                assert!(angle >= quality.min_angle - 1e-9, "angle {} below the bound", angle);
+            }
+
+            // empty circumcircle
+            for (v, &q) in mesh.vertices.iter().enumerate() {
+                if !triangle.contains(&v) {
+                    assert!(in_circle(p[0], p[1], p[2], q) <= 1e-9 * scale.powi(4),
+                        "vertex {} inside the circumcircle of {:?}", v, triangle);
+                }
+            }
+        }
+        let expected_area = polygon_area(outer) - holes.iter().map(|hole| polygon_area(hole)).sum::<f64>();
+        assert!((total_area - expected_area).abs() <= 1e-9 * expected_area, "area {} instead of {}", total_area, expected_area);
+
+        // every input edge is the union of the mesh edges between the vertices on it
+        let edges: HashSet<[usize; 2]> = mesh.triangles.iter()
+            .flat_map(|t| (0..3).map(move |i| segment_key(t[i], t[(i + 1) % 3])))
+            .collect();
+        for polygon in std::iter::once(outer).chain(holes.iter().map(|hole| hole.as_slice())) {
+            for i in 0..polygon.len() {
+                let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+                let length = distance(a, b);
+                let mut on_edge: Vec<(f64, usize)> = mesh.vertices.iter()
+                    .enumerate()
+                    .filter(|&(_, &q)| orient(a, b, q).abs() <= 1e-9 * length * length)
+                    .map(|(v, &q)| (((q[0] - a[0]) * (b[0] - a[0]) + (q[1] - a[1]) * (b[1] - a[1])) / (length * length), v))
+                    .filter(|&(s, _)| (-1e-9..=1.0 + 1e-9).contains(&s))
+                    .collect();
+                on_edge.sort_by(|x, y| x.0.total_cmp(&y.0));
+                assert!(on_edge.first().is_some_and(|&(s, _)| s.abs() <= 1e-9));
+                assert!(on_edge.last().is_some_and(|&(s, _)| (s - 1.0).abs() <= 1e-9));
+                for pair in on_edge.windows(2) {
+                    let key = segment_key(pair[0].1, pair[1].1);
+                    assert!(edges.contains(&key) && mesh.segments.contains_key(&key), "input edge {:?} to {:?} is not covered", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn square_meets_the_quality() {
+        let outer = square([0.0, 0.0], 2.0);
+        let quality = MeshQuality { max_area: Some(0.05), min_angle: 25.0, ..MeshQuality::default() };
+        let mesh = triangulate_polygon(&outer, &[], &quality).unwrap();
+        check_mesh(&mesh, &outer, &[], &quality);
+    }
+
+    #[test]
+    fn polygon_with_hole_meets_the_quality() {
+        let outer = vec![[0.0, 0.0], [4.0, 0.0], [4.0, 3.0], [2.0, 4.0], [0.0, 3.0]];
+        let holes = vec![square([2.0, 2.0], 1.0)];
+        let quality = MeshQuality { max_area: Some(0.1), min_angle: 28.0, ..MeshQuality::default() };
+        let mesh = triangulate_polygon(&outer, &holes, &quality).unwrap();
+        check_mesh(&mesh, &outer, &holes, &quality);
+    }
+
+    #[test]
+    fn vertex_limit_is_reported() {
+        let outer = square([0.0, 0.0], 2.0);
+        let quality = MeshQuality { max_area: Some(1e-4), min_angle: 20.0, max_vertices: 100 };
+        let mesh = triangulate_polygon(&outer, &[], &quality).unwrap();
+        assert!(mesh.reached_vertex_limit);
+        assert!(matches!(TriangleMesh::new_polygon(&outer, &[], &quality),
+            Err(MeshError::VertexLimitReached { max_vertices: 100 })));
+    }
+}
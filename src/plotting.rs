@@ -2,10 +2,18 @@ use std::error::Error;
 use crate::mesh::TriangleMesh;
 use crate::cv::MedianCentroidControlVolume;
 use plotters::prelude::*;
-use plotters::prelude::full_palette::PINK; 
 
-use plotters_gtk4::Paintable;
-use plotters_gtk4::PaintableBackend;
+// drawing on the GTK window, see window.rs
+#[cfg(feature = "gtk")]
+use crate::sim::Simulator;
+#[cfg(feature = "gtk")]
+use plotters::coord::types::RangedCoordf64;
+#[cfg(feature = "gtk")]
+use plotters::prelude::full_palette::PINK;
+#[cfg(feature = "gtk")]
+use plotters_gtk4::{Paintable, PaintableBackend};
+#[cfg(feature = "gtk")]
+use plotters_arrows::ThinArrow;
 
 pub fn plot_triangle_mesh(mesh: &TriangleMesh, filename: &str) -> Result<(), Box<dyn Error>> {
     let root = BitMapBackend::new(filename, (600,400)).into_drawing_area();
@@ -174,6 +182,23 @@ pub fn plot_triangle_mesh_with_cv(
     Ok(())
 }
 
+#[cfg(feature = "gtk")]
+fn draw_outline<DB: DrawingBackend>(chart: &mut ChartContext<DB, Cartesian2d<RangedCoordf64, RangedCoordf64>>,
+    mesh: &TriangleMesh,
+    style: ShapeStyle) -> () {
+    // draws every boundary loop of the mesh as a closed path
+    for boundary_loop in mesh.topology.boundary_loops.iter() {
+        let outline: Vec<(f64, f64)> = boundary_loop.iter()
+            .chain(boundary_loop.first())
+            .map(|&node_idx| (mesh.vertices[[node_idx, 0]], mesh.vertices[[node_idx, 1]]))
+            .collect();
+        chart
+            .draw_series(std::iter::once(PathElement::new(outline, style)))
+            .unwrap();
+    }
+}
+
+#[cfg(feature = "gtk")]
 pub fn draw_triangle_mesh_on_area(mesh: &TriangleMesh, paintable: &Paintable) -> () {
     let backend = PaintableBackend::new(paintable);
     let root = backend.into_drawing_area();
@@ -200,5 +225,91 @@ pub fn draw_triangle_mesh_on_area(mesh: &TriangleMesh, paintable: &Paintable) ->
             .unwrap();
     }
 
+    draw_outline(&mut chart, mesh, BLACK.stroke_width(2));
+
+    root.present().unwrap();
+}
+
+#[cfg(feature = "gtk")]
+pub fn draw_simulation_on_paintable(sim: &dyn Simulator, paintable: &Paintable) -> () {
+
+    let backend = PaintableBackend::new(paintable);
+    let root = backend.into_drawing_area();
+    root.fill(&BLACK).unwrap();
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(format!("Beam mesh, t={time:.*}s", 3, time=sim.time()), ("sans-serif", 12, &WHITE))
+        .build_cartesian_2d(-40.0..60.0, -40.0..40.0)
+        .unwrap();
+     
+    let mesh = sim.mesh();
+    let velocities = sim.velocities();
+
+    for tri in mesh.triangles.outer_iter() {
+        let triangle = vec![
+            (mesh.vertices[[tri[0], 0]], mesh.vertices[[tri[0], 1]]),
+            (mesh.vertices[[tri[1], 0]], mesh.vertices[[tri[1], 1]]),
+            (mesh.vertices[[tri[2], 0]], mesh.vertices[[tri[2], 1]]),
+            (mesh.vertices[[tri[0], 0]], mesh.vertices[[tri[0], 1]]), // close loop
+        ];
+
+        chart
+            .draw_series(std::iter::once(PathElement::new(triangle, &WHITE)))
+            .unwrap();
+        }
+
+    draw_outline(&mut chart, mesh, WHITE.stroke_width(2));
+    
+    // draw immovable boundary nodes as a different color
+    chart
+        .draw_series(sim.immovable_boundary()
+            .iter()
+            .map(|&node_idx| Circle::new((mesh.vertices[[node_idx, 0]], mesh.vertices[[node_idx, 1]]), 2, CYAN.filled())))
+        .unwrap();
+
+    // draw traction nodes as a different color
+    chart
+        .draw_series(sim.tractions()
+            .iter()
+            .flat_map(|traction| traction.nodes.iter())
+            .map(|&node_idx| Circle::new((mesh.vertices[[node_idx, 0]], mesh.vertices[[node_idx, 1]]), 2, RED.filled())))
+        .unwrap();
+    
+    // draw velocity vectors       
+    chart 
+        .draw_series((0..mesh.vertices.nrows())
+            .into_iter()
+            .map(|node_idx| {
+                let arrow_size = 0.15;
+                let x = mesh.vertices[[node_idx, 0]];
+                let y = mesh.vertices[[node_idx, 1]];
+                let dx = arrow_size * velocities[[node_idx, 0]];
+                let dy = arrow_size * velocities[[node_idx, 1]];
+                ThinArrow::new((x, y), (x + dx, y + dy), &GREEN)
+            }))
+            .unwrap();
+    
+    // draw the current traction per unit length at the middle of every loaded edge
+    let t = sim.time();
+    chart 
+        .draw_series(sim.tractions()
+            .iter()
+            .flat_map(|traction| traction.edges.iter().map(move |&edge| (traction, edge)))
+            .map(|(traction, [a, b])| {
+                let arrow_size = 1e-5;
+                let edge_force = traction.edge_force([a, b], sim.material_coords(), &mesh.vertices, t);
+                let length = (&mesh.vertices.row(b) - &mesh.vertices.row(a)).mapv(|d| d * d).sum().sqrt();
+                let x = 0.5 * (mesh.vertices[[a, 0]] + mesh.vertices[[b, 0]]);
+                let y = 0.5 * (mesh.vertices[[a, 1]] + mesh.vertices[[b, 1]]);
+                let dx = arrow_size * edge_force[0] / length;
+                let dy = arrow_size * edge_force[1] / length;
+                ThinArrow::new((x, y), (x + dx, y + dy), &RED)
+            }))
+            .unwrap();
+    
+    // draw floor
+    let x_values = vec![-6.0, 6.0];
+    chart.draw_series(LineSeries::new(x_values.into_iter().map(|x| (x, -3.5)), &WHITE)).unwrap();
+
     root.present().unwrap();
 }
@@ -0,0 +1,500 @@
+use ndarray::prelude::*;
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::mesh::TriangleMesh;
+use crate::mesher::{MeshError, MeshQuality};
+use crate::mesh_io::MeshIoError;
+use crate::sim::{new_simulator, Simulator, SimulatorKind};
+use crate::sim::boundary::{Axes, Motion, Region, Side};
+use crate::sim::cauchy_fvm::CauchyFVM;
+use crate::sim::constitutive::ConstitutiveModel;
+use crate::sim::damping::Damping;
+use crate::sim::error::SimError;
+use crate::sim::integrator::Integrator;
+use crate::sim::load::LoadCurve;
+
+// A simulation described by a TOML file: the mesh, material, timestep, boundary conditions and loads,
+// and events that change them at given times, e.g.
+//
+//     simulator = "fvm"
+//     material = "rubber"
+//     dt = 1e-3
+//     mesh = { generator = "beam", width = 6.0, height = 2.0, cells = [12, 4] }
+//     clamp = { side = "left" }
+//
+//     [[tractions]]
+//     region = { side = "right" }
+//     force = [0.0, -2.5e4]
+//     curve = { ramp = { start = 0.0, end = 1.0 } }
+//
+//     [[events]]
+//     time = 5.0
+//     action = "clamp"
+//     region = { sides = ["left", "right"] }
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub name: Option<String>,
+    #[serde(default = "default_simulator")]
+    pub simulator: String, // { 'fvm', 'fvm_seq', 'fem' }
+    pub mesh: MeshSpec,
+    #[serde(default = "default_material")]
+    pub material: String,  // name in the MATERIALS table
+    pub model: Option<String>, // constitutive model, the default of the simulator if not given
+    pub dt: f64,
+    pub duration: Option<f64>, // end time of run()
+
+    // settings of the explicit FVM solver
+    pub frame_dt: Option<f64>, // substepping, see CauchyFVM::set_substepping
+    pub integrator: Option<String>,
+    pub damping: Option<DampingSpec>,
+
+    pub gravity: Option<[f64; 2]>,
+    pub gravity_curve: Option<CurveSpec>,
+    // immovable boundary, nothing is clamped if not given
+    pub clamp: Option<RegionSpec>,
+    #[serde(default)]
+    pub tractions: Vec<TractionSpec>,
+    #[serde(default)]
+    pub constraints: Vec<ConstraintSpec>,
+    #[serde(default)]
+    pub events: Vec<EventSpec>,
+
+    // directory that relative mesh file paths are resolved against
+    #[serde(skip)]
+    pub base_dir: PathBuf,
+}
+
+fn default_simulator() -> String {
+    "fvm".to_string()
+}
+
+fn default_material() -> String {
+    "default".to_string()
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "generator", rename_all = "snake_case", deny_unknown_fields)]
+pub enum MeshSpec {
+    Beam { width: f64, height: f64, cells: [usize; 2] },
+    Ball { resolution: usize },
+    // constrained Delaunay mesh of a polygon with holes
+    Polygon {
+        outer: Vec<[f64; 2]>,
+        #[serde(default)]
+        holes: Vec<Vec<[f64; 2]>>,
+        max_area: Option<f64>,
+        min_angle: Option<f64>,
+    },
+    // ASCII Gmsh .msh file of version 2, 4.0 or 4.1, or the .node/.ele files of Triangle
+    File { path: PathBuf },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum RegionSpec {
+    Side(String),
+    Sides(Vec<String>),
+    Box { min: [f64; 2], max: [f64; 2] },
+    Nodes(Vec<usize>),
+    EdgeTag(String),
+    Union(Vec<RegionSpec>),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum CurveSpec {
+    Constant(f64),
+    Table(Vec<[f64; 2]>),
+    Ramp { start: f64, end: f64 },
+    Sine {
+        amplitude: f64,
+        frequency: f64,
+        #[serde(default)]
+        phase: f64,
+        #[serde(default)]
+        offset: f64,
+    },
+    Step { time: f64 },
+    // 1 between start and end and 0 otherwise
+    Pulse { start: f64, end: f64 },
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DampingSpec {
+    pub mass: f64,
+    pub stiffness: f64,
+    pub viscosity: f64,
+}
+
+// Dead load per unit length and/or follower pressure on the boundary edges of a region
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TractionSpec {
+    pub region: RegionSpec,
+    pub force: Option<[f64; 2]>,
+    pub pressure: Option<f64>,
+    pub curve: Option<CurveSpec>,
+}
+
+// Dirichlet constraint, fixed unless a displacement (scaled by the curve) or a velocity is prescribed
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConstraintSpec {
+    pub region: RegionSpec,
+    #[serde(default = "default_axes")]
+    pub axes: String, // { 'x', 'y', 'both' }
+    pub displacement: Option<[f64; 2]>,
+    pub velocity: Option<[f64; 2]>,
+    pub curve: Option<CurveSpec>,
+}
+
+fn default_axes() -> String {
+    "both".to_string()
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EventSpec {
+    pub time: f64,
+    #[serde(flatten)]
+    pub action: EventAction,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum EventAction {
+    // replaces the immovable boundary
+    Clamp { region: RegionSpec },
+    Unclamp,
+    AddTraction(TractionSpec),
+    ClearTractions,
+    AddConstraint(ConstraintSpec),
+    ClearConstraints,
+    SetGravity { gravity: [f64; 2] },
+    SetMaterial { material: String },
+}
+
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(io::Error),
+    // malformed TOML or unknown fields
+    Parse(String),
+    // well-formed scenario with invalid values, e.g. an unknown side or a negative timestep
+    Invalid(String),
+    Mesh(MeshError),
+    MeshIo(MeshIoError),
+    Sim(SimError),
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::Io(e) => write!(f, "Could not read scenario: {}", e),
+            ScenarioError::Parse(message) => write!(f, "Could not parse scenario: {}", message),
+            ScenarioError::Invalid(message) => write!(f, "Invalid scenario: {}", message),
+            ScenarioError::Mesh(e) => write!(f, "Could not mesh scenario: {}", e),
+            ScenarioError::MeshIo(e) => write!(f, "{}", e),
+            ScenarioError::Sim(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for ScenarioError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ScenarioError::Io(e) => Some(e),
+            ScenarioError::Mesh(e) => Some(e),
+            ScenarioError::MeshIo(e) => Some(e),
+            ScenarioError::Sim(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ScenarioError {
+    fn from(e: io::Error) -> ScenarioError {
+        ScenarioError::Io(e)
+    }
+}
+
+impl From<MeshError> for ScenarioError {
+    fn from(e: MeshError) -> ScenarioError {
+        ScenarioError::Mesh(e)
+    }
+}
+
+impl From<MeshIoError> for ScenarioError {
+    fn from(e: MeshIoError) -> ScenarioError {
+        ScenarioError::MeshIo(e)
+    }
+}
+
+impl From<SimError> for ScenarioError {
+    fn from(e: SimError) -> ScenarioError {
+        ScenarioError::Sim(e)
+    }
+}
+
+// names of the simulator, model, side etc. use the FromStr impls of their enums
+fn parse<T: std::str::FromStr<Err = String>>(name: &str) -> Result<T, ScenarioError> {
+    name.parse::<T>().map_err(ScenarioError::Invalid)
+}
+
+// scenarios compiled into the library, selectable by name instead of a file path
+pub const BUILTIN_SCENARIOS: [(&str, &str); 4] = [
+    ("beam", include_str!("../scenarios/beam.toml")),
+    ("beam_example1", include_str!("../scenarios/beam_example1.toml")),
+    ("ball_example1", include_str!("../scenarios/ball_example1.toml")),
+    ("beam_example2", include_str!("../scenarios/beam_example2.toml")),
+];
+
+// loads a built-in scenario by name, or else a scenario file
+pub fn load_scenario(name_or_path: &str) -> Result<Scenario, ScenarioError> {
+    match BUILTIN_SCENARIOS.iter().find(|(name, _)| *name == name_or_path) {
+        Some((_, source)) => Scenario::from_toml(source),
+        None => Scenario::from_file(name_or_path),
+    }
+}
+
+impl Scenario {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Scenario, ScenarioError> {
+        let path = path.as_ref();
+        let mut scenario = Scenario::from_toml(&fs::read_to_string(path)?)?;
+        scenario.base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(scenario)
+    }
+
+    pub fn from_toml(source: &str) -> Result<Scenario, ScenarioError> {
+        let mut scenario: Scenario = toml::from_str(source).map_err(|e| ScenarioError::Parse(e.to_string()))?;
+        if !scenario.dt.is_finite() || scenario.dt <= 0.0 {
+            return Err(ScenarioError::Invalid(format!("timestep must be positive, got {}", scenario.dt)));
+        }
+        if let Some(duration) = scenario.duration.filter(|duration| !duration.is_finite() || *duration < 0.0) {
+            return Err(ScenarioError::Invalid(format!("duration must be finite and not negative, got {}", duration)));
+        }
+        if let Some(frame_dt) = scenario.frame_dt.filter(|frame_dt| !frame_dt.is_finite() || *frame_dt <= 0.0) {
+            return Err(ScenarioError::Invalid(format!("frame_dt must be positive, got {}", frame_dt)));
+        }
+        if scenario.events.iter().any(|event| !event.time.is_finite()) {
+            return Err(ScenarioError::Invalid("event with a non-finite time".to_string()));
+        }
+        // events at the same time keep the order of the file
+        scenario.events.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(scenario)
+    }
+
+    pub fn build_mesh(&self) -> Result<TriangleMesh, ScenarioError> {
+        Ok(match &self.mesh {
+            MeshSpec::Beam { width, height, cells } => TriangleMesh::new_beam(*width, *height, (cells[0], cells[1])),
+            MeshSpec::Ball { resolution } => TriangleMesh::new_ball(*resolution),
+            MeshSpec::Polygon { outer, holes, max_area, min_angle } => {
+                let default_quality = MeshQuality::default();
+                let quality = MeshQuality {
+                    max_area: *max_area,
+                    min_angle: min_angle.unwrap_or(default_quality.min_angle),
+                    ..default_quality
+                };
+                TriangleMesh::new_polygon(outer, holes, &quality)?
+            },
+            MeshSpec::File { path } => {
+                let path = self.base_dir.join(path);
+                match path.extension().and_then(|extension| extension.to_str()) {
+                    Some("msh") => TriangleMesh::read_msh(&path)?,
+                    Some("node" | "ele" | "poly") => TriangleMesh::read_triangle(&path)?,
+                    _ => return Err(ScenarioError::Invalid(format!("unknown mesh file format of {}", path.display()))),
+                }
+            },
+        })
+    }
+
+    // Builds the simulator in its initial state, together with the schedule of its events. Events
+    // at t = 0 are applied right away.
+    pub fn build(&self) -> Result<(Box<dyn Simulator>, EventSchedule), ScenarioError> {
+        let mesh = self.build_mesh()?;
+        let kind = parse::<SimulatorKind>(&self.simulator)?;
+
+        let mut sim: Box<dyn Simulator> = match kind {
+            SimulatorKind::Fvm => {
+                let mut fvm = CauchyFVM::new(&mesh, &self.material, self.dt)?;
+                if let Some(integrator) = &self.integrator {
+                    fvm.set_integrator(parse::<Integrator>(integrator)?);
+                }
+                if let Some(damping) = self.damping {
+                    fvm.set_damping(Damping { mass: damping.mass, stiffness: damping.stiffness, viscosity: damping.viscosity });
+                }
+                fvm.set_substepping(self.frame_dt);
+                Box::new(fvm)
+            },
+            _ => {
+                if self.integrator.is_some() || self.damping.is_some() || self.frame_dt.is_some() {
+                    return Err(ScenarioError::Invalid(
+                        "integrator, damping and frame_dt are only supported by the 'fvm' simulator".to_string()));
+                }
+                new_simulator(kind, &mesh, &self.material, self.dt)?
+            },
+        };
+
+        // the scenario replaces the default boundary conditions of the simulator
+        sim.clear_immovable_boundary();
+        sim.clear_tractions();
+        if let Some(model) = &self.model {
+            sim.set_constitutive_model(parse::<ConstitutiveModel>(model)?);
+        }
+        if let Some(gravity) = self.gravity {
+            sim.set_gravity(array![gravity[0], gravity[1]]);
+        }
+        if let Some(curve) = &self.gravity_curve {
+            sim.set_gravity_curve(curve.to_load_curve());
+        }
+        if let Some(clamp) = &self.clamp {
+            sim.set_immovable_boundary(&clamp.to_region()?)?;
+        }
+        for traction in self.tractions.iter() {
+            traction.apply(sim.as_mut())?;
+        }
+        for constraint in self.constraints.iter() {
+            constraint.apply(sim.as_mut())?;
+        }
+
+        let mut schedule = EventSchedule { events: self.events.clone(), next_event: 0 };
+        schedule.apply_due_events(sim.as_mut())?;
+        Ok((sim, schedule))
+    }
+
+    // runs the scenario without a window until its duration, returning the simulator in its final state
+    pub fn run(&self) -> Result<Box<dyn Simulator>, ScenarioError> {
+        let duration = self.duration
+            .ok_or_else(|| ScenarioError::Invalid("running a scenario needs a duration".to_string()))?;
+        let (mut sim, mut schedule) = self.build()?;
+        while sim.time() < duration {
+            schedule.step(sim.as_mut())?;
+        }
+        Ok(sim)
+    }
+}
+
+impl RegionSpec {
+    pub fn to_region(&self) -> Result<Region, ScenarioError> {
+        Ok(match self {
+            RegionSpec::Side(side) => Region::Side(parse::<Side>(side)?),
+            RegionSpec::Sides(sides) => Region::Union(sides.iter()
+                .map(|side| Ok(Region::Side(parse::<Side>(side)?)))
+                .collect::<Result<Vec<Region>, ScenarioError>>()?),
+            RegionSpec::Box { min, max } => Region::Box { min: *min, max: *max },
+            RegionSpec::Nodes(nodes) => Region::Nodes(nodes.clone()),
+            RegionSpec::EdgeTag(tag) => Region::EdgeTag(tag.clone()),
+            RegionSpec::Union(regions) => Region::Union(regions.iter()
+                .map(RegionSpec::to_region)
+                .collect::<Result<Vec<Region>, ScenarioError>>()?),
+        })
+    }
+}
+
+impl CurveSpec {
+    pub fn to_load_curve(&self) -> LoadCurve {
+        match self {
+            CurveSpec::Constant(value) => LoadCurve::Constant(*value),
+            CurveSpec::Table(points) => LoadCurve::Table(points.iter().map(|point| (point[0], point[1])).collect()),
+            CurveSpec::Ramp { start, end } => LoadCurve::Ramp { start: *start, end: *end },
+            CurveSpec::Sine { amplitude, frequency, phase, offset } =>
+                LoadCurve::Sine { amplitude: *amplitude, frequency: *frequency, phase: *phase, offset: *offset },
+            CurveSpec::Step { time } => LoadCurve::Step { time: *time },
+            CurveSpec::Pulse { start, end } => LoadCurve::pulse(*start, *end),
+        }
+    }
+}
+
+impl TractionSpec {
+    fn apply(&self, sim: &mut dyn Simulator) -> Result<usize, ScenarioError> {
+        let region = self.region.to_region()?;
+        let traction_idx = match (self.force, self.pressure) {
+            (Some(force), pressure) => {
+                let traction_idx = sim.add_traction(&region, array![force[0], force[1]])?;
+                sim.set_traction_pressure(traction_idx, pressure.unwrap_or(0.0))?;
+                traction_idx
+            },
+            (None, Some(pressure)) => sim.add_pressure(&region, pressure)?,
+            (None, None) => return Err(ScenarioError::Invalid("traction needs a force or a pressure".to_string())),
+        };
+        if let Some(curve) = &self.curve {
+            sim.set_traction_curve(traction_idx, curve.to_load_curve())?;
+        }
+        Ok(traction_idx)
+    }
+}
+
+impl ConstraintSpec {
+    fn apply(&self, sim: &mut dyn Simulator) -> Result<usize, ScenarioError> {
+        let curve = self.curve.as_ref().map(CurveSpec::to_load_curve).unwrap_or_default();
+        let motion = match (self.displacement, self.velocity) {
+            (None, None) => Motion::Fixed,
+            (Some(displacement), None) => Motion::displacement_curve(displacement, curve),
+            (None, Some(velocity)) => Motion::velocity_curve(velocity, curve),
+            (Some(_), Some(_)) =>
+                return Err(ScenarioError::Invalid("constraint with both a displacement and a velocity".to_string())),
+        };
+        Ok(sim.add_constraint(&self.region.to_region()?, parse::<Axes>(&self.axes)?, motion)?)
+    }
+}
+
+impl EventAction {
+    pub fn apply(&self, sim: &mut dyn Simulator) -> Result<(), ScenarioError> {
+        match self {
+            EventAction::Clamp { region } => sim.set_immovable_boundary(&region.to_region()?)?,
+            EventAction::Unclamp => sim.clear_immovable_boundary(),
+            EventAction::AddTraction(traction) => {
+                traction.apply(sim)?;
+            },
+            EventAction::ClearTractions => sim.clear_tractions(),
+            EventAction::AddConstraint(constraint) => {
+                constraint.apply(sim)?;
+            },
+            EventAction::ClearConstraints => sim.clear_constraints(),
+            EventAction::SetGravity { gravity } => sim.set_gravity(array![gravity[0], gravity[1]]),
+            EventAction::SetMaterial { material } => sim.set_material(material)?,
+        }
+        Ok(())
+    }
+}
+
+// Events of a scenario in time order, applied once the simulation time reaches them
+pub struct EventSchedule {
+    events: Vec<EventSpec>,
+    next_event: usize,
+}
+
+impl EventSchedule {
+    // applies all events up to the current simulation time, returning how many were applied
+    pub fn apply_due_events(&mut self, sim: &mut dyn Simulator) -> Result<usize, ScenarioError> {
+        let first_event = self.next_event;
+        while let Some(event) = self.events.get(self.next_event).filter(|event| event.time <= sim.time()) {
+            event.action.apply(sim)?;
+            self.next_event += 1;
+        }
+        Ok(self.next_event - first_event)
+    }
+
+    // advances the simulation by one update and applies the events that became due
+    pub fn step(&mut self, sim: &mut dyn Simulator) -> Result<(), ScenarioError> {
+        sim.update()?;
+        self.apply_due_events(sim)?;
+        Ok(())
+    }
+
+    // marks the events up to the given time as applied, for a simulator restored from a checkpoint
+    // that already holds their effects
+    pub fn skip_events_until(&mut self, time: f64) -> () {
+        while self.events.get(self.next_event).is_some_and(|event| event.time <= time) {
+            self.next_event += 1;
+        }
+    }
+
+    pub fn num_pending(&self) -> usize {
+        self.events.len() - self.next_event
+    }
+}
@@ -0,0 +1,119 @@
+use ndarray::prelude::*;
+use std::sync::Arc;
+use crate::sim::load::LoadCurve;
+
+// standard gravitational acceleration (m/s^2)
+pub const STANDARD_GRAVITY: f64 = 9.81;
+
+// Spatially varying body force per unit mass, i.e. an acceleration field b evaluated at the current
+// node positions. The resulting nodal force is rho * b * area.
+#[derive(Clone)]
+pub enum BodyForce {
+    // b(x, y, t), e.g. a centrifugal or attractor field
+    Field(Arc<dyn Fn(f64, f64, f64) -> [f64; 2] + Send + Sync>),
+    // b sampled on a regular grid
+    Grid(SampledField),
+}
+
+impl BodyForce {
+    pub fn field<F: Fn(f64, f64, f64) -> [f64; 2] + Send + Sync + 'static>(field: F) -> BodyForce {
+        BodyForce::Field(Arc::new(field))
+    }
+
+    // centrifugal acceleration omega^2 (x - c) in a frame rotating with angular velocity omega
+    // about the center c
+    pub fn centrifugal(center: [f64; 2], omega: f64) -> BodyForce {
+        BodyForce::field(move |x, y, _| [omega * omega * (x - center[0]), omega * omega * (y - center[1])])
+    }
+
+    // acceleration towards the center with magnitude strength / (r^2 + softening^2), where the
+    // softening length keeps the field finite at the center
+    pub fn radial_attractor(center: [f64; 2], strength: f64, softening: f64) -> BodyForce {
+        BodyForce::field(move |x, y, _| {
+            let (dx, dy) = (center[0] - x, center[1] - y);
+            let r2 = dx * dx + dy * dy + softening * softening;
+            let scale = strength / (r2 * r2.sqrt());
+            [scale * dx, scale * dy]
+        })
+    }
+
+    pub fn acceleration_at(&self, x: f64, y: f64, t: f64) -> [f64; 2] {
+        match self {
+            BodyForce::Field(field) => field(x, y, t),
+            BodyForce::Grid(grid) => grid.sample(x, y),
+        }
+    }
+}
+
+// Vector field sampled on a regular grid, bilinearly interpolated between the samples and clamped to
+// the border samples outside of the grid
+#[derive(Clone, Debug)]
+pub struct SampledField {
+    pub origin: [f64; 2],  // position of sample [0, 0]
+    pub spacing: [f64; 2], // distance between samples along x and y
+    pub values: Array3<f64>, // samples (ny, nx, 2), row j is at y = origin[1] + j * spacing[1]
+}
+
+impl SampledField {
+    pub fn new(origin: [f64; 2], spacing: [f64; 2], values: Array3<f64>) -> SampledField {
+        assert!(values.dim().0 > 0 && values.dim().1 > 0 && values.dim().2 == 2,
+            "Sampled field needs at least one sample of shape (ny, nx, 2)");
+        SampledField { origin, spacing, values }
+    }
+
+    pub fn sample(&self, x: f64, y: f64) -> [f64; 2] {
+        let (ny, nx, _) = self.values.dim();
+        // cell index and local coordinate along one axis
+        let locate = |p: f64, axis: usize, n: usize| -> (usize, usize, f64) {
+            let s = ((p - self.origin[axis]) / self.spacing[axis]).clamp(0.0, (n - 1) as f64);
+            let i0 = (s.floor() as usize).min(n.saturating_sub(2));
+            let i1 = (i0 + 1).min(n - 1);
+            (i0, i1, s - i0 as f64)
+        };
+        let (i0, i1, wx) = locate(x, 0, nx);
+        let (j0, j1, wy) = locate(y, 1, ny);
+
+        let mut value = [0.0; 2];
+        for (axis, v) in value.iter_mut().enumerate() {
+            let bottom = (1.0 - wx) * self.values[[j0, i0, axis]] + wx * self.values[[j0, i1, axis]];
+            let top = (1.0 - wx) * self.values[[j1, i0, axis]] + wx * self.values[[j1, i1, axis]];
+            *v = (1.0 - wy) * bottom + wy * top;
+        }
+        value
+    }
+}
+
+// Gravity and additional body force fields of a simulator, all given per unit mass
+#[derive(Clone)]
+pub struct BodyForces {
+    pub gravity: Array1<f64>,   // gravitational acceleration, (0, -9.81) by default
+    pub gravity_curve: LoadCurve, // scales gravity over time
+    pub fields: Vec<BodyForce>,
+}
+
+impl Default for BodyForces {
+    fn default() -> BodyForces {
+        BodyForces {
+            gravity: array![0.0, -STANDARD_GRAVITY],
+            gravity_curve: LoadCurve::default(),
+            fields: Vec::new(),
+        }
+    }
+}
+
+impl BodyForces {
+    // body acceleration of every node (N, 2) at its current position at time t
+    pub fn accelerations(&self, positions: &Array2<f64>, t: f64) -> Array2<f64> {
+        let gravity = self.gravity_curve.value(t) * &self.gravity;
+        let mut accelerations = Array2::<f64>::zeros(positions.raw_dim());
+        for (node_idx, mut acceleration) in accelerations.outer_iter_mut().enumerate() {
+            acceleration.assign(&gravity);
+            for field in self.fields.iter() {
+                let b = field.acceleration_at(positions[[node_idx, 0]], positions[[node_idx, 1]], t);
+                acceleration[0] += b[0];
+                acceleration[1] += b[1];
+            }
+        }
+        accelerations
+    }
+}
@@ -0,0 +1,361 @@
+use ndarray::prelude::*;
+use std::str::FromStr;
+use std::sync::Arc;
+use crate::mesh::TriangleMesh;
+use crate::sim::error::SimError;
+use crate::sim::load::LoadCurve;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+    Down,
+    Up,
+}
+
+impl FromStr for Side {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "left" => Ok(Side::Left),
+            "right" => Ok(Side::Right),
+            "down" => Ok(Side::Down),
+            "up" => Ok(Side::Up),
+            _ => Err(format!("Unknown side '{}'! Sides: {{ 'left', 'right', 'down', 'up' }}", name)),
+        }
+    }
+}
+
+// A set of mesh nodes, selected by their material (undeformed) coordinates or by mesh data
+#[derive(Clone)]
+pub enum Region {
+    // nodes whose material coordinates (x, y) satisfy the predicate
+    Predicate(Arc<dyn Fn(f64, f64) -> bool + Send + Sync>),
+    // nodes inside the axis aligned box, including its border
+    Box { min: [f64; 2], max: [f64; 2] },
+    // explicit list of node indices
+    Nodes(Vec<usize>),
+    // nodes on the mesh boundary edges with this tag
+    EdgeTag(String),
+    // nodes on one side of the bounding box of the mesh
+    Side(Side),
+    // nodes in any of the regions
+    Union(Vec<Region>),
+}
+
+// relative tolerance for nodes lying on a side of the bounding box
+const SIDE_TOLERANCE: f64 = 1e-6;
+
+impl Region {
+    pub fn predicate<F: Fn(f64, f64) -> bool + Send + Sync + 'static>(predicate: F) -> Region {
+        Region::Predicate(Arc::new(predicate))
+    }
+
+    // sorted indices of all nodes in the region
+    pub fn select(&self, mesh: &TriangleMesh, material_coords: &Array2<f64>) -> Vec<usize> {
+        let select_by = |predicate: &dyn Fn(f64, f64) -> bool| -> Vec<usize> {
+            (0..material_coords.nrows())
+                .filter(|&node_idx| predicate(material_coords[[node_idx, 0]], material_coords[[node_idx, 1]]))
+                .collect()
+        };
+
+        let mut nodes = match self {
+            Region::Predicate(predicate) => select_by(predicate.as_ref()),
+            Region::Box { min, max } =>
+                select_by(&|x, y| x >= min[0] && x <= max[0] && y >= min[1] && y <= max[1]),
+            Region::Nodes(nodes) => nodes.iter()
+                .copied()
+                .filter(|&node_idx| node_idx < material_coords.nrows())
+                .collect(),
+            Region::EdgeTag(tag) => mesh.edge_tags.get(tag)
+                .map(|edges| edges.iter().flat_map(|edge| edge.iter().copied()).collect())
+                .unwrap_or_default(),
+            Region::Side(side) => {
+                let column = |axis: usize| material_coords.column(axis);
+                let min_x = column(0).fold(f64::INFINITY, |a, &b| a.min(b));
+                let max_x = column(0).fold(f64::NEG_INFINITY, |a, &b| a.max(b));
+                let min_y = column(1).fold(f64::INFINITY, |a, &b| a.min(b));
+                let max_y = column(1).fold(f64::NEG_INFINITY, |a, &b| a.max(b));
+                let eps = SIDE_TOLERANCE * (max_x - min_x).max(max_y - min_y);
+                match side {
+                    Side::Left => select_by(&|x, _| x <= min_x + eps),
+                    Side::Right => select_by(&|x, _| x >= max_x - eps),
+                    Side::Down => select_by(&|_, y| y <= min_y + eps),
+                    Side::Up => select_by(&|_, y| y >= max_y - eps),
+                }
+            },
+            Region::Union(regions) => regions.iter()
+                .flat_map(|region| region.select(mesh, material_coords))
+                .collect(),
+        };
+        nodes.sort_unstable();
+        nodes.dedup();
+        nodes
+    }
+}
+
+// Default load of the right side, in force per unit length, so its total only depends on the length
+// of the side and not on the mesh resolution. Tractions used to be a force per unit area times the
+// control volume area of each node, with a default of 10e4. On the default 6 x 2 beam of 12 x 4
+// cells, the control volumes of the right side have a total area of 0.5, and 2.5e4 along its height
+// of 2 gives the same total load of 5e4. Finer or coarser meshes had other totals before.
+pub const DEFAULT_TRACTION: [f64; 2] = [0.0, -2.5e4];
+
+// A surface traction on boundary edges, integrated along the edges and scaled by a load curve. The
+// dead load keeps its direction, while the pressure follows the deformed surface.
+#[derive(Clone, Debug)]
+pub struct Traction {
+    pub nodes: Vec<usize>,      // sorted indices of the edge nodes
+    pub edges: Vec<[usize; 2]>, // boundary edges, counter-clockwise around the body
+    pub force: Array1<f64>,     // dead load per unit reference length
+    pub pressure: f64,          // follower pressure normal to the deformed edges, positive pushes inwards
+    pub curve: LoadCurve,
+}
+
+impl Traction {
+    pub fn new(edges: Vec<[usize; 2]>, force: Array1<f64>) -> Traction {
+        let mut nodes: Vec<usize> = edges.iter().flat_map(|edge| edge.iter().copied()).collect();
+        nodes.sort_unstable();
+        nodes.dedup();
+        Traction { nodes, edges, force, pressure: 0.0, curve: LoadCurve::default() }
+    }
+
+    pub fn pressure(edges: Vec<[usize; 2]>, pressure: f64) -> Traction {
+        Traction { pressure, ..Traction::new(edges, Array1::zeros(2)) }
+    }
+
+    // dead load per unit reference length at time t
+    pub fn force_at(&self, t: f64) -> Array1<f64> {
+        self.curve.value(t) * &self.force
+    }
+
+    // total force on an edge at time t
+    pub fn edge_force(&self, edge: [usize; 2], material_coords: &Array2<f64>, positions: &Array2<f64>, t: f64) -> [f64; 2] {
+        let [a, b] = edge;
+        let reference_length = (&material_coords.row(b) - &material_coords.row(a)).mapv(|d| d * d).sum().sqrt();
+        // outward normal of the counter-clockwise edge, scaled by its deformed length
+        let dx = positions[[b, 0]] - positions[[a, 0]];
+        let dy = positions[[b, 1]] - positions[[a, 1]];
+        let normal = [dy, -dx];
+        let factor = self.curve.value(t);
+        [factor * (self.force[0] * reference_length - self.pressure * normal[0]),
+         factor * (self.force[1] * reference_length - self.pressure * normal[1])]
+    }
+
+    // adds the nodal forces (N, 2) at time t, lumping half of each edge force on both of its nodes
+    pub fn add_nodal_forces(&self,
+        material_coords: &Array2<f64>,
+        positions: &Array2<f64>,
+        t: f64,
+        forces: &mut Array2<f64>) -> () {
+        for &edge in self.edges.iter() {
+            let edge_force = self.edge_force(edge, material_coords, positions, t);
+            for &node_idx in edge.iter() {
+                forces[[node_idx, 0]] += 0.5 * edge_force[0];
+                forces[[node_idx, 1]] += 0.5 * edge_force[1];
+            }
+        }
+    }
+}
+
+// Selects the nodes of a region, failing if it does not contain any node
+pub fn select_nodes(region: &Region,
+    mesh: &TriangleMesh,
+    material_coords: &Array2<f64>) -> Result<Vec<usize>, SimError> {
+    let nodes = region.select(mesh, material_coords);
+    if nodes.is_empty() {
+        return Err(SimError::EmptyRegion);
+    }
+    Ok(nodes)
+}
+
+// Selects the boundary edges with both nodes in a region, failing if there are none. The edges are
+// counter-clockwise around the body, i.e. it lies to their left.
+pub fn select_boundary_edges(region: &Region,
+    mesh: &TriangleMesh,
+    material_coords: &Array2<f64>) -> Result<Vec<[usize; 2]>, SimError> {
+    let nodes = select_nodes(region, mesh, material_coords)?;
+    let edges: Vec<[usize; 2]> = mesh.topology.boundary_edges.iter()
+        .copied()
+        .filter(|edge| edge.iter().all(|node_idx| nodes.binary_search(node_idx).is_ok()))
+        .collect();
+    if edges.is_empty() {
+        return Err(SimError::NoBoundaryEdges);
+    }
+    Ok(edges)
+}
+
+// Constrained axes of a Dirichlet constraint, e.g. Y for a roller support that slides along x
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axes {
+    X,
+    Y,
+    Both,
+}
+
+impl FromStr for Axes {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "x" => Ok(Axes::X),
+            "y" => Ok(Axes::Y),
+            "both" => Ok(Axes::Both),
+            _ => Err(format!("Unknown axes '{}'! Axes: {{ 'x', 'y', 'both' }}", name)),
+        }
+    }
+}
+
+impl Axes {
+    pub fn contains(&self, axis: usize) -> bool {
+        match self {
+            Axes::X => axis == 0,
+            Axes::Y => axis == 1,
+            Axes::Both => true,
+        }
+    }
+}
+
+// Prescribed motion of the constrained axes as a function of time
+#[derive(Clone)]
+pub enum Motion {
+    // no motion
+    Fixed,
+    // displacement u scaled by a load curve, e.g. a ramped or cyclic clamp motion
+    DisplacementCurve { u: [f64; 2], curve: LoadCurve },
+    // velocity v scaled by a load curve
+    VelocityCurve { v: [f64; 2], curve: LoadCurve },
+    // displacement u(t) from the material coordinates as a closure, which checkpoints cannot store
+    Displacement(Arc<dyn Fn(f64) -> [f64; 2] + Send + Sync>),
+    // velocity v(t) as a closure
+    Velocity(Arc<dyn Fn(f64) -> [f64; 2] + Send + Sync>),
+}
+
+// time step of the central difference giving the velocity of a prescribed displacement
+const DISPLACEMENT_RATE_STEP: f64 = 1e-6;
+
+impl Motion {
+    pub fn displacement<F: Fn(f64) -> [f64; 2] + Send + Sync + 'static>(displacement: F) -> Motion {
+        Motion::Displacement(Arc::new(displacement))
+    }
+
+    pub fn velocity<F: Fn(f64) -> [f64; 2] + Send + Sync + 'static>(velocity: F) -> Motion {
+        Motion::Velocity(Arc::new(velocity))
+    }
+
+    pub fn displacement_curve(u: [f64; 2], curve: LoadCurve) -> Motion {
+        Motion::DisplacementCurve { u, curve }
+    }
+
+    pub fn velocity_curve(v: [f64; 2], curve: LoadCurve) -> Motion {
+        Motion::VelocityCurve { v, curve }
+    }
+
+    // prescribed displacement at time t, None for the velocity motions
+    pub fn displacement_at(&self, t: f64) -> Option<[f64; 2]> {
+        match self {
+            Motion::Fixed => Some([0.0, 0.0]),
+            Motion::DisplacementCurve { u, curve } => {
+                let factor = curve.value(t);
+                Some([factor * u[0], factor * u[1]])
+            },
+            Motion::Displacement(displacement) => Some(displacement(t)),
+            Motion::VelocityCurve { .. } | Motion::Velocity(_) => None,
+        }
+    }
+
+    pub fn velocity_at(&self, t: f64) -> [f64; 2] {
+        match self {
+            Motion::Fixed => [0.0, 0.0],
+            Motion::DisplacementCurve { .. } | Motion::Displacement(_) => {
+                let h = DISPLACEMENT_RATE_STEP;
+                let (u0, u1) = (self.displacement_at(t - h).unwrap(), self.displacement_at(t + h).unwrap());
+                [(u1[0] - u0[0]) / (2.0 * h), (u1[1] - u0[1]) / (2.0 * h)]
+            },
+            Motion::VelocityCurve { v, curve } => {
+                let factor = curve.value(t);
+                [factor * v[0], factor * v[1]]
+            },
+            Motion::Velocity(velocity) => velocity(t),
+        }
+    }
+}
+
+// A Dirichlet constraint on some axes of a set of nodes
+#[derive(Clone)]
+pub struct Constraint {
+    pub nodes: Vec<usize>, // sorted node indices
+    pub axes: Axes,
+    pub motion: Motion,
+}
+
+// 1.0 for every free and 0.0 for every constrained degree of freedom (N, 2)
+pub fn compute_free_mask(num_nodes: usize, immovable_boundary: &[usize], constraints: &[Constraint]) -> Array2<f64> {
+    let mut free_mask = Array2::<f64>::ones((num_nodes, 2));
+    for &node_idx in immovable_boundary.iter() {
+        free_mask.row_mut(node_idx).fill(0.0);
+    }
+    for constraint in constraints.iter() {
+        for &node_idx in constraint.nodes.iter() {
+            for axis in (0..2).filter(|&axis| constraint.axes.contains(axis)) {
+                free_mask[[node_idx, axis]] = 0.0;
+            }
+        }
+    }
+    free_mask
+}
+
+// Prescribed velocities at time t (N, 2). Free degrees of freedom are zero, and when constraints
+// overlap the later one wins, except on the immovable boundary which stays fixed.
+pub fn compute_constrained_velocities(num_nodes: usize,
+    immovable_boundary: &[usize],
+    constraints: &[Constraint],
+    t: f64) -> Array2<f64> {
+    let mut velocities = Array2::<f64>::zeros((num_nodes, 2));
+    for constraint in constraints.iter() {
+        let velocity = constraint.motion.velocity_at(t);
+        for &node_idx in constraint.nodes.iter() {
+            for axis in (0..2).filter(|&axis| constraint.axes.contains(axis)) {
+                velocities[[node_idx, axis]] = velocity[axis];
+            }
+        }
+    }
+    for &node_idx in immovable_boundary.iter() {
+        velocities.row_mut(node_idx).fill(0.0);
+    }
+    velocities
+}
+
+// Moves the nodes with a prescribed displacement exactly to their position at time t, which removes
+// the drift of integrating the prescribed velocities. Overlapping constraints resolve like in
+// compute_constrained_velocities.
+pub fn apply_prescribed_displacements(positions: &mut Array2<f64>,
+    material_coords: &Array2<f64>,
+    immovable_boundary: &[usize],
+    constraints: &[Constraint],
+    t: f64) -> () {
+    // prescribed position of every degree of freedom, NaN if it has none
+    let mut targets = Array2::<f64>::from_elem(positions.raw_dim(), f64::NAN);
+    for constraint in constraints.iter() {
+        // fixed constraints only zero the velocity, like before
+        let u = match &constraint.motion {
+            Motion::Fixed => None,
+            motion => motion.displacement_at(t),
+        };
+        for &node_idx in constraint.nodes.iter() {
+            for axis in (0..2).filter(|&axis| constraint.axes.contains(axis)) {
+                targets[[node_idx, axis]] = u.map_or(f64::NAN, |u| material_coords[[node_idx, axis]] + u[axis]);
+            }
+        }
+    }
+    for &node_idx in immovable_boundary.iter() {
+        targets.row_mut(node_idx).fill(f64::NAN);
+    }
+
+    positions.zip_mut_with(&targets, |position, &target| {
+        if !target.is_nan() {
+            *position = target;
+        }
+    });
+}
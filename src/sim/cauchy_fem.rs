@@ -1,136 +1,201 @@
 use ndarray::prelude::*;
 use crate::mesh::*;
-use crate::cv::*;
+use crate::sim::material::*;
+use crate::sim::constitutive::*;
+use crate::sim::boundary::*;
+use crate::sim::load::LoadCurve;
+use crate::sim::body_force::*;
+use crate::sim::{Simulator, SimulatorKind};
+use crate::sim::checkpoint::*;
+use crate::sim::error::*;
+use crate::sim::sparse::*;
 use ndarray_linalg::Inverse;
 use ndarray::stack;
-use ndarray_linalg::Trace;
-use std::time::Instant;
-use ndarray_linalg::Solve;
 use std::collections::HashSet;
 use ndarray::concatenate;
 
 pub struct CauchyFEM {
+    // holds data for an implicit simulator based on the corotated linear FEM
     num_nodes: usize,
     num_elements: usize,
     pub sim_mesh: TriangleMesh,
     pub material_coords: Array2<f64>,
-    // perhaps use an array holding each element?
 
-    t: f64, // current time
+    pub t: f64, // current time
     dt: f64, // delta time
-    
+
     // material parameters
-    young_modulus: f64, // Young's modulus, E
-    nu: f64,            // Poisson ratio
-    rho: f64,           // material density
-    //lambda: f64,        // First Lamé coefficient
-    //mu: f64,            // Second Lamé coefficient
-    
+    material: Material,
+    // hyperelastic model of the elastic forces. The corotated linear model uses the rotated
+    // stiffness matrices directly, every other model computes its forces from the stresses and
+    // uses the rotated stiffness matrices as an approximate tangent in the implicit solve
+    model: ConstitutiveModel,
+
     // precomputed E_0 matrix inverses for all elements
-    inv_e0: Vec<Array2<f64>>, 
-    
-    // precomputed mass matrix
-    mass: Array2<f64>,
+    inv_e0: Vec<Array2<f64>>,
+
+    // precomputed element stiffness matrices in the reference configuration,
+    // stored as a block array of 6 x 6 matrices for each element e
+    ke: Array2<f64>,
+
+    // precomputed lumped mass matrix, stored as its diagonal of size (2N)
+    mass: Array1<f64>,
+
+    // gravity and body force fields per unit mass
+    body_forces: BodyForces,
+
+    // traction regions, each with its own force vector and load curve
+    tractions: Vec<Traction>,
+
+    immovable_boundary: Vec<usize>,
+    // per axis and prescribed motion constraints
+    constraints: Vec<Constraint>,
 
     // Holds current velocity for each node
     velocities: Array2<f64>,
 }
 
 impl CauchyFEM {
-    pub fn new(mesh: &TriangleMesh) -> CauchyFEM {
-        // hardcoded material parameters 
-        let young_modulus = 0.01e9;
-        let nu = 0.48;
-        let rho = 1050.0;
-        
-        let lambda = (young_modulus * nu) / ((1.0+nu)*(1.0-2.0*nu));
-        let mu = young_modulus / (2.0 * (1.0+nu));
-        
-        let mut t = 0.0;
-        let dt = 0.01;
-
-        let mut sim_mesh = mesh.clone();
-        let material_coords = sim_mesh.vertices.clone();
-        
+    pub fn new(mesh: &TriangleMesh, material_name: &str, dt: f64) -> Result<CauchyFEM, SimError> {
         let num_nodes = mesh.vertices.nrows();
         let num_elements = mesh.triangles.nrows();
-        let inv_e0 = Self::precompute_e0_invs(num_elements, mesh);
-        let mass = Self::precompute_mass(num_elements, mesh, rho);
-        
+        let sim_mesh = mesh.clone();
+        let material_coords = mesh.vertices.clone(); // material coordinates
+
+        // set time and delta time
+        let t = 0.0;
+        let dt = dt;
+
+        // set material parameters
+        let material = Material::from_name(material_name)?;
+        let model = ConstitutiveModel::CorotatedLinear;
+
+        let inv_e0 = Self::precompute_e0_invs(num_elements, mesh)?;
+        let ke = Self::compute_ke(&material, model, &material_coords, mesh);
+        let mass = Self::precompute_mass(num_elements, mesh, material.rho);
+
+        let body_forces = BodyForces::default();
+
+        // by default the right side is pulled down, if it has boundary edges, and the left side is clamped
+        let tractions = select_boundary_edges(&Region::Side(Side::Right), &sim_mesh, &material_coords)
+            .map(|edges| vec![Traction::new(edges, arr1(&DEFAULT_TRACTION))])
+            .unwrap_or_default();
+
+        // set of nodes on the immovable boundary
+        let immovable_boundary = select_nodes(&Region::Side(Side::Left), &sim_mesh, &material_coords)?;
+        let constraints = Vec::<Constraint>::new();
+
         // initial velocity is (0,0) for all nodes
-        let mut velocities = Array2::<f64>::zeros((num_nodes, 2));
+        let velocities = Array2::<f64>::zeros((num_nodes, 2));
 
-        CauchyFEM {
+        Ok(CauchyFEM {
             num_nodes,
             num_elements,
             sim_mesh,
             material_coords,
             t,
             dt,
-            young_modulus,
-            nu,
-            rho,
+            material,
+            model,
             inv_e0,
+            ke,
             mass,
+            body_forces,
+            tractions,
+            immovable_boundary,
+            constraints,
             velocities,
-        }
+        })
     }
-    
-    pub fn update(&mut self) -> () {
-        let new_vertices = self.compute_new_vertex_positions();
-        self.sim_mesh.vertices = new_vertices;
-        self.t += self.dt;
+
+    pub fn from_checkpoint_state(state: SimulatorState) -> Result<CauchyFEM, CheckpointError> {
+        // rebuilds the simulator on the reference mesh and restores its state
+        let mut sim = CauchyFEM::new(&state.reference_mesh()?, "default", state.dt)?;
+        sim.model = state.model;
+        sim.apply_material(state.material);
+        sim.tractions = state.tractions;
+        sim.body_forces = state.body_forces;
+        sim.immovable_boundary = state.immovable_boundary;
+        sim.constraints = state.constraints;
+        sim.sim_mesh.vertices = state.positions;
+        sim.velocities = state.velocities;
+        sim.t = state.t;
+        Ok(sim)
     }
 
-    fn compute_ke(&self) -> Array2<f64> {
+    fn apply_material(&mut self, material: Material) -> () {
+        // element areas of the simulation mesh are those of the reference configuration
+        self.ke = Self::compute_ke(&material, self.model, &self.material_coords, &self.sim_mesh);
+        self.mass = Self::precompute_mass(self.num_elements, &self.sim_mesh, material.rho);
+        self.material = material;
+    }
+
+    fn compute_ke(material: &Material,
+        model: ConstitutiveModel,
+        material_coords: &Array2<f64>,
+        mesh: &TriangleMesh) -> Array2<f64> {
+        let num_elements = mesh.triangles.nrows();
         // block array of 6 x 6 matrices for each element e
-        let mut ke = Array2::<f64>::zeros((6, self.num_elements * 6));
-        
-        let nu = self.nu;
-        // elasticity matrix
-        let d = self.young_modulus/(1.0 - (nu * nu)) * array![[1.0, nu, 0.0],
-                                                              [nu, 1.0, 0.0 ],
-                                                              [0.0, 0.0, (1.0-nu)/2.0]];
-        // TODO: parallelize over elements, perhaps also move corotational form in here
-        for elem_idx in 0..self.num_elements {
-            let triangle = self.sim_mesh.triangles.row(elem_idx);
+        let mut ke = Array2::<f64>::zeros((6, num_elements * 6));
+
+        let nu = material.nu;
+        // elasticity matrix. The hyperelastic models use the plane strain Lamé coefficients, so
+        // their tangent must be built from the same ones to keep the implicit solve stable.
+        let d = match model {
+            ConstitutiveModel::CorotatedLinear =>
+                material.young_modulus/(1.0 - (nu * nu)) * array![[1.0, nu, 0.0],
+                                                                  [nu, 1.0, 0.0 ],
+                                                                  [0.0, 0.0, (1.0-nu)/2.0]],
+            _ => {
+                let (lambda, mu) = material.lame_parameters();
+                array![[lambda + 2.0*mu, lambda, 0.0],
+                       [lambda, lambda + 2.0*mu, 0.0],
+                       [0.0, 0.0, mu]]
+            },
+        };
+        for elem_idx in 0..num_elements {
+            let triangle = mesh.triangles.row(elem_idx);
             let i = triangle[0];
             let j = triangle[1];
             let k = triangle[2];
 
-            let area = self.sim_mesh.areas[elem_idx];
-            
-            // get triangle coordinates
-            let vertices = &self.sim_mesh.vertices;
-            let xi = vertices[[i, 0]];
-            let xj = vertices[[j, 0]];
-            let xk = vertices[[k, 0]];
-            let yi = vertices[[i, 1]];
-            let yj = vertices[[j, 1]];
-            let yk = vertices[[k, 1]];
+            let area = mesh.areas[elem_idx];
+
+            // get reference triangle coordinates
+            let xi = material_coords[[i, 0]];
+            let xj = material_coords[[j, 0]];
+            let xk = material_coords[[k, 0]];
+            let yi = material_coords[[i, 1]];
+            let yj = material_coords[[j, 1]];
+            let yk = material_coords[[k, 1]];
 
             // compute spatial gradients of the barycentric coordinates
-            let dw1dx = &yj - &yk;
-            let dw1dy = &xk - &xj;
-            let dw2dx = &yk - &yi;
-            let dw2dy = &xi - &xk;
-            let dw3dx = &yi - &yj;
-            let dw3dy = &xj - &xi;
+            let dw1dx = yj - yk;
+            let dw1dy = xk - xj;
+            let dw2dx = yk - yi;
+            let dw2dy = xi - xk;
+            let dw3dx = yi - yj;
+            let dw3dy = xj - xi;
 
             let b = (1.0 / (2.0*area)) * array![[dw1dx, 0.0, dw2dx, 0.0, dw3dx, 0.0],
                            [0.0, dw1dy, 0.0, dw2dy, 0.0, dw3dy],
                            [dw1dy, dw1dx, dw2dy, dw2dx, dw3dy, dw3dx]];
-           
-            // Compute element stiffness matrix and store it in arrays of K^e's 
+
+            // Compute element stiffness matrix and store it in arrays of K^e's
             let local_ke = b.t().dot(&d).dot(&b) * area;
             ke.slice_mut(s![.., 6*elem_idx..6*elem_idx+6]).assign(&local_ke);
         }
         ke
     }
-    fn compute_corotational_form(&self, ke: Array2<f64>, inv_e0: Vec<Array2<f64>>) -> Array2<f64> {
-        // TODO: finish the function
-        let ke_prime = Array2::<f64>::zeros((6, self.num_elements * 6));
-        
+
+    fn compute_corotational_form(&self) -> (Array2<f64>, Array1<f64>) {
+        // Rotates every element stiffness matrix into the current configuration, K'^e = R^e K^e (R^e)^T,
+        // and assembles the rotated material forces f_0 = sum_e R^e K^e x_0^e. The elastic force is then
+        // f_e = -(K' x - f_0), which is invariant to rigid rotations of the element.
+        let num_nodes = self.num_nodes;
+        let mut ke_prime = Array2::<f64>::zeros((6, self.num_elements * 6));
+        let mut f0 = Array1::<f64>::zeros(2 * num_nodes);
+
         for elem_idx in 0..self.num_elements {
             let triangle = self.sim_mesh.triangles.row(elem_idx);
             let vertices = &self.sim_mesh.vertices;
@@ -138,217 +203,537 @@ impl CauchyFEM {
             let i = triangle[0];
             let j = triangle[1];
             let k = triangle[2];
-            
-            let pi = vertices.slice(s![i, ..]); // vertex i 
-            let pj = vertices.slice(s![j, ..]); // vertex j 
+
+            let pi = vertices.slice(s![i, ..]); // vertex i
+            let pj = vertices.slice(s![j, ..]); // vertex j
             let pk = vertices.slice(s![k, ..]); // vertex k
-            
+
+            // deformation gradient F^e = D^e (E_0^e)^{-1}
+            let gij = &pj - &pi;
+            let gik = &pk - &pi;
+            let d_elem = stack![Axis(1), gij, gik];
+            let fe = d_elem.dot(&self.inv_e0[elem_idx]);
+
+            // rotational part of F^e from its polar decomposition
+            let re = polar_rotation(&fe);
+
+            // block diagonal 6 x 6 rotation for the 3 vertices of the element
+            let mut re_block = Array2::<f64>::zeros((6, 6));
+            for local_idx in 0..3 {
+                re_block.slice_mut(s![2*local_idx..2*local_idx+2, 2*local_idx..2*local_idx+2]).assign(&re);
+            }
+
+            let local_ke = self.ke.slice(s![.., 6*elem_idx..6*elem_idx+6]);
+            let rk = re_block.dot(&local_ke);
+            ke_prime.slice_mut(s![.., 6*elem_idx..6*elem_idx+6]).assign(&rk.dot(&re_block.t()));
+
+            // material coordinates of the element in local order i_x, i_y, j_x, j_y, k_x, k_y
+            let x0 = array![
+                self.material_coords[[i, 0]], self.material_coords[[i, 1]],
+                self.material_coords[[j, 0]], self.material_coords[[j, 1]],
+                self.material_coords[[k, 0]], self.material_coords[[k, 1]]
+            ];
+            let local_f0 = rk.dot(&x0);
+
+            let gidx = [i, num_nodes + i, j, num_nodes + j, k, num_nodes + k];
+            for idx in 0..6 {
+                f0[gidx[idx]] += local_f0[idx];
+            }
+        }
+        (ke_prime, f0)
+    }
+
+    fn compute_elastic_forces(&self) -> Array1<f64> {
+        // elastic forces of size (2N) in the current state, the same ones that drive the solve
+        match self.model {
+            ConstitutiveModel::CorotatedLinear => {
+                let (ke_prime, f0) = self.compute_corotational_form();
+                let num_dofs = 2 * self.num_nodes;
+                let k_matrix = CsrMatrix::from_triplets(num_dofs, num_dofs, self.matrix_assembly(&ke_prime));
+                let vertices = &self.sim_mesh.vertices;
+                let flattened_vertices: Array1<f64> = concatenate![Axis(0), vertices.column(0), vertices.column(1)];
+                f0 - &k_matrix.dot(&flattened_vertices)
+            },
+            _ => self.compute_hyperelastic_forces(),
         }
-        ke_prime
     }
-    
-    fn compute_new_vertex_positions(&self) -> Array2<f64> {
-        // TODO: Apply boundary conditions
+
+    fn compute_hyperelastic_forces(&self) -> Array1<f64> {
+        // Elastic forces of size (2N) from the first Piola-Kirchhoff stresses of the elements. The
+        // columns of H^e = -A^e P^e (E_0^e)^{-T} are the forces on vertices j and k, and vertex i
+        // gets the negative of their sum.
         let num_nodes = self.num_nodes;
+        let (lambda, mu) = self.material.lame_parameters();
+        let mut forces = Array1::<f64>::zeros(2 * num_nodes);
+
+        for elem_idx in 0..self.num_elements {
+            let triangle = self.sim_mesh.triangles.row(elem_idx);
+            let vertices = &self.sim_mesh.vertices;
+
+            let i = triangle[0];
+            let j = triangle[1];
+            let k = triangle[2];
+
+            let gij = &vertices.row(j) - &vertices.row(i);
+            let gik = &vertices.row(k) - &vertices.row(i);
+            let d_elem = stack![Axis(1), gij, gik];
+            let fe = d_elem.dot(&self.inv_e0[elem_idx]);
+
+            let pe = self.model.first_piola(&fe, lambda, mu);
+            // element areas of the simulation mesh are those of the reference configuration
+            let he = -self.sim_mesh.areas[elem_idx] * pe.dot(&self.inv_e0[elem_idx].t());
+
+            forces[j] += he[[0, 0]];
+            forces[num_nodes + j] += he[[1, 0]];
+            forces[k] += he[[0, 1]];
+            forces[num_nodes + k] += he[[1, 1]];
+            forces[i] -= he[[0, 0]] + he[[0, 1]];
+            forces[num_nodes + i] -= he[[1, 0]] + he[[1, 1]];
+        }
+        forces
+    }
+
+    fn compute_total_forces(&self) -> Array2<f64> {
+        // elastic, body and traction forces (N, 2) in the current state
+        let num_nodes = self.num_nodes;
+        let vertices = &self.sim_mesh.vertices;
+        let mut forces = Self::unflatten(&self.compute_elastic_forces(), num_nodes);
+
+        let body_accelerations = self.body_forces.accelerations(vertices, self.t);
+        for node_idx in 0..num_nodes {
+            forces[[node_idx, 0]] += self.mass[node_idx] * body_accelerations[[node_idx, 0]];
+            forces[[node_idx, 1]] += self.mass[num_nodes + node_idx] * body_accelerations[[node_idx, 1]];
+        }
+        for traction in self.tractions.iter() {
+            traction.add_nodal_forces(&self.material_coords, vertices, self.t, &mut forces);
+        }
+        forces
+    }
+
+    fn compute_new_state(&self) -> Result<(Array2<f64>, Array2<f64>), SimError> {
+        let num_nodes = self.num_nodes;
+        let num_dofs = 2 * num_nodes;
 
         // assemble global system of equations
         let dt = &self.dt;
         let mass = &self.mass;
-        let ke = self.compute_ke();
-        let k_matrix = self.matrix_assembly(ke);
-        
+        let (ke_prime, f0) = self.compute_corotational_form();
+        let k_triplets = self.matrix_assembly(&ke_prime);
+
         // current vertex positions
         let vertices = &self.sim_mesh.vertices;
         let vertices_x = vertices.column(0);
         let vertices_y = vertices.column(1);
-        let flattened_vertices: Array1<f64> = concatenate![Axis(0), vertices_x, vertices_y]; 
-        
-        let material_coords_x = self.material_coords.column(0);
-        let material_coords_y = self.material_coords.column(1);
-        let flattened_material_coords: Array1<f64> = concatenate![Axis(0), material_coords_x, material_coords_y]; 
+        let flattened_vertices: Array1<f64> = concatenate![Axis(0), vertices_x, vertices_y];
 
         // force vector of size (2N). First N entries are x, all following N+i entries are y.
-        let mut f = Array1::<f64>::zeros(2 * self.num_nodes);
-
-        // hardcoded body forces, just gravity for now (-9.8 in the y direction only)
-        f.slice_mut(s![self.num_nodes..]).fill(-9.8);
-        
-        // add traction force only to the traction nodes
-        let x_traction_vec: Vec<usize> = material_coords_x.indexed_iter()
-            .filter(|&(_, &val)| val > 2.9)
-            .map(|(idx, _)| idx)
-            .collect();
-        let x_traction = Array1::from_vec(x_traction_vec);
-        let y_traction = &x_traction + num_nodes;
-        let traction_arr = concatenate![Axis(0), x_traction, y_traction]; 
-        let traction_indices = traction_arr.to_vec();
-        
-        // Fill in traction forces only at traction indices   
-        for i in y_traction {
-            if self.t < 1.50 {
-                f[i] += -10e4;
-            }
-        } 
+        // The step is implicit, so the loads are evaluated at the end of the step.
+        let mut f = Array1::<f64>::zeros(num_dofs);
+        let t_new = self.t + dt;
+
+        // gravity and body forces rho * b * area, at the current positions
+        let body_accelerations = self.body_forces.accelerations(vertices, t_new);
+        for node_idx in 0..num_nodes {
+            f[node_idx] += mass[node_idx] * body_accelerations[[node_idx, 0]];
+            f[num_nodes + node_idx] += mass[num_nodes + node_idx] * body_accelerations[[node_idx, 1]];
+        }
+
+        // add the edge tractions, with the follower pressures on the current positions
+        let mut traction_forces = Array2::<f64>::zeros((num_nodes, 2));
+        for traction in self.tractions.iter() {
+            traction.add_nodal_forces(&self.material_coords, vertices, t_new, &mut traction_forces);
+        }
+        for node_idx in 0..num_nodes {
+            f[node_idx] += traction_forces[[node_idx, 0]];
+            f[num_nodes + node_idx] += traction_forces[[node_idx, 1]];
+        }
 
-        // material forces
-        let f0 = k_matrix.dot(&flattened_material_coords);
-        
         // flatten velocities
-        let velocities = &self.velocities;        
+        let velocities = &self.velocities;
         let velocities_x = velocities.column(0);
-        let velocities_y = velocities.column(1); 
-        let flattened_velocities = concatenate![Axis(0), velocities_x, velocities_y]; 
-
-        let mut a = mass + (dt * dt) * &k_matrix; 
-        let mut b = mass.dot(&flattened_velocities) + (*dt) * (f - &k_matrix.dot(&flattened_vertices) + f0);
-        
-        // before solving, set boundary conditions
-        
-        // Collect indices where x < -2.9
-        let x_indices_vec: Vec<usize> = material_coords_x.indexed_iter()
-            .filter(|&(_, &val)| val < -2.9)
-            .map(|(idx, _)| idx)
+        let velocities_y = velocities.column(1);
+        let flattened_velocities: Array1<f64> = concatenate![Axis(0), velocities_x, velocities_y];
+
+        // before solving, set boundary conditions. The constrained degrees of freedom are decoupled
+        // from the rest of the system and get an identity row, so their velocities solve to the
+        // prescribed velocities at the end of the step.
+        let free_mask = compute_free_mask(num_nodes, &self.immovable_boundary, &self.constraints);
+        let constrained_indices: HashSet<usize> = (0..num_nodes)
+            .flat_map(|node_idx| [(node_idx, 0), (node_idx, 1)])
+            .filter(|&(node_idx, axis)| free_mask[[node_idx, axis]] == 0.0)
+            .map(|(node_idx, axis)| axis * num_nodes + node_idx)
             .collect();
-        let x_indices = Array1::from_vec(x_indices_vec);
-        let y_indices = &x_indices + num_nodes;
-        let indices_arr = concatenate![Axis(0), x_indices, y_indices]; 
-        let indices = indices_arr.to_vec();
-        
-        let all_indices: HashSet<usize> = (0..2*num_nodes).collect();
-        let constrained_indices: HashSet<usize> = indices.into_iter().collect();
-        let free_indices: Vec<usize> = all_indices
-            .difference(&constrained_indices)
-            .copied()
-            .collect(); 
-
-        let af = a.select(Axis(0), &free_indices);
-        let aff = af.select(Axis(1), &free_indices); 
-        let bf = b.select(Axis(0), &free_indices);
-
-        // solve the linear system
-        let new_velocities = aff.solve_into(bf).unwrap();
-        
-        let mut full_new_velocities = Array1::<f64>::zeros(2*num_nodes);
-
-        // Fill in values only at free indices
-        for (i, &idx) in free_indices.iter().enumerate() {
-            full_new_velocities[idx] = new_velocities[i];
+        let constrained_velocities = compute_constrained_velocities(num_nodes,
+            &self.immovable_boundary, &self.constraints, self.t + dt);
+        let prescribed_velocities: Array1<f64> = concatenate![Axis(0),
+            constrained_velocities.column(0), constrained_velocities.column(1)] * concatenate![Axis(0),
+            free_mask.column(0), free_mask.column(1)].mapv(|free| 1.0 - free);
+
+        // A = M + dt^2 K'
+        let mut a_triplets: Vec<(usize, usize, f64)> = k_triplets.iter()
+            .filter(|(row, col, _)| !constrained_indices.contains(row) && !constrained_indices.contains(col))
+            .map(|&(row, col, value)| (row, col, dt * dt * value))
+            .collect();
+        for dof in 0..num_dofs {
+            if constrained_indices.contains(&dof) {
+                a_triplets.push((dof, dof, 1.0));
+            } else {
+                a_triplets.push((dof, dof, mass[dof]));
+            }
+        }
+        let k_matrix = CsrMatrix::from_triplets(num_dofs, num_dofs, k_triplets);
+        let a = CsrMatrix::from_triplets(num_dofs, num_dofs, a_triplets);
+
+        // elastic forces, f_0 - K' x for the corotated linear model
+        let elastic_forces = match self.model {
+            ConstitutiveModel::CorotatedLinear => f0 - &k_matrix.dot(&flattened_vertices),
+            _ => self.compute_hyperelastic_forces(),
+        };
+
+        // b = M v + dt (f + f_elastic), where the prescribed velocities move from the dropped
+        // columns of dt^2 K' to the right hand side
+        let mut b = mass * &flattened_velocities + (*dt) * (f + elastic_forces)
+            - dt * dt * k_matrix.dot(&prescribed_velocities);
+        let mut x0 = flattened_velocities;
+        for &dof in constrained_indices.iter() {
+            b[dof] = prescribed_velocities[dof];
+            x0[dof] = prescribed_velocities[dof];
         }
 
+        // solve the sparse linear system, starting from the current velocities. A node of no triangle
+        // has neither mass nor stiffness, so its zero row keeps its velocity and is not preconditioned.
+        let inv_diagonal = a.diagonal().mapv(|d| if d > 0.0 { 1.0 / d } else { 1.0 });
+        let result = conjugate_gradient(|x| a.dot(x), &b, x0, &inv_diagonal, 1e-8, 2 * num_dofs);
+        if !result.converged {
+            return Err(SimError::SolverDiverged {
+                iterations: result.iterations,
+                residual: result.residual_norm,
+                time: t_new,
+            });
+        }
+        let new_velocities = result.solution;
+
         // add update
-        let vertices_update = &flattened_vertices + (*dt) * &full_new_velocities; 
-        
-        // split and column stack into 2D array and return
-        let (xs, ys) = vertices_update.view().split_at(Axis(0), self.num_nodes);
-        
-        let x_view: ArrayView1<f64> = ArrayView1::from(xs);
-        let y_view: ArrayView1<f64> = ArrayView1::from(ys);
-        let mut new_vertices = Array2::<f64>::zeros((self.num_nodes, 2));
-
-        new_vertices.slice_mut(s![.., 0]).assign(&x_view);
-        new_vertices.slice_mut(s![.., 1]).assign(&y_view); 
-        
-        new_vertices
-    }
-
-    fn matrix_assembly(&self, ke: Array2<f64>) -> Array2<f64> {
-        let num_nodes = self.sim_mesh.vertices.nrows();
-        let mut k_matrix = Array2::<f64>::zeros((num_nodes*2, num_nodes*2));
-        
+        let vertices_update = &flattened_vertices + (*dt) * &new_velocities;
+
+        Ok((Self::unflatten(&vertices_update, num_nodes), Self::unflatten(&new_velocities, num_nodes)))
+    }
+
+    fn unflatten(flattened: &Array1<f64>, num_nodes: usize) -> Array2<f64> {
+        // split and column stack into 2D array
+        let (xs, ys) = flattened.view().split_at(Axis(0), num_nodes);
+        let mut unflattened = Array2::<f64>::zeros((num_nodes, 2));
+
+        unflattened.slice_mut(s![.., 0]).assign(&xs);
+        unflattened.slice_mut(s![.., 1]).assign(&ys);
+
+        unflattened
+    }
+
+    fn matrix_assembly(&self, ke: &Array2<f64>) -> Vec<(usize, usize, f64)> {
+        // scatters the element stiffness matrices into (row, col, value) triplets of the global
+        // stiffness matrix. Duplicate entries are summed when the CSR matrix is built.
+        let num_nodes = self.num_nodes;
+        let mut k_triplets = Vec::<(usize, usize, f64)>::with_capacity(36 * self.num_elements);
+
         for elem_idx in 0..self.num_elements {
             let triangle = self.sim_mesh.triangles.row(elem_idx);
             let i = triangle[0];
             let j = triangle[1];
             let k = triangle[2];
 
-            // Local order of vertex coordinates is i_x, i_y, j_x j_y, k_x, and  k_y. 
+            // Local order of vertex coordinates is i_x, i_y, j_x j_y, k_x, and  k_y.
             // This is how local vertex indices (0,1,2,..,5) are mapped to global vertex
             // indices
-            
-            let gidx = array![i, num_nodes + i, j, num_nodes + j, k, num_nodes + k];
-            
+
+            let gidx = [i, num_nodes + i, j, num_nodes + j, k, num_nodes + k];
+
             for idx_i in 0..6 {
                 for idx_j in 0..6 {
-                    let global_i = gidx[idx_i];
-                    let global_j = gidx[idx_j];
-
-                    let local_ke_entry = &ke[[idx_i, idx_j + elem_idx*6]];
-                    let k_matrix_update = &k_matrix[[gidx[idx_i], gidx[idx_j]]] + local_ke_entry;
-                    k_matrix[[gidx[idx_i], gidx[idx_j]]] = k_matrix_update;
+                    k_triplets.push((gidx[idx_i], gidx[idx_j], ke[[idx_i, idx_j + elem_idx*6]]));
                 }
             }
         }
-        k_matrix
+        k_triplets
     }
-    fn precompute_mass(num_elements: usize, mesh: &TriangleMesh, rho: f64) -> Array2<f64> {
-        let areas = mesh.areas.clone();
+
+    fn precompute_mass(num_elements: usize, mesh: &TriangleMesh, rho: f64) -> Array1<f64> {
         let num_nodes = mesh.vertices.nrows();
+        let nodal_areas = Self::precompute_nodal_areas(num_elements, mesh);
 
-        // Store diagonal masses for each node
-        let mut nodal_masses = vec![0.0; num_nodes];
+        // diagonal of the lumped mass matrix of size (2N), using the same x-block, y-block
+        // ordering as the stiffness matrix
+        let mut mass = Array1::<f64>::zeros(2*num_nodes);
 
-        // Accumulate lumped mass per node
-        for elem_idx in 0..num_elements {
-            let triangle = mesh.triangles.row(elem_idx);
-            let i = triangle[0];
-            let j = triangle[1];
-            let k = triangle[2];
+        for node_idx in 0..num_nodes {
+            let nodal_mass = rho * nodal_areas[node_idx];
+            mass[node_idx] = nodal_mass;
+            mass[num_nodes + node_idx] = nodal_mass;
+        }
 
-            let m = (rho * areas[elem_idx]) / 3.0;
+        mass
+    }
 
-            nodal_masses[i] += m;
-            nodal_masses[j] += m;
-            nodal_masses[k] += m;
-        }
+    fn precompute_nodal_areas(num_elements: usize, mesh: &TriangleMesh) -> Array1<f64> {
+        let num_nodes = mesh.vertices.nrows();
+        let mut nodal_areas = Array1::<f64>::zeros(num_nodes);
 
-        // Now build the full diagonal mass matrix of size (2N x 2N)
-        let mut mass = Array2::<f64>::zeros((2*num_nodes, 2*num_nodes));
+        // Accumulate lumped area per node
+        for elem_idx in 0..num_elements {
+            let triangle = mesh.triangles.row(elem_idx);
+            let a = mesh.areas[elem_idx] / 3.0;
 
-        for node_idx in 0..num_nodes {
-            mass[[2*node_idx, 2*node_idx]] = nodal_masses[node_idx];
-            mass[[2*node_idx+1, 2*node_idx+1]] = nodal_masses[node_idx];
+            nodal_areas[triangle[0]] += a;
+            nodal_areas[triangle[1]] += a;
+            nodal_areas[triangle[2]] += a;
         }
-
-        mass
+        nodal_areas
     }
 
     fn precompute_e0_invs(num_elements: usize,
-        sim_mesh: &TriangleMesh) -> Vec<Array2<f64>> {
+        sim_mesh: &TriangleMesh) -> Result<Vec<Array2<f64>>, SimError> {
         let mut inv_e0_elements = Vec::<Array2<f64>>::new();
-        
+
         for elem_idx in 0..num_elements {
             let triangle = sim_mesh.triangles.row(elem_idx);
             let i = triangle[0];
             let j = triangle[1];
             let k = triangle[2];
-            
-            let pi = sim_mesh.vertices.slice(s![i, ..]); // vertex i 
-            let pj = sim_mesh.vertices.slice(s![j, ..]); // vertex j 
+
+            let pi = sim_mesh.vertices.slice(s![i, ..]); // vertex i
+            let pj = sim_mesh.vertices.slice(s![j, ..]); // vertex j
             let pk = sim_mesh.vertices.slice(s![k, ..]); // vertex k
 
             let gij = &pj - &pi;
             let gik = &pk - &pi;
-            
+
             let e0_elem = stack![Axis(1), gij, gik]; // column stack gij and gik to form E_0^e for element e
-                                                     
-            inv_e0_elements.push(e0_elem.inv().expect("LinAlg Error! Matrix not invertible.")); // invert E_0^e
+
+            // the reference triangle must have a positive area for E_0^e to be invertible
+            if gij[0] * gik[1] - gik[0] * gij[1] <= 0.0 {
+                return Err(SimError::DegenerateTriangle { triangle: elem_idx });
+            }
+            let inv_e0_elem = e0_elem.inv().map_err(|_| SimError::DegenerateTriangle { triangle: elem_idx })?;
+            inv_e0_elements.push(inv_e0_elem); // invert E_0^e
+        }
+        Ok(inv_e0_elements)
+    }
+}
+
+impl Simulator for CauchyFEM {
+    fn update(&mut self) -> Result<(), SimError> {
+        let (new_vertices, new_velocities) = self.compute_new_state()?;
+        self.sim_mesh.vertices = new_vertices;
+        self.velocities = new_velocities;
+        self.t += self.dt;
+
+        apply_prescribed_displacements(&mut self.sim_mesh.vertices, &self.material_coords,
+            &self.immovable_boundary, &self.constraints, self.t);
+
+        validate_state(&self.sim_mesh.triangles, &self.sim_mesh.vertices, &self.velocities, self.model, self.t)
+    }
+
+    fn set_material(&mut self, name: &str) -> Result<(), SimError> {
+        self.apply_material(Material::from_name(name)?);
+        Ok(())
+    }
+
+    fn set_immovable_boundary(&mut self, region: &Region) -> Result<(), SimError> {
+        self.immovable_boundary = select_nodes(region, &self.sim_mesh, &self.material_coords)?;
+        Ok(())
+    }
+
+    fn clear_immovable_boundary(&mut self) -> () {
+        self.immovable_boundary.clear();
+    }
+
+    fn add_traction(&mut self, region: &Region, force_vector: Array1<f64>) -> Result<usize, SimError> {
+        let edges = select_boundary_edges(region, &self.sim_mesh, &self.material_coords)?;
+        self.tractions.push(Traction::new(edges, force_vector));
+        Ok(self.tractions.len() - 1)
+    }
+
+    fn add_pressure(&mut self, region: &Region, pressure: f64) -> Result<usize, SimError> {
+        let edges = select_boundary_edges(region, &self.sim_mesh, &self.material_coords)?;
+        self.tractions.push(Traction::pressure(edges, pressure));
+        Ok(self.tractions.len() - 1)
+    }
+
+    fn set_traction_force(&mut self, traction_idx: usize, force_vector: Array1<f64>) -> Result<(), SimError> {
+        self.tractions.get_mut(traction_idx).ok_or(SimError::InvalidTraction(traction_idx))?.force = force_vector;
+        Ok(())
+    }
+
+    fn set_traction_pressure(&mut self, traction_idx: usize, pressure: f64) -> Result<(), SimError> {
+        self.tractions.get_mut(traction_idx).ok_or(SimError::InvalidTraction(traction_idx))?.pressure = pressure;
+        Ok(())
+    }
+
+    fn set_traction_curve(&mut self, traction_idx: usize, curve: LoadCurve) -> Result<(), SimError> {
+        self.tractions.get_mut(traction_idx).ok_or(SimError::InvalidTraction(traction_idx))?.curve = curve;
+        Ok(())
+    }
+
+    fn clear_tractions(&mut self) -> () {
+        self.tractions.clear();
+    }
+
+    fn set_gravity(&mut self, gravity: Array1<f64>) -> () {
+        self.body_forces.gravity = gravity;
+    }
+
+    fn gravity(&self) -> &Array1<f64> {
+        &self.body_forces.gravity
+    }
+
+    fn set_gravity_curve(&mut self, curve: LoadCurve) -> () {
+        self.body_forces.gravity_curve = curve;
+    }
+
+    fn add_body_force(&mut self, body_force: BodyForce) -> usize {
+        self.body_forces.fields.push(body_force);
+        self.body_forces.fields.len() - 1
+    }
+
+    fn clear_body_forces(&mut self) -> () {
+        self.body_forces.fields.clear();
+    }
+
+    fn body_forces(&self) -> &BodyForces {
+        &self.body_forces
+    }
+
+    fn add_constraint(&mut self, region: &Region, axes: Axes, motion: Motion) -> Result<usize, SimError> {
+        let nodes = select_nodes(region, &self.sim_mesh, &self.material_coords)?;
+        self.constraints.push(Constraint { nodes, axes, motion });
+        Ok(self.constraints.len() - 1)
+    }
+
+    fn clear_constraints(&mut self) -> () {
+        self.constraints.clear();
+    }
+
+    fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
+    fn set_constitutive_model(&mut self, model: ConstitutiveModel) -> () {
+        self.model = model;
+        self.ke = Self::compute_ke(&self.material, model, &self.material_coords, &self.sim_mesh);
+    }
+
+    fn constitutive_model(&self) -> ConstitutiveModel {
+        self.model
+    }
+
+    fn time(&self) -> f64 {
+        self.t
+    }
+
+    fn mesh(&self) -> &TriangleMesh {
+        &self.sim_mesh
+    }
+
+    fn material_coords(&self) -> &Array2<f64> {
+        &self.material_coords
+    }
+
+    fn velocities(&self) -> &Array2<f64> {
+        &self.velocities
+    }
+
+    fn forces(&self) -> Array2<f64> {
+        self.compute_total_forces()
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn lame_parameters(&self) -> (f64, f64) {
+        // the corotated linear stiffness matrices are those of plane stress
+        match self.model {
+            ConstitutiveModel::CorotatedLinear => self.material.plane_stress_lame_parameters(),
+            _ => self.material.lame_parameters(),
         }
-        inv_e0_elements
     }
 
-    pub fn benchmark(&mut self, iters: usize) -> () {
-        // simple benchmarking function for checking how many updates per second we can get
-        let now = Instant::now();
-        let mut last_elapsed: u128 = 0;
+    fn plane_strain(&self) -> bool {
+        self.model != ConstitutiveModel::CorotatedLinear
+    }
 
-        for i in 0..iters {
-            self.update();
+    fn lumped_masses(&self) -> Array1<f64> {
+        // the x-block of the mass diagonal
+        self.mass.slice(s![..self.num_nodes]).to_owned()
+    }
 
-            if i % 100 == 0 {
-                let elapsed_millisecs = now.elapsed().as_millis() - last_elapsed;
-                last_elapsed += elapsed_millisecs;
-                println!("updates/sec: {}", (1.0)/((elapsed_millisecs) as f64) * 100.0 * 1000.0);
+    fn tractions(&self) -> &[Traction] {
+        &self.tractions
+    }
+
+    fn immovable_boundary(&self) -> &[usize] {
+        &self.immovable_boundary
+    }
+    fn checkpoint_state(&self) -> Result<SimulatorState, CheckpointError> {
+        Ok(SimulatorState {
+            kind: SimulatorKind::Fem,
+            material_coords: self.material_coords.clone(),
+            triangles: self.sim_mesh.triangles.clone(),
+            edge_tags: self.sim_mesh.edge_tags.clone(),
+            positions: self.sim_mesh.vertices.clone(),
+            velocities: self.velocities.clone(),
+            t: self.t,
+            dt: self.dt,
+            material: self.material.clone(),
+            model: self.model,
+            immovable_boundary: self.immovable_boundary.clone(),
+            constraints: self.constraints.clone(),
+            tractions: self.tractions.clone(),
+            body_forces: self.body_forces.clone(),
+            explicit: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forces_vanish_in_static_equilibrium() {
+        // implicit Euler damps the beam into its static equilibrium, where the elastic forces balance
+        // the loads on every free node
+        for model in [ConstitutiveModel::CorotatedLinear, ConstitutiveModel::NeoHookean] {
+            let mesh = TriangleMesh::new_beam(6.0, 2.0, (12, 4));
+            let mut sim = CauchyFEM::new(&mesh, "rubber", 0.05).unwrap();
+            sim.set_constitutive_model(model);
+            for _ in 0..2000 {
+                sim.update().unwrap();
+                if sim.velocities().iter().all(|v| v.abs() < 1e-12) {
+                    break;
+                }
             }
+            assert!(sim.velocities().iter().all(|v| v.abs() < 1e-12), "{:?} did not settle", model);
+
+            let load = sim.tractions()[0].force[1].abs() * 2.0; // traction times the height of the beam
+            let free_mask = compute_free_mask(sim.num_nodes, sim.immovable_boundary(), sim.constraints());
+            let residual = sim.forces() * &free_mask;
+            let max_residual = residual.iter().fold(0.0_f64, |max, f| max.max(f.abs()));
+            assert!(max_residual < 1e-6 * load, "{:?} residual {} of load {}", model, max_residual, load);
+        }
+    }
+
+    #[test]
+    fn nodes_of_no_triangle_stay_in_place() {
+        let beam = TriangleMesh::new_beam(6.0, 2.0, (12, 4));
+        let vertices = concatenate![Axis(0), beam.vertices, array![[10.0, 10.0]]];
+        let mesh = TriangleMesh::from_triangles(vertices, beam.triangles.clone(), beam.edge_tags.clone()).unwrap();
+        let isolated = mesh.vertices.nrows() - 1;
+        let mut sim = CauchyFEM::new(&mesh, "rubber", 1e-2).unwrap();
+        for _ in 0..10 {
+            sim.update().unwrap();
         }
+        assert_eq!(sim.positions().row(isolated), array![10.0, 10.0]);
+        assert!(sim.velocities().iter().all(|v| v.is_finite()));
     }
 }
@@ -1,27 +1,23 @@
 use ndarray::prelude::*;
 use crate::mesh::*;
 use crate::cv::*;
+use crate::sim::material::*;
+use crate::sim::constitutive::ConstitutiveModel;
+use crate::sim::damping::Damping;
+use crate::sim::boundary::*;
+use crate::sim::load::LoadCurve;
+use crate::sim::body_force::*;
+use crate::sim::{Simulator, SimulatorKind};
+use crate::sim::checkpoint::*;
+use crate::sim::error::*;
+use crate::sim::integrator::*;
+use crate::sim::statics::*;
 use ndarray_linalg::Inverse;
 use ndarray::stack;
-use ndarray_linalg::Trace;
-use std::time::Instant;
 use rayon::prelude::*;
-use std::collections::HashMap;
 
-use plotters::prelude::*;
-use plotters::prelude::full_palette::PINK; 
-use plotters_gtk4::Paintable;
-use plotters_gtk4::PaintableBackend;
-
-use lazy_static::lazy_static;
-use plotters_arrows::ThinArrow;
-
-#[derive(Clone)]
-struct Material {
-    young_modulus: f64, // Young's modulus, 
-    nu: f64,            // Poisson ratio
-    rho: f64,           // material density
-}
+// Courant number used for the stable explicit timestep, dt = CFL_NUMBER * h_min / c_p
+const CFL_NUMBER: f64 = 0.5;
 
 pub struct CauchyFVM {
     // holds data for a simulator based on the FVM applied to Cauchy's equation
@@ -32,26 +28,46 @@ pub struct CauchyFVM {
     pub t: f64, // current time
     dt: f64, // delta time
 
+    // if set, every update() advances the simulation by this frame time, split into substeps
+    // below the stable timestep
+    frame_dt: Option<f64>,
+
+    // shortest edge in the reference mesh, used for the stable timestep
+    min_edge_length: f64,
+
     // material parameters  
     material: Material,
     lambda: f64,        // First Lamé coefficient
     mu: f64,            // Second Lamé coefficient
+    model: ConstitutiveModel, // hyperelastic model giving the stress for a deformation gradient
+    damping: Damping,   // Rayleigh and Kelvin-Voigt damping, none by default
     
     // precomputed D_0 matrix inverses for all nodes and their elements
     inv_d0: Vec<Vec<Array2<f64>>>, 
     
-    // force vector for the traction surface
-    traction_force_vector: Array1<f64>,
-    traction_boundary: Vec<usize>,
+    // traction regions, each with its own force vector and load curve
+    tractions: Vec<Traction>,
+    // gravity and body force fields per unit mass
+    body_forces: BodyForces,
 
     immovable_boundary: Vec<usize>,
+    // per axis and prescribed motion constraints
+    constraints: Vec<Constraint>,
+
+    // lumped mass of each control volume
+    nodal_masses: Array1<f64>,
+    // 1.0 for free and 0.0 for immovable or constrained degrees of freedom
+    free_mask: Array2<f64>,
+
+    // time integration scheme used by update()
+    integrator: Integrator,
 
     // Holds current velocity for each node
     velocities: Array2<f64>,
 }
 
 impl CauchyFVM {
-    pub fn new(mesh: &TriangleMesh, material_name: &str, dt: f64) -> CauchyFVM {
+    pub fn new(mesh: &TriangleMesh, material_name: &str, dt: f64) -> Result<CauchyFVM, SimError> {
         let num_nodes = mesh.vertices.nrows();
         let sim_mesh = mesh.clone();
         let material_coords = mesh.clone().vertices; // material coordinates
@@ -66,62 +82,94 @@ impl CauchyFVM {
         // set time and delta time
         let t = 0.0;
         let dt = dt;
+        let frame_dt = None;
+        let min_edge_length = Self::compute_min_edge_length(&control_volumes);
         
         // set material parameters
-        let material = MATERIALS.get(material_name).unwrap().clone();
-
-        let lambda = (material.young_modulus * material.nu) / ((1.0+material.nu)*(1.0-2.0*material.nu));
-        let mu = material.young_modulus / (2.0 * (1.0+material.nu));
+        let material = Material::from_name(material_name)?;
+        let (lambda, mu) = material.lame_parameters();
+        let model = ConstitutiveModel::StVenantKirchhoff;
+        let damping = Damping::none();
         
         // precompute (D_0)^{-1} 
-        let inv_d0 = Self::precompute_d0_invs(num_nodes, &sim_mesh, &control_volumes);
-        
-        let traction_force_vector = array![0.0, -10e4];
-        
-        // set of nodes on the traction boundary
-        let traction_boundary: Vec<_> = (0..material_coords.nrows())
-            .filter_map(|node_idx| {
-                if material_coords[[node_idx, 0]] > 2.99 {
-                    Some(node_idx)
-                } else {
-                    None
-                }
-            })
-            .collect();
-        
+        let inv_d0 = Self::precompute_d0_invs(num_nodes, &sim_mesh, &control_volumes)?;
+
+        // by default the right side is pulled down, if it has boundary edges, and the left side is clamped
+        let tractions = select_boundary_edges(&Region::Side(Side::Right), &sim_mesh, &material_coords)
+            .map(|edges| vec![Traction::new(edges, arr1(&DEFAULT_TRACTION))])
+            .unwrap_or_default();
+        let body_forces = BodyForces::default();
+
         // set of nodes on the immovable boundary
-        let immovable_boundary: Vec<_> = (0..material_coords.nrows())
-            .filter_map(|node_idx| {
-                if material_coords[[node_idx, 0]] < -2.99 {
-                    Some(node_idx)
-                } else {
-                    None
-                }
-            })
-            .collect();
+        let immovable_boundary = select_nodes(&Region::Side(Side::Left), &sim_mesh, &material_coords)?;
+
+        let nodal_masses = Self::compute_nodal_masses(&control_volumes, &material);
+        let constraints = Vec::<Constraint>::new();
+        let free_mask = compute_free_mask(num_nodes, &immovable_boundary, &constraints);
+
+        let integrator = Integrator::SymplecticEuler;
 
         // initial velocity is (0,0) for all nodes
         let velocities = Array2::<f64>::zeros((num_nodes, 2));
 
-        CauchyFVM {
+        Ok(CauchyFVM {
             num_nodes,
             sim_mesh,
             material_coords,
             control_volumes,
             dt,
             t, 
+            frame_dt,
+            min_edge_length,
             material,
             lambda,
             mu,
+            model,
+            damping,
             inv_d0,
-            traction_force_vector,
-            traction_boundary,
+            tractions,
+            body_forces,
             immovable_boundary,
+            constraints,
+            nodal_masses,
+            free_mask,
+            integrator,
             velocities,
+        })
+    }
+
+    pub fn from_checkpoint_state(state: SimulatorState) -> Result<CauchyFVM, CheckpointError> {
+        // rebuilds the simulator on the reference mesh and restores its state, so that it continues
+        // exactly like the simulator the checkpoint was taken from
+        let mut sim = CauchyFVM::new(&state.reference_mesh()?, "default", state.dt)?;
+        sim.apply_material(state.material);
+        sim.model = state.model;
+        sim.tractions = state.tractions;
+        sim.body_forces = state.body_forces;
+        sim.immovable_boundary = state.immovable_boundary;
+        sim.constraints = state.constraints;
+        sim.free_mask = compute_free_mask(sim.num_nodes, &sim.immovable_boundary, &sim.constraints);
+        if let Some(settings) = state.explicit {
+            sim.integrator = settings.integrator;
+            sim.damping = settings.damping;
+            sim.frame_dt = settings.frame_dt;
         }
+        sim.sim_mesh.vertices = state.positions;
+        sim.velocities = state.velocities;
+        sim.t = state.t;
+        Ok(sim)
     }
-    
+
     pub fn compute_stress_tensors(&self) -> Vec<Vec<Array2<f64>>> {
+        self.compute_stress_tensors_at(&self.sim_mesh.vertices, Some(&self.velocities))
+    }
+
+    fn compute_stress_tensors_at(&self,
+        positions: &Array2<f64>,
+        velocities: Option<&Array2<f64>>) -> Vec<Vec<Array2<f64>>> {
+        // the viscous stress is only added if velocities are given
+        let velocities = velocities.filter(|_| self.damping.viscosity > 0.0);
+
         // compute stress tensors in parallel across nodes
         (0..self.num_nodes).into_par_iter()
             .map(|node_idx| {
@@ -134,9 +182,9 @@ impl CauchyFVM {
                         let j = triangle[1];
                         let k = triangle[2];
 
-                        let pi = self.sim_mesh.vertices.row(i);
-                        let pj = self.sim_mesh.vertices.row(j);
-                        let pk = self.sim_mesh.vertices.row(k);
+                        let pi = positions.row(i);
+                        let pj = positions.row(j);
+                        let pk = positions.row(k);
 
                         let gij = &pj - &pi;
                         let gik = &pk - &pi;
@@ -145,22 +193,33 @@ impl CauchyFVM {
                         let inv_d0_elem = &self.inv_d0[node_idx][local_tri_idx];
 
                         let fe = d0_elem.dot(inv_d0_elem);
-                        let ee: Array2<f64> = 0.5 * (&fe.t().dot(&fe) - Array::eye(2));
-                        // compute second Piola-Kirchoff stress tensor
-                        let se: Array2<f64> = (self.lambda * ee.trace().expect("LinAlg Error! Could not find trace")) * Array::eye(2) + (2.0 * self.mu * ee);
                         // compute first Piola-Kirchoff stress tensor
-                        let pe: Array2<f64> = fe.dot(&se);
-
-                        pe
+                        let pe = self.model.first_piola(&fe, self.lambda, self.mu);
+
+                        match velocities {
+                            Some(velocities) => {
+                                // Kelvin-Voigt viscous stress P_v = F (2 eta dE/dt), with the
+                                // strain rate dE/dt = 1/2 (dF/dt^T F + F^T dF/dt)
+                                let vij = &velocities.row(j) - &velocities.row(i);
+                                let vik = &velocities.row(k) - &velocities.row(i);
+                                let fe_rate = stack![Axis(1), vij, vik].dot(inv_d0_elem);
+                                let fe_rate_ft = fe_rate.t().dot(&fe);
+                                let strain_rate = 0.5 * (&fe_rate_ft + &fe_rate_ft.t());
+                                pe + fe.dot(&(2.0 * self.damping.viscosity * strain_rate))
+                            },
+                            None => pe,
+                        }
                     })
                 .collect::<Vec<_>>()
             })
         .collect()
     }
 
-    fn compute_elastic_forces(&self) -> Array2<f64> {
+    fn compute_elastic_forces(&self,
+        positions: &Array2<f64>,
+        velocities: Option<&Array2<f64>>) -> Array2<f64> {
         let num_nodes = self.num_nodes;
-        let stress_tensors = Self::compute_stress_tensors(self);
+        let stress_tensors = self.compute_stress_tensors_at(positions, velocities);
         
         // compute elastic forces in parallel across nodes
         let elastic_forces: Vec<Array1<f64>> = (0..num_nodes).into_par_iter()
@@ -191,80 +250,162 @@ impl CauchyFVM {
         elastic_forces
     }
     
-    fn compute_total_forces (&self) -> Array2<f64> { 
-        let elastic_forces = Self::compute_elastic_forces(self);
+    fn compute_damping_forces(&self, positions: &Array2<f64>, velocities: &Array2<f64>) -> Array2<f64> {
+        // mass proportional damping -alpha M v
+        let masses = self.nodal_masses.view().insert_axis(Axis(1));
+        let mut damping_forces = -self.damping.mass * &masses * velocities;
+
+        // Stiffness proportional damping -beta K v. The stiffness K = -df/dx is never assembled, its
+        // product with v is the finite difference of the elastic forces in the direction of v.
+        let v_norm = velocities.iter().map(|v| v * v).sum::<f64>().sqrt();
+        if self.damping.stiffness > 0.0 && v_norm > 0.0 {
+            let x_norm = positions.iter().map(|x| x * x).sum::<f64>().sqrt();
+            let eps = f64::EPSILON.sqrt() * (1.0 + x_norm) / v_norm;
+            let forces = self.compute_elastic_forces(positions, None);
+            let perturbed_forces = self.compute_elastic_forces(&(positions + &(eps * velocities)), None);
+            damping_forces = damping_forces + (self.damping.stiffness / eps) * (perturbed_forces - forces);
+        }
+        damping_forces
+    }
 
-        // compute total forces in parallel across nodes
-        let total_forces: Vec<Array1<f64>> = (0..self.num_nodes).into_par_iter()
-            .map(|node_idx| {
-                let cv = &self.control_volumes[node_idx];
+    fn compute_total_forces (&self, positions: &Array2<f64>, velocities: &Array2<f64>, t: f64) -> Array2<f64> { 
+        let elastic_forces = self.compute_elastic_forces(positions, Some(velocities));
+        let damping_forces = self.compute_damping_forces(positions, velocities);
+        let external_forces = self.compute_external_forces(positions, t);
+
+        elastic_forces + damping_forces + external_forces
+    }
 
-                let mut traction_force = Array1::<f64>::zeros(2); 
+    fn compute_external_forces(&self, positions: &Array2<f64>, t: f64) -> Array2<f64> {
+        let body_accelerations = self.body_forces.accelerations(positions, t);
+
+        // compute body forces in parallel across nodes
+        let external_forces: Vec<Array1<f64>> = (0..self.num_nodes).into_par_iter()
+            .map(|node_idx| {
                 let mut penalty_force = Array1::<f64>::zeros(2); 
-                if self.traction_boundary.contains(&node_idx) {
-                    let force_array = &self.traction_force_vector * cv.area;
-                    traction_force.assign(&force_array);  
-                }
                 /*
                 if self.sim_mesh.vertices[[node_idx, 1]] < -500.5 {
                     let node_pos = array![0.0, self.sim_mesh.vertices[[node_idx, 1]]];
                     let penetration = -1.0 * node_pos;
-                    penalty_force = 1e7 * penetration * cv.area; // stiffness * penetration
+                    penalty_force = 1e7 * penetration * self.control_volumes[node_idx].area; // stiffness * penetration
                 }
                 */
-                let gravity = array![0.0, -9.8e2] * cv.area;
-                &elastic_forces.row(node_idx) + &traction_force + &gravity// + &penalty_force
+                // gravity and body forces rho * b * area
+                let body_force = &body_accelerations.row(node_idx) * self.nodal_masses[node_idx];
+                body_force// + &penalty_force
             })
             .collect();
         
         // Convert back to Array2
-        let total_forces = Array2::from_shape_vec((self.num_nodes, 2),
-        total_forces.into_iter().flat_map(|row| row.into_iter()).collect())
+        let mut external_forces = Array2::from_shape_vec((self.num_nodes, 2),
+        external_forces.into_iter().flat_map(|row| row.into_iter()).collect())
             .unwrap();    
-        total_forces
+
+        // edge tractions, overlapping regions add up
+        for traction in self.tractions.iter() {
+            traction.add_nodal_forces(&self.material_coords, positions, t, &mut external_forces);
+        }
+        external_forces
     }
 
-    fn compute_velocities(&self) -> Array2<f64> {     
-        let forces = Self::compute_total_forces(&self);
+    fn compute_nodal_masses(control_volumes: &Vec<MedianCentroidControlVolume>, material: &Material) -> Array1<f64> {
+        control_volumes.iter()
+            .map(|cv| material.rho * cv.area)
+            .collect()
+    }
 
-        // compute nodal velocities in parallel across nodes
-        let velocities: Vec<Array1<f64>> = (0..self.num_nodes).into_par_iter()
-            .map(|node_idx| {
-                let cv = &self.control_volumes[node_idx];
-                let nodal_mass = self.material.rho * cv.area;
-                let old_velocity = self.velocities.row(node_idx);
-                if self.immovable_boundary.contains(&node_idx) {
-                    array![0.0, 0.0]
-                } else {
-                    &old_velocity + (self.dt / nodal_mass) * &forces.row(node_idx)
-                } 
-            })
-            .collect();
+    fn apply_material(&mut self, material: Material) -> () {
+        let (lambda, mu) = material.lame_parameters();
 
-        // Convert back to Array2
-        let velocities = Array2::from_shape_vec((self.num_nodes, 2),
-        velocities.into_iter().flat_map(|row| row.into_iter()).collect())
-            .unwrap();    
-        velocities
+        self.nodal_masses = Self::compute_nodal_masses(&self.control_volumes, &material);
+        self.material = material;
+        self.lambda = lambda;
+        self.mu = mu;
     }
 
-    pub fn update(&mut self) -> () { 
-        // compute new velocities
-        self.velocities = self.compute_velocities();
-        
-        // set new vertex positions
-        for node_idx in 0..self.num_nodes {
-            let pos_update = &self.sim_mesh.vertices.row(node_idx) + (&self.velocities.row(node_idx) * self.dt);
-            self.sim_mesh.vertices.row_mut(node_idx).assign(&pos_update);
-        }
+    fn compute_min_edge_length(control_volumes: &Vec<MedianCentroidControlVolume>) -> f64 {
+        control_volumes.iter()
+            .flat_map(|cv| cv.lij.iter().chain(cv.lik.iter()))
+            .fold(f64::INFINITY, |min_length, &length| min_length.min(length))
+    }
+
+    pub fn set_integrator(&mut self, integrator: Integrator) -> () {
+        self.integrator = integrator;
+    }
+
+    pub fn stable_timestep(&self) -> f64 {
+        // CFL bound for the explicit integrators: an elastic wave must not cross the smallest
+        // element in a single timestep
+        let undamped_dt = self.min_edge_length / self.material.p_wave_speed();
+
+        // damping shrinks the stable timestep of the highest mode, omega_max = 2 / undamped_dt,
+        // to 2 / omega_max (sqrt(1 + xi^2) - xi) with its damping ratio xi
+        let xi = self.damping.damping_ratio(2.0 / undamped_dt, self.mu);
+        CFL_NUMBER * undamped_dt * ((1.0 + xi * xi).sqrt() - xi)
+    }
+
+    pub fn set_damping(&mut self, damping: Damping) -> () {
+        self.damping = damping;
+    }
+
+    pub fn damping(&self) -> Damping {
+        self.damping
+    }
+
+    pub fn dt(&self) -> f64 {
+        self.dt
+    }
+
+    pub fn set_dt(&mut self, dt: f64) -> () {
+        self.dt = dt;
+    }
+
+    pub fn set_substepping(&mut self, frame_dt: Option<f64>) -> () {
+        // with Some(frame_dt), every update() advances the simulation by frame_dt using as many
+        // equal substeps as needed to stay below the stable timestep
+        self.frame_dt = frame_dt;
+    }
+
+    pub fn solve_static(&mut self, tolerance: f64, max_iters: usize) -> Result<StaticSolution, SimError> {
+        // Moves the mesh into static equilibrium under the loads and boundary conditions at the current time with
+        // dynamic relaxation, starting from the current positions. The simulation ends up at rest and
+        // its time is left unchanged. Converges when the norm of the free residual forces is below
+        // tolerance times the largest norm of the external loads, internal forces and reactions.
+        let external_forces = self.compute_external_forces(&self.sim_mesh.vertices, self.t);
+
+        // the prescribed displacements are held at their current value
+        apply_prescribed_displacements(&mut self.sim_mesh.vertices, &self.material_coords,
+            &self.immovable_boundary, &self.constraints, self.t);
+
+        // the undamped stable timestep, since the relaxation ignores damping
+        let dt = CFL_NUMBER * self.min_edge_length / self.material.p_wave_speed();
+        let solution = dynamic_relaxation(self, &self.sim_mesh.vertices, self.t, dt, &external_forces, tolerance, max_iters);
+
+        self.sim_mesh.vertices = solution.vertices.clone();
+        self.velocities.fill(0.0);
+
+        validate_state(&self.sim_mesh.triangles, &self.sim_mesh.vertices, &self.velocities, self.model, self.t)?;
+        Ok(solution)
+    }
+
+    fn step(&mut self, dt: f64) -> Result<(), SimError> {
+        // compute new vertex positions and velocities with the chosen integrator
+        let (positions, velocities) = self.integrator.step(self, &self.sim_mesh.vertices, &self.velocities, self.t, dt)?;
+        self.sim_mesh.vertices = positions;
+        self.velocities = velocities;
         
         // step forward in time
-        self.t += self.dt;
+        self.t += dt;
+
+        apply_prescribed_displacements(&mut self.sim_mesh.vertices, &self.material_coords,
+            &self.immovable_boundary, &self.constraints, self.t);
+
+        validate_state(&self.sim_mesh.triangles, &self.sim_mesh.vertices, &self.velocities, self.model, self.t)
     }
 
     fn precompute_d0_invs(num_nodes: usize,
         sim_mesh: &TriangleMesh,
-        control_volumes: &Vec<MedianCentroidControlVolume>) -> Vec<Vec<Array2<f64>>> {
+        control_volumes: &Vec<MedianCentroidControlVolume>) -> Result<Vec<Vec<Array2<f64>>>, SimError> {
         let mut inv_d0 = Vec::<Vec<Array2<f64>>>::new();
         
         for node_idx in 0..num_nodes { 
@@ -287,206 +428,219 @@ impl CauchyFVM {
 
                 let d0_elem = stack![Axis(1), gij, gik]; // column stack gij and gik to
                                                                  // form D0
-                inv_d0_elements.push(d0_elem.inv().expect("LinAlg Error! Matrix not invertible.")); // invert D0_e
+                // the reference triangle must have a positive area for D0 to be invertible
+                if gij[0] * gik[1] - gik[0] * gij[1] <= 0.0 {
+                    return Err(SimError::DegenerateTriangle { triangle: tri_id });
+                }
+                let inv_d0_elem = d0_elem.inv().map_err(|_| SimError::DegenerateTriangle { triangle: tri_id })?;
+                inv_d0_elements.push(inv_d0_elem); // invert D0_e
                 
             }
             inv_d0.push(inv_d0_elements); // append all D_0^{-1} for the elements of this node
         }
-        inv_d0
+        Ok(inv_d0)
     }
-    
-    pub fn set_material(&mut self, name: &str) -> () {
-        let material = MATERIALS.get(name).unwrap().clone();
-        let lambda = (material.young_modulus * material.nu) / ((1.0+material.nu)*(1.0-2.0*material.nu));
-        let mu = material.young_modulus / (2.0 * (1.0+material.nu));
+}
 
-        self.material = material;
-        self.lambda = lambda;
-        self.mu = mu;
+impl Simulator for CauchyFVM {
+    fn update(&mut self) -> Result<(), SimError> { 
+        match self.frame_dt {
+            Some(frame_dt) => {
+                // the implicit integrator is not bound by the CFL condition
+                let max_dt = match self.integrator {
+                    Integrator::ImplicitEuler => frame_dt,
+                    _ => self.stable_timestep(),
+                };
+                let substeps = (frame_dt / max_dt).ceil().max(1.0) as usize;
+                for _ in 0..substeps {
+                    self.step(frame_dt / substeps as f64)?;
+                }
+                Ok(())
+            },
+            None => self.step(self.dt),
+        }
     }
 
-    pub fn set_traction_force(&mut self, force_vector: Array1<f64>) -> () {
-        self.traction_force_vector = force_vector;
-    }
-    
-    pub fn set_traction_boundary(&mut self, boundary_name: &str) -> () {
-        match boundary_name {
-            "right" => self.traction_boundary = (0..self.material_coords.nrows())
-                .filter_map(|node_idx| {
-                    if self.material_coords[[node_idx, 0]] > 2.99 {
-                        Some(node_idx)
-                    } else {
-                        None
-                    }
-                })
-                .collect(),
-            "down" => self.traction_boundary = (0..self.material_coords.nrows())
-                .filter_map(|node_idx| {
-                    if self.material_coords[[node_idx, 1]] < -0.99 {
-                        Some(node_idx)
-                    } else {
-                        None
-                    }
-                })
-                .collect(),
-            "up" => self.traction_boundary = (0..self.material_coords.nrows())
-                    .filter_map(|node_idx| {
-                        if self.material_coords[[node_idx, 1]] > 0.99 {
-                            Some(node_idx)
-                        } else {
-                            None
-                        }
-                    })
-                .collect(),
-            _ => ()
+    fn check_timestep(&self) -> Result<(), SimError> {
+        // substepping stays below the stable timestep by itself
+        let stable_dt = self.stable_timestep();
+        match self.frame_dt.is_none() && self.integrator != Integrator::ImplicitEuler && self.dt > stable_dt {
+            true => Err(SimError::UnstableTimestep { dt: self.dt, stable_dt }),
+            false => Ok(()),
         }
     }
-    
-    pub fn set_immovable_boundary(&mut self, boundary_name: &str) -> () {
-        match boundary_name {
-            "left" => self.immovable_boundary = (0..self.material_coords.nrows())
-                .filter_map(|node_idx| {
-                    if self.material_coords[[node_idx, 0]] < -2.99 {
-                        Some(node_idx)
-                    } else {
-                        None
-                    }
-                })
-                .collect(),
-            "leftright" => self.immovable_boundary = (0..self.material_coords.nrows())
-                .filter_map(|node_idx| {
-                    if self.material_coords[[node_idx, 0]] < -2.99
-                        || self.material_coords[[node_idx, 0]] > 2.99 {
-                            Some(node_idx)
-                        } else {
-                            None
-                        }
-                })
-                .collect(),
-            _ => println!("Error: this boundary does not exist! \n
-                Immovable boundaries: {{ 'left', 'leftright' }}")
 
-        }
+    fn set_material(&mut self, name: &str) -> Result<(), SimError> {
+        self.apply_material(Material::from_name(name)?);
+        Ok(())
     }
 
-    pub fn benchmark(&mut self, iters: usize) -> () {
-        // simple benchmarking function for checking how many updates per second we can get
-        let now = Instant::now();
-        let mut last_elapsed: u128 = 0;
+    fn set_immovable_boundary(&mut self, region: &Region) -> Result<(), SimError> {
+        self.immovable_boundary = select_nodes(region, &self.sim_mesh, &self.material_coords)?;
+        self.free_mask = compute_free_mask(self.num_nodes, &self.immovable_boundary, &self.constraints);
+        Ok(())
+    }
 
-        for i in 0..iters {
-            self.update();
+    fn clear_immovable_boundary(&mut self) -> () {
+        self.immovable_boundary.clear();
+        self.free_mask = compute_free_mask(self.num_nodes, &self.immovable_boundary, &self.constraints);
+    }
 
-            if i % 100 == 0 {
-                let elapsed_millisecs = now.elapsed().as_millis() - last_elapsed;
-                last_elapsed += elapsed_millisecs;
-                println!("updates/sec: {}", (1.0)/((elapsed_millisecs) as f64) * 100.0 * 1000.0);
-            }
-        }
+    fn add_traction(&mut self, region: &Region, force_vector: Array1<f64>) -> Result<usize, SimError> {
+        let edges = select_boundary_edges(region, &self.sim_mesh, &self.material_coords)?;
+        self.tractions.push(Traction::new(edges, force_vector));
+        Ok(self.tractions.len() - 1)
     }
 
-    pub fn display_on_paintable(&self, paintable: &Paintable) -> () {
+    fn add_pressure(&mut self, region: &Region, pressure: f64) -> Result<usize, SimError> {
+        let edges = select_boundary_edges(region, &self.sim_mesh, &self.material_coords)?;
+        self.tractions.push(Traction::pressure(edges, pressure));
+        Ok(self.tractions.len() - 1)
+    }
 
-        let backend = PaintableBackend::new(paintable);
-        let root = backend.into_drawing_area();
-        root.fill(&BLACK).unwrap();
+    fn set_traction_force(&mut self, traction_idx: usize, force_vector: Array1<f64>) -> Result<(), SimError> {
+        self.tractions.get_mut(traction_idx).ok_or(SimError::InvalidTraction(traction_idx))?.force = force_vector;
+        Ok(())
+    }
 
-        let mut chart = ChartBuilder::on(&root)
-            .caption(format!("Beam mesh, t={time:.*}s", 3, time=self.t), ("sans-serif", 12, &WHITE))
-            .build_cartesian_2d(-40.0..60.0, -40.0..40.0)
-            .unwrap();
-         
-        let mesh = &self.sim_mesh;
-        let material_coords = &self.material_coords;
-
-        for tri in mesh.triangles.outer_iter() {
-            let triangle = vec![
-                (mesh.vertices[[tri[0], 0]], mesh.vertices[[tri[0], 1]]),
-                (mesh.vertices[[tri[1], 0]], mesh.vertices[[tri[1], 1]]),
-                (mesh.vertices[[tri[2], 0]], mesh.vertices[[tri[2], 1]]),
-                (mesh.vertices[[tri[0], 0]], mesh.vertices[[tri[0], 1]]), // close loop
-            ];
-
-            chart
-                .draw_series(std::iter::once(PathElement::new(triangle, &WHITE)))
-                .unwrap();
-            }
-        
-        // draw immovable boundary surface as a different color
-        let mut boundary_left_vec = Vec::<(f64, f64)>::new();
-        for node_idx in 0..mesh.vertices.nrows() {
-            if self.immovable_boundary.contains(&node_idx) && material_coords[[node_idx, 0]] < -2.99 {
-                boundary_left_vec.push((mesh.vertices[[node_idx, 0]], mesh.vertices[[node_idx, 1]]));
-            }
-        }
-        
-        let mut boundary_right_vec = Vec::<(f64, f64)>::new();
-        for node_idx in 0..mesh.vertices.nrows() {
-            if self.immovable_boundary.contains(&node_idx) && material_coords[[node_idx, 0]] > 2.99 {
-                boundary_right_vec.push((mesh.vertices[[node_idx, 0]], mesh.vertices[[node_idx, 1]]));
-            }
-        }
+    fn set_traction_pressure(&mut self, traction_idx: usize, pressure: f64) -> Result<(), SimError> {
+        self.tractions.get_mut(traction_idx).ok_or(SimError::InvalidTraction(traction_idx))?.pressure = pressure;
+        Ok(())
+    }
 
-        chart 
-            .draw_series(std::iter::once(PathElement::new(boundary_left_vec, &CYAN)))
-            .unwrap();  
-        chart 
-            .draw_series(std::iter::once(PathElement::new(boundary_right_vec, &CYAN)))
-            .unwrap();  
-
-        // draw traction surface as a different color
-        let mut traction_vec = Vec::<(f64, f64)>::new();
-        for node_idx in 0..mesh.vertices.nrows() {
-            if self.traction_boundary.contains(&node_idx) {
-                traction_vec.push((mesh.vertices[[node_idx, 0]], mesh.vertices[[node_idx, 1]]));
-            }
-        }
+    fn set_traction_curve(&mut self, traction_idx: usize, curve: LoadCurve) -> Result<(), SimError> {
+        self.tractions.get_mut(traction_idx).ok_or(SimError::InvalidTraction(traction_idx))?.curve = curve;
+        Ok(())
+    }
 
-        chart 
-            .draw_series(std::iter::once(PathElement::new(traction_vec, &RED)))
-            .unwrap();  
-        
-        // draw velocity vectors       
-        chart 
-            .draw_series((0..self.num_nodes)
-                .into_iter()
-                .map(|node_idx| {
-                    let arrow_size = 0.15;
-                    let x = mesh.vertices[[node_idx, 0]];
-                    let y = mesh.vertices[[node_idx, 1]];
-                    let dx = arrow_size * self.velocities[[node_idx, 0]];
-                    let dy = arrow_size * self.velocities[[node_idx, 1]];
-                    ThinArrow::new((x, y), (x + dx, y + dy), &GREEN)
-                }))
-                .unwrap();
-        
-        // draw force vectors        
-        chart 
-            .draw_series(self.traction_boundary
-                .clone()
-                .into_iter()
-                .map(|node_idx| {
-                    let arrow_size = 1e-5;
-                    let x = mesh.vertices[[node_idx, 0]];
-                    let y = mesh.vertices[[node_idx, 1]];
-                    let dx = arrow_size * self.traction_force_vector[0];
-                    let dy = arrow_size * self.traction_force_vector[1];
-                    ThinArrow::new((x, y), (x + dx, y + dy), &RED)
-                }))
-                .unwrap();
-        
-        // draw floor
-        let x_values = vec![-6.0, 6.0];
-        chart.draw_series(LineSeries::new(x_values.into_iter().map(|x| (x, -3.5)), &WHITE)).unwrap();
+    fn clear_tractions(&mut self) -> () {
+        self.tractions.clear();
+    }
 
-        root.present().unwrap();
+    fn set_gravity(&mut self, gravity: Array1<f64>) -> () {
+        self.body_forces.gravity = gravity;
+    }
+
+    fn gravity(&self) -> &Array1<f64> {
+        &self.body_forces.gravity
     }
-}
 
-lazy_static! {
-    static ref MATERIALS: HashMap<&'static str, Material> = HashMap::from([
-            ("default", Material {young_modulus: 10e5, nu: 0.3, rho: 1000.0}),
-            ("rubber", Material {young_modulus: 0.01e9, nu: 0.48, rho: 1050.0}),
-        ]);
+    fn set_gravity_curve(&mut self, curve: LoadCurve) -> () {
+        self.body_forces.gravity_curve = curve;
+    }
+
+    fn add_body_force(&mut self, body_force: BodyForce) -> usize {
+        self.body_forces.fields.push(body_force);
+        self.body_forces.fields.len() - 1
+    }
+
+    fn clear_body_forces(&mut self) -> () {
+        self.body_forces.fields.clear();
+    }
+
+    fn body_forces(&self) -> &BodyForces {
+        &self.body_forces
+    }
+
+    fn add_constraint(&mut self, region: &Region, axes: Axes, motion: Motion) -> Result<usize, SimError> {
+        let nodes = select_nodes(region, &self.sim_mesh, &self.material_coords)?;
+        self.constraints.push(Constraint { nodes, axes, motion });
+        self.free_mask = compute_free_mask(self.num_nodes, &self.immovable_boundary, &self.constraints);
+        Ok(self.constraints.len() - 1)
+    }
+
+    fn clear_constraints(&mut self) -> () {
+        self.constraints.clear();
+        self.free_mask = compute_free_mask(self.num_nodes, &self.immovable_boundary, &self.constraints);
+    }
+
+    fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
+    fn set_constitutive_model(&mut self, model: ConstitutiveModel) -> () {
+        self.model = model;
+    }
+
+    fn constitutive_model(&self) -> ConstitutiveModel {
+        self.model
+    }
+
+    fn time(&self) -> f64 {
+        self.t
+    }
+
+    fn mesh(&self) -> &TriangleMesh {
+        &self.sim_mesh
+    }
+
+    fn material_coords(&self) -> &Array2<f64> {
+        &self.material_coords
+    }
+
+    fn velocities(&self) -> &Array2<f64> {
+        &self.velocities
+    }
+
+    fn forces(&self) -> Array2<f64> {
+        self.compute_total_forces(&self.sim_mesh.vertices, &self.velocities, self.t)
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn lumped_masses(&self) -> Array1<f64> {
+        self.nodal_masses.clone()
+    }
+
+    fn tractions(&self) -> &[Traction] {
+        &self.tractions
+    }
+
+    fn immovable_boundary(&self) -> &[usize] {
+        &self.immovable_boundary
+    }
+
+    fn checkpoint_state(&self) -> Result<SimulatorState, CheckpointError> {
+        Ok(SimulatorState {
+            kind: SimulatorKind::Fvm,
+            material_coords: self.material_coords.clone(),
+            triangles: self.sim_mesh.triangles.clone(),
+            edge_tags: self.sim_mesh.edge_tags.clone(),
+            positions: self.sim_mesh.vertices.clone(),
+            velocities: self.velocities.clone(),
+            t: self.t,
+            dt: self.dt,
+            material: self.material.clone(),
+            model: self.model,
+            immovable_boundary: self.immovable_boundary.clone(),
+            constraints: self.constraints.clone(),
+            tractions: self.tractions.clone(),
+            body_forces: self.body_forces.clone(),
+            explicit: Some(ExplicitSettings {
+                integrator: self.integrator,
+                damping: self.damping,
+                frame_dt: self.frame_dt,
+            }),
+        })
+    }
 }
 
+impl DynamicSystem for CauchyFVM {
+    fn total_forces(&self, positions: &Array2<f64>, velocities: &Array2<f64>, t: f64) -> Array2<f64> {
+        self.compute_total_forces(positions, velocities, t)
+    }
+
+    fn nodal_masses(&self) -> &Array1<f64> {
+        &self.nodal_masses
+    }
+
+    fn free_mask(&self) -> &Array2<f64> {
+        &self.free_mask
+    }
+
+    fn constrained_velocities(&self, t: f64) -> Array2<f64> {
+        compute_constrained_velocities(self.num_nodes, &self.immovable_boundary, &self.constraints, t)
+    }
+}
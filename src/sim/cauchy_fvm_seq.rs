@@ -3,38 +3,53 @@
 use ndarray::prelude::*;
 use crate::mesh::*;
 use crate::cv::*;
+use crate::sim::material::*;
+use crate::sim::constitutive::ConstitutiveModel;
+use crate::sim::boundary::*;
+use crate::sim::load::LoadCurve;
+use crate::sim::body_force::*;
+use crate::sim::{Simulator, SimulatorKind};
+use crate::sim::checkpoint::*;
+use crate::sim::error::*;
 use ndarray_linalg::Inverse;
 use ndarray::stack;
-use ndarray_linalg::Trace;
-use std::time::Instant;
-use rayon::prelude::*;
 
 pub struct CauchyFVM {
     // holds data for a simulator based on the FVM applied to Cauchy's equation
     num_nodes: usize,
     pub sim_mesh: TriangleMesh,
+    material_coords: Array2<f64>,
     control_volumes: Vec<MedianCentroidControlVolume>, 
     t: f64, // current time
     dt: f64, // delta time
     
     // material parameters
-    young_modulus: f64, // Young's modulus, E
-    nu: f64,            // Poisson ratio
-    rho: f64,           // material density
+    material: Material,
     lambda: f64,        // First Lamé coefficient
     mu: f64,            // Second Lamé coefficient
+    model: ConstitutiveModel, // hyperelastic model giving the stress for a deformation gradient
     
     // precomputed D_0 matrix inverses for all nodes and their elements
     inv_d0: Vec<Vec<Array2<f64>>>, 
     
+    // traction regions, each with its own force vector
+    tractions: Vec<Traction>,
+    // gravity and body force fields per unit mass
+    body_forces: BodyForces,
+
+    immovable_boundary: Vec<usize>,
+    // per axis and prescribed motion constraints
+    constraints: Vec<Constraint>,
+
     // Holds current velocity for each node
     velocities: Array2<f64>,
 }
 
 impl CauchyFVM {
-    pub fn new(mesh: &TriangleMesh) -> CauchyFVM {
+    pub fn new(mesh: &TriangleMesh, material_name: &str, dt: f64) -> Result<CauchyFVM, SimError> {
         let num_nodes = mesh.vertices.nrows();
         let sim_mesh = mesh.clone();
+        let material_coords = mesh.vertices.clone(); // material coordinates
         
         let mut control_volumes = Vec::<MedianCentroidControlVolume>::new();
         
@@ -43,41 +58,72 @@ impl CauchyFVM {
             control_volumes.push(MedianCentroidControlVolume::new(node_idx, &sim_mesh));
         }
         
-        // set time and delta time (hardcoded for now)
+        // set time and delta time
         let t = 0.0;
-        let dt = 0.001;
-
-        // set material parameters (hardcoded for now)
-        //let young_modulus = 10e5;
-        //let nu = 0.3;
-        //let rho = 1000.0;
-        let young_modulus = 0.01e9;
-        let nu = 0.48;
-        let rho = 1050.0;
-
-        let lambda = (young_modulus * nu) / ((1.0+nu)*(1.0-2.0*nu));
-        let mu = young_modulus / (2.0 * (1.0+nu));
+        let dt = dt;
+
+        // set material parameters
+        let material = Material::from_name(material_name)?;
+        let (lambda, mu) = material.lame_parameters();
+        let model = ConstitutiveModel::StVenantKirchhoff;
         
         // precompute (D_0)^{-1} 
-        let inv_d0 = Self::precompute_d0_invs(num_nodes, &sim_mesh, &control_volumes);
+        let inv_d0 = Self::precompute_d0_invs(num_nodes, &sim_mesh, &control_volumes)?;
+
+        // by default the right side is pulled down, if it has boundary edges, and the left side is clamped
+        let tractions = select_boundary_edges(&Region::Side(Side::Right), &sim_mesh, &material_coords)
+            .map(|edges| vec![Traction::new(edges, arr1(&DEFAULT_TRACTION))])
+            .unwrap_or_default();
+        let body_forces = BodyForces::default();
+
+        // set of nodes on the immovable boundary
+        let immovable_boundary = select_nodes(&Region::Side(Side::Left), &sim_mesh, &material_coords)?;
+        let constraints = Vec::<Constraint>::new();
 
         // initial velocity is (0,0) for all nodes
         let velocities = Array2::<f64>::zeros((num_nodes, 2));
 
-        CauchyFVM {
+        Ok(CauchyFVM {
             num_nodes,
             sim_mesh,
+            material_coords,
             control_volumes,
             dt,
             t, 
-            young_modulus,
-            nu,
-            rho,
+            material,
             lambda,
             mu,
+            model,
             inv_d0,
+            tractions,
+            body_forces,
+            immovable_boundary,
+            constraints,
             velocities,
-        }
+        })
+    }
+
+    pub fn from_checkpoint_state(state: SimulatorState) -> Result<CauchyFVM, CheckpointError> {
+        // rebuilds the simulator on the reference mesh and restores its state
+        let mut sim = CauchyFVM::new(&state.reference_mesh()?, "default", state.dt)?;
+        sim.apply_material(state.material);
+        sim.model = state.model;
+        sim.tractions = state.tractions;
+        sim.body_forces = state.body_forces;
+        sim.immovable_boundary = state.immovable_boundary;
+        sim.constraints = state.constraints;
+        sim.sim_mesh.vertices = state.positions;
+        sim.velocities = state.velocities;
+        sim.t = state.t;
+        Ok(sim)
+    }
+
+    fn apply_material(&mut self, material: Material) -> () {
+        let (lambda, mu) = material.lame_parameters();
+
+        self.material = material;
+        self.lambda = lambda;
+        self.mu = mu;
     }
     
     pub fn compute_stress_tensors(&self) -> Vec<Vec<Array2<f64>>> {
@@ -107,10 +153,8 @@ impl CauchyFVM {
                 let inv_d0_elem = &self.inv_d0[node_idx][local_tri_idx];
 
                 let fe = d0_elem.dot(inv_d0_elem); // Matrix F^e (2 x 2)
-                let ee: Array2<f64> = 0.5 * (&fe.t().dot(&fe) - Array::eye(2)); // Matrix E^e (2 x 2)
-                let se = (self.lambda * &ee.trace().expect("LinAlg Error! Could not find trace")) * Array::eye(2) + (2.0 * self.mu * &ee); // Matrix S^e (2 x 2)
 
-                let pe = fe.dot(&se); // Stress tensor for current triangle element: Matrix P^e (2 x 2)
+                let pe = self.model.first_piola(&fe, self.lambda, self.mu); // Stress tensor for current triangle element: Matrix P^e (2 x 2)
                 stress_tensors_elements.push(pe);
             }
             stress_tensors.push(stress_tensors_elements);
@@ -144,16 +188,18 @@ impl CauchyFVM {
     fn compute_total_forces (&self) -> Array2<f64> { 
         let mut total_forces = Array2::<f64>::zeros((self.num_nodes, 2));
         let elastic_forces = Self::compute_elastic_forces(self);
+        let body_accelerations = self.body_forces.accelerations(&self.sim_mesh.vertices, self.t);
+
+        // edge tractions
+        for traction in self.tractions.iter() {
+            traction.add_nodal_forces(&self.material_coords, &self.sim_mesh.vertices, self.t, &mut total_forces);
+        }
         
         for node_idx in 0..self.num_nodes {
             let cv = &self.control_volumes[node_idx];
             
-            let mut traction_force = Array1::<f64>::zeros(2);
-            if *&self.sim_mesh.vertices[[node_idx, 0]] > 2.99 { // hardcoded traction boundary
-                let force_array = array![0.0, -10e4] * cv.area;
-                traction_force.assign(&force_array);  
-            }
-            let add_row = &total_forces.row(node_idx) + traction_force + elastic_forces.row(node_idx);
+            let body_force = &body_accelerations.row(node_idx) * (self.material.rho * cv.area);
+            let add_row = &total_forces.row(node_idx) + body_force + elastic_forces.row(node_idx);
             total_forces.row_mut(node_idx).assign(&add_row);
             
         }
@@ -165,31 +211,27 @@ impl CauchyFVM {
         let mut velocities = self.velocities.clone();
         for node_idx in 0..self.num_nodes {
             let cv = &self.control_volumes[node_idx];
-            let nodal_mass = self.rho * cv.area;
-            if *&self.sim_mesh.vertices[[node_idx, 0]] > -2.99 { // hardcoded immovable boundary
+            let nodal_mass = self.material.rho * cv.area;
+            if self.immovable_boundary.contains(&node_idx) {
+                velocities.row_mut(node_idx).fill(0.0);
+            } else {
                 let add_row = &velocities.row(node_idx) + ((self.dt/nodal_mass) * &forces.row(node_idx));
                 velocities.row_mut(node_idx).assign(&add_row);
             } 
         }
-        velocities
-    }
-
-    pub fn update(&mut self) -> () {
-        // compute new velocities
-        self.velocities = self.compute_velocities();
-        
-        // set new vertex positions
-        for node_idx in 0..self.num_nodes {
-            let pos_update = &self.sim_mesh.vertices.row(node_idx) + (&self.velocities.row(node_idx) * self.dt);
-            self.sim_mesh.vertices.row_mut(node_idx).assign(&pos_update);
+        // constrained axes move with their prescribed velocity
+        if !self.constraints.is_empty() {
+            let free_mask = compute_free_mask(self.num_nodes, &self.immovable_boundary, &self.constraints);
+            let constrained_velocities = compute_constrained_velocities(self.num_nodes,
+                &self.immovable_boundary, &self.constraints, self.t + self.dt);
+            velocities = &velocities * &free_mask + &(constrained_velocities * &(1.0 - &free_mask));
         }
-        // step forward in time
-        self.t += self.dt;
+        velocities
     }
 
     fn precompute_d0_invs(num_nodes: usize,
         sim_mesh: &TriangleMesh,
-        control_volumes: &Vec<MedianCentroidControlVolume>) -> Vec<Vec<Array2<f64>>> {
+        control_volumes: &Vec<MedianCentroidControlVolume>) -> Result<Vec<Vec<Array2<f64>>>, SimError> {
         let mut inv_d0 = Vec::<Vec<Array2<f64>>>::new();
         
         for node_idx in 0..num_nodes { 
@@ -212,28 +254,183 @@ impl CauchyFVM {
 
                 let d0_elem = stack![Axis(1), gij, gik]; // column stack gij and gik to
                                                                  // form D0
-                inv_d0_elements.push(d0_elem.inv().expect("LinAlg Error! Matrix not invertible.")); // invert D0_e
+                // the reference triangle must have a positive area for D0 to be invertible
+                if gij[0] * gik[1] - gik[0] * gij[1] <= 0.0 {
+                    return Err(SimError::DegenerateTriangle { triangle: tri_id });
+                }
+                let inv_d0_elem = d0_elem.inv().map_err(|_| SimError::DegenerateTriangle { triangle: tri_id })?;
+                inv_d0_elements.push(inv_d0_elem); // invert D0_e
                 
             }
             inv_d0.push(inv_d0_elements); // append all D_0^{-1} for the elements of this node
         }
-        inv_d0
+        Ok(inv_d0)
+    }
+}
+
+impl Simulator for CauchyFVM {
+    fn update(&mut self) -> Result<(), SimError> {
+        // compute new velocities
+        self.velocities = self.compute_velocities();
+        
+        // set new vertex positions
+        for node_idx in 0..self.num_nodes {
+            let pos_update = &self.sim_mesh.vertices.row(node_idx) + (&self.velocities.row(node_idx) * self.dt);
+            self.sim_mesh.vertices.row_mut(node_idx).assign(&pos_update);
+        }
+        // step forward in time
+        self.t += self.dt;
+
+        apply_prescribed_displacements(&mut self.sim_mesh.vertices, &self.material_coords,
+            &self.immovable_boundary, &self.constraints, self.t);
+
+        validate_state(&self.sim_mesh.triangles, &self.sim_mesh.vertices, &self.velocities, self.model, self.t)
+    }
+
+    fn set_material(&mut self, name: &str) -> Result<(), SimError> {
+        self.apply_material(Material::from_name(name)?);
+        Ok(())
     }
 
-    pub fn benchmark(&mut self, iters: usize) -> () {
-        // simple benchmarking function for checking how many updates per second we can get
-        let now = Instant::now();
-        let mut last_elapsed: u128 = 0;
+    fn set_immovable_boundary(&mut self, region: &Region) -> Result<(), SimError> {
+        self.immovable_boundary = select_nodes(region, &self.sim_mesh, &self.material_coords)?;
+        Ok(())
+    }
 
-        for i in 0..iters {
-            self.update();
+    fn clear_immovable_boundary(&mut self) -> () {
+        self.immovable_boundary.clear();
+    }
 
-            if i % 100 == 0 {
-                let elapsed_millisecs = now.elapsed().as_millis() - last_elapsed;
-                last_elapsed += elapsed_millisecs;
-                println!("updates/sec: {}", (1.0)/((elapsed_millisecs) as f64) * 100.0 * 1000.0);
-            }
-        }
+    fn add_traction(&mut self, region: &Region, force_vector: Array1<f64>) -> Result<usize, SimError> {
+        let edges = select_boundary_edges(region, &self.sim_mesh, &self.material_coords)?;
+        self.tractions.push(Traction::new(edges, force_vector));
+        Ok(self.tractions.len() - 1)
+    }
 
+    fn add_pressure(&mut self, region: &Region, pressure: f64) -> Result<usize, SimError> {
+        let edges = select_boundary_edges(region, &self.sim_mesh, &self.material_coords)?;
+        self.tractions.push(Traction::pressure(edges, pressure));
+        Ok(self.tractions.len() - 1)
+    }
+
+    fn set_traction_force(&mut self, traction_idx: usize, force_vector: Array1<f64>) -> Result<(), SimError> {
+        self.tractions.get_mut(traction_idx).ok_or(SimError::InvalidTraction(traction_idx))?.force = force_vector;
+        Ok(())
+    }
+
+    fn set_traction_pressure(&mut self, traction_idx: usize, pressure: f64) -> Result<(), SimError> {
+        self.tractions.get_mut(traction_idx).ok_or(SimError::InvalidTraction(traction_idx))?.pressure = pressure;
+        Ok(())
+    }
+
+    fn set_traction_curve(&mut self, traction_idx: usize, curve: LoadCurve) -> Result<(), SimError> {
+        self.tractions.get_mut(traction_idx).ok_or(SimError::InvalidTraction(traction_idx))?.curve = curve;
+        Ok(())
+    }
+
+    fn clear_tractions(&mut self) -> () {
+        self.tractions.clear();
+    }
+
+    fn set_gravity(&mut self, gravity: Array1<f64>) -> () {
+        self.body_forces.gravity = gravity;
+    }
+
+    fn gravity(&self) -> &Array1<f64> {
+        &self.body_forces.gravity
+    }
+
+    fn set_gravity_curve(&mut self, curve: LoadCurve) -> () {
+        self.body_forces.gravity_curve = curve;
+    }
+
+    fn add_body_force(&mut self, body_force: BodyForce) -> usize {
+        self.body_forces.fields.push(body_force);
+        self.body_forces.fields.len() - 1
+    }
+
+    fn clear_body_forces(&mut self) -> () {
+        self.body_forces.fields.clear();
+    }
+
+    fn body_forces(&self) -> &BodyForces {
+        &self.body_forces
+    }
+
+    fn add_constraint(&mut self, region: &Region, axes: Axes, motion: Motion) -> Result<usize, SimError> {
+        let nodes = select_nodes(region, &self.sim_mesh, &self.material_coords)?;
+        self.constraints.push(Constraint { nodes, axes, motion });
+        Ok(self.constraints.len() - 1)
+    }
+
+    fn clear_constraints(&mut self) -> () {
+        self.constraints.clear();
+    }
+
+    fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
+    fn set_constitutive_model(&mut self, model: ConstitutiveModel) -> () {
+        self.model = model;
+    }
+
+    fn constitutive_model(&self) -> ConstitutiveModel {
+        self.model
+    }
+
+    fn time(&self) -> f64 {
+        self.t
+    }
+
+    fn mesh(&self) -> &TriangleMesh {
+        &self.sim_mesh
+    }
+
+    fn material_coords(&self) -> &Array2<f64> {
+        &self.material_coords
+    }
+
+    fn velocities(&self) -> &Array2<f64> {
+        &self.velocities
+    }
+
+    fn forces(&self) -> Array2<f64> {
+        self.compute_total_forces()
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn lumped_masses(&self) -> Array1<f64> {
+        self.control_volumes.iter().map(|cv| self.material.rho * cv.area).collect()
+    }
+
+    fn tractions(&self) -> &[Traction] {
+        &self.tractions
+    }
+
+    fn immovable_boundary(&self) -> &[usize] {
+        &self.immovable_boundary
+    }
+    fn checkpoint_state(&self) -> Result<SimulatorState, CheckpointError> {
+        Ok(SimulatorState {
+            kind: SimulatorKind::FvmSequential,
+            material_coords: self.material_coords.clone(),
+            triangles: self.sim_mesh.triangles.clone(),
+            edge_tags: self.sim_mesh.edge_tags.clone(),
+            positions: self.sim_mesh.vertices.clone(),
+            velocities: self.velocities.clone(),
+            t: self.t,
+            dt: self.dt,
+            material: self.material.clone(),
+            model: self.model,
+            immovable_boundary: self.immovable_boundary.clone(),
+            constraints: self.constraints.clone(),
+            tractions: self.tractions.clone(),
+            body_forces: self.body_forces.clone(),
+            explicit: None,
+        })
     }
 }
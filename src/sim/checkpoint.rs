@@ -0,0 +1,723 @@
+use ndarray::prelude::*;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::mesh::TriangleMesh;
+use crate::sim::{cauchy_fem, cauchy_fvm, cauchy_fvm_seq, Simulator, SimulatorKind};
+use crate::sim::body_force::{BodyForce, BodyForces, SampledField};
+use crate::sim::boundary::{Axes, Constraint, Motion, Traction};
+use crate::sim::constitutive::ConstitutiveModel;
+use crate::sim::damping::Damping;
+use crate::sim::error::SimError;
+use crate::sim::integrator::Integrator;
+use crate::sim::load::LoadCurve;
+use crate::sim::material::Material;
+
+// Checkpoints are little-endian binary files starting with the magic bytes and the format version.
+// All floats are stored with their exact bits, so a restored simulator continues bit for bit.
+const CHECKPOINT_MAGIC: &[u8; 8] = b"SIMCKPT\0";
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    // file that is not a checkpoint or is truncated
+    InvalidFormat(String),
+    // checkpoint written by a newer format version
+    UnsupportedVersion { version: u32 },
+    // state that cannot be stored, e.g. closures of custom load curves or prescribed motions
+    NotSerializable(String),
+    // the simulator could not be rebuilt from the checkpoint
+    Sim(SimError),
+    // checkpoint of another simulator or mesh than the run it should continue
+    Mismatch(String),
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(e) => write!(f, "Could not access checkpoint: {}", e),
+            CheckpointError::InvalidFormat(message) => write!(f, "Invalid checkpoint: {}", message),
+            CheckpointError::UnsupportedVersion { version } =>
+                write!(f, "Checkpoint version {} is newer than the supported version {}", version, CHECKPOINT_VERSION),
+            CheckpointError::NotSerializable(what) => write!(f, "Cannot store {} in a checkpoint", what),
+            CheckpointError::Sim(e) => write!(f, "Could not restore simulator: {}", e),
+            CheckpointError::Mismatch(message) => write!(f, "Checkpoint does not match the run: {}", message),
+        }
+    }
+}
+
+impl Error for CheckpointError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CheckpointError::Io(e) => Some(e),
+            CheckpointError::Sim(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CheckpointError {
+    fn from(e: io::Error) -> CheckpointError {
+        CheckpointError::Io(e)
+    }
+}
+
+impl From<SimError> for CheckpointError {
+    fn from(e: SimError) -> CheckpointError {
+        CheckpointError::Sim(e)
+    }
+}
+
+// Settings of the explicit FVM solver
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExplicitSettings {
+    pub integrator: Integrator,
+    pub damping: Damping,
+    pub frame_dt: Option<f64>,
+}
+
+// Full state of a simulator, from which it is rebuilt exactly. Precomputed data such as control
+// volumes and stiffness matrices are recomputed from the reference mesh and material.
+#[derive(Clone)]
+pub struct SimulatorState {
+    pub kind: SimulatorKind,
+    pub material_coords: Array2<f64>,
+    pub triangles: Array2<usize>,
+    pub edge_tags: HashMap<String, Vec<[usize; 2]>>,
+    pub positions: Array2<f64>,
+    pub velocities: Array2<f64>,
+    pub t: f64,
+    pub dt: f64,
+    pub material: Material,
+    pub model: ConstitutiveModel,
+    pub immovable_boundary: Vec<usize>,
+    pub constraints: Vec<Constraint>,
+    pub tractions: Vec<Traction>,
+    pub body_forces: BodyForces,
+    pub explicit: Option<ExplicitSettings>, // only for the parallel FVM solver
+}
+
+impl SimulatorState {
+    // mesh in the reference configuration
+    pub fn reference_mesh(&self) -> Result<TriangleMesh, CheckpointError> {
+        TriangleMesh::from_triangles(self.material_coords.clone(), self.triangles.clone(), self.edge_tags.clone())
+            .map_err(|e| CheckpointError::InvalidFormat(e.to_string()))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), CheckpointError> {
+        let mut writer = CheckpointWriter::default();
+        writer.bytes(CHECKPOINT_MAGIC);
+        writer.u32(CHECKPOINT_VERSION);
+        self.write(&mut writer)?;
+
+        // written next to the target first, so that an interrupted save keeps the previous checkpoint
+        let path = path.as_ref();
+        let partial_path = path.with_extension("partial");
+        fs::write(&partial_path, &writer.buffer)?;
+        fs::rename(&partial_path, path)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SimulatorState, CheckpointError> {
+        let data = fs::read(path)?;
+        let mut reader = CheckpointReader { data: &data, pos: 0 };
+        if reader.bytes(CHECKPOINT_MAGIC.len())? != CHECKPOINT_MAGIC {
+            return Err(CheckpointError::InvalidFormat("missing checkpoint header".to_string()));
+        }
+        let version = reader.u32()?;
+        if version > CHECKPOINT_VERSION {
+            return Err(CheckpointError::UnsupportedVersion { version });
+        }
+        let state = Self::read(&mut reader)?;
+        if reader.pos != data.len() {
+            return Err(CheckpointError::InvalidFormat("trailing data after the state".to_string()));
+        }
+        Ok(state)
+    }
+
+    fn write(&self, w: &mut CheckpointWriter) -> Result<(), CheckpointError> {
+        w.u8(match self.kind {
+            SimulatorKind::Fvm => 0,
+            SimulatorKind::FvmSequential => 1,
+            SimulatorKind::Fem => 2,
+        });
+        w.array2(&self.material_coords);
+        w.usize(self.triangles.nrows());
+        for &v in self.triangles.iter() {
+            w.usize(v);
+        }
+        let mut tags: Vec<(&String, &Vec<[usize; 2]>)> = self.edge_tags.iter().collect();
+        tags.sort();
+        w.usize(tags.len());
+        for (tag, edges) in tags {
+            w.string(tag);
+            w.edges(edges);
+        }
+
+        w.array2(&self.positions);
+        w.array2(&self.velocities);
+        w.f64(self.t);
+        w.f64(self.dt);
+
+        w.f64(self.material.young_modulus);
+        w.f64(self.material.nu);
+        w.f64(self.material.rho);
+        w.u8(match self.model {
+            ConstitutiveModel::StVenantKirchhoff => 0,
+            ConstitutiveModel::NeoHookean => 1,
+            ConstitutiveModel::StableNeoHookean => 2,
+            ConstitutiveModel::CorotatedLinear => 3,
+            ConstitutiveModel::MooneyRivlin => 4,
+        });
+
+        w.usizes(&self.immovable_boundary);
+        w.usize(self.constraints.len());
+        for constraint in self.constraints.iter() {
+            w.usizes(&constraint.nodes);
+            w.u8(match constraint.axes {
+                Axes::X => 0,
+                Axes::Y => 1,
+                Axes::Both => 2,
+            });
+            match &constraint.motion {
+                Motion::Fixed => w.u8(0),
+                Motion::DisplacementCurve { u, curve } => {
+                    w.u8(1);
+                    w.f64(u[0]);
+                    w.f64(u[1]);
+                    w.load_curve(curve)?;
+                },
+                Motion::VelocityCurve { v, curve } => {
+                    w.u8(2);
+                    w.f64(v[0]);
+                    w.f64(v[1]);
+                    w.load_curve(curve)?;
+                },
+                Motion::Displacement(_) | Motion::Velocity(_) =>
+                    return Err(CheckpointError::NotSerializable("prescribed motion closures".to_string())),
+            }
+        }
+
+        w.usize(self.tractions.len());
+        for traction in self.tractions.iter() {
+            w.edges(&traction.edges);
+            w.array1(&traction.force);
+            w.f64(traction.pressure);
+            w.load_curve(&traction.curve)?;
+        }
+
+        w.array1(&self.body_forces.gravity);
+        w.load_curve(&self.body_forces.gravity_curve)?;
+        w.usize(self.body_forces.fields.len());
+        for field in self.body_forces.fields.iter() {
+            match field {
+                BodyForce::Grid(grid) => {
+                    w.f64(grid.origin[0]);
+                    w.f64(grid.origin[1]);
+                    w.f64(grid.spacing[0]);
+                    w.f64(grid.spacing[1]);
+                    let (ny, nx, _) = grid.values.dim();
+                    w.usize(ny);
+                    w.usize(nx);
+                    for &value in grid.values.iter() {
+                        w.f64(value);
+                    }
+                },
+                BodyForce::Field(_) => return Err(CheckpointError::NotSerializable("body force closures".to_string())),
+            }
+        }
+
+        match &self.explicit {
+            None => w.u8(0),
+            Some(settings) => {
+                w.u8(1);
+                w.u8(match settings.integrator {
+                    Integrator::SymplecticEuler => 0,
+                    Integrator::VelocityVerlet => 1,
+                    Integrator::RungeKutta4 => 2,
+                    Integrator::ImplicitEuler => 3,
+                });
+                w.f64(settings.damping.mass);
+                w.f64(settings.damping.stiffness);
+                w.f64(settings.damping.viscosity);
+                match settings.frame_dt {
+                    None => w.u8(0),
+                    Some(frame_dt) => {
+                        w.u8(1);
+                        w.f64(frame_dt);
+                    },
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn read(r: &mut CheckpointReader) -> Result<SimulatorState, CheckpointError> {
+        let kind = match r.u8()? {
+            0 => SimulatorKind::Fvm,
+            1 => SimulatorKind::FvmSequential,
+            2 => SimulatorKind::Fem,
+            tag => return Err(invalid_tag("simulator kind", tag)),
+        };
+        let material_coords = r.array2()?;
+        let num_triangles = r.usize()?;
+        let triangle_indices = (0..3 * num_triangles).map(|_| r.usize()).collect::<Result<Vec<usize>, CheckpointError>>()?;
+        let triangles = Array2::from_shape_vec((num_triangles, 3), triangle_indices)
+            .map_err(|e| CheckpointError::InvalidFormat(e.to_string()))?;
+        if triangles.iter().any(|&v| v >= material_coords.nrows()) {
+            return Err(CheckpointError::InvalidFormat("triangle with an unknown vertex".to_string()));
+        }
+        let num_tags = r.usize()?;
+        let mut edge_tags = HashMap::new();
+        for _ in 0..num_tags {
+            let tag = r.string()?;
+            edge_tags.insert(tag, r.edges()?);
+        }
+
+        let positions = r.array2()?;
+        let velocities = r.array2()?;
+        if positions.dim() != material_coords.dim() || velocities.dim() != material_coords.dim() {
+            return Err(CheckpointError::InvalidFormat("state does not match the mesh".to_string()));
+        }
+        let t = r.f64()?;
+        let dt = r.f64()?;
+
+        let material = Material { young_modulus: r.f64()?, nu: r.f64()?, rho: r.f64()? };
+        let model = match r.u8()? {
+            0 => ConstitutiveModel::StVenantKirchhoff,
+            1 => ConstitutiveModel::NeoHookean,
+            2 => ConstitutiveModel::StableNeoHookean,
+            3 => ConstitutiveModel::CorotatedLinear,
+            4 => ConstitutiveModel::MooneyRivlin,
+            tag => return Err(invalid_tag("constitutive model", tag)),
+        };
+
+        let immovable_boundary = r.usizes()?;
+        let num_constraints = r.usize()?;
+        let mut constraints = Vec::with_capacity(num_constraints.min(r.remaining()));
+        for _ in 0..num_constraints {
+            let nodes = r.usizes()?;
+            let axes = match r.u8()? {
+                0 => Axes::X,
+                1 => Axes::Y,
+                2 => Axes::Both,
+                tag => return Err(invalid_tag("constraint axes", tag)),
+            };
+            let motion = match r.u8()? {
+                0 => Motion::Fixed,
+                1 => Motion::DisplacementCurve { u: [r.f64()?, r.f64()?], curve: r.load_curve()? },
+                2 => Motion::VelocityCurve { v: [r.f64()?, r.f64()?], curve: r.load_curve()? },
+                tag => return Err(invalid_tag("prescribed motion", tag)),
+            };
+            constraints.push(Constraint { nodes, axes, motion });
+        }
+
+        let num_tractions = r.usize()?;
+        let mut tractions = Vec::with_capacity(num_tractions.min(r.remaining()));
+        for _ in 0..num_tractions {
+            let edges = r.edges()?;
+            let force = r.array1()?;
+            let pressure = r.f64()?;
+            let curve = r.load_curve()?;
+            tractions.push(Traction { pressure, curve, ..Traction::new(edges, force) });
+        }
+
+        let gravity = r.array1()?;
+        let gravity_curve = r.load_curve()?;
+        let num_fields = r.usize()?;
+        let mut fields = Vec::with_capacity(num_fields.min(r.remaining()));
+        for _ in 0..num_fields {
+            let origin = [r.f64()?, r.f64()?];
+            let spacing = [r.f64()?, r.f64()?];
+            let (ny, nx) = (r.usize()?, r.usize()?);
+            if ny == 0 || nx == 0 {
+                return Err(CheckpointError::InvalidFormat(format!("sampled field of {} x {} samples", nx, ny)));
+            }
+            let values = (0..ny.saturating_mul(nx).saturating_mul(2)).map(|_| r.f64()).collect::<Result<Vec<f64>, CheckpointError>>()?;
+            let values = Array3::from_shape_vec((ny, nx, 2), values).map_err(|e| CheckpointError::InvalidFormat(e.to_string()))?;
+            fields.push(BodyForce::Grid(SampledField::new(origin, spacing, values)));
+        }
+        let body_forces = BodyForces { gravity, gravity_curve, fields };
+
+        let explicit = match r.u8()? {
+            0 => None,
+            1 => {
+                let integrator = match r.u8()? {
+                    0 => Integrator::SymplecticEuler,
+                    1 => Integrator::VelocityVerlet,
+                    2 => Integrator::RungeKutta4,
+                    3 => Integrator::ImplicitEuler,
+                    tag => return Err(invalid_tag("integrator", tag)),
+                };
+                let damping = Damping { mass: r.f64()?, stiffness: r.f64()?, viscosity: r.f64()? };
+                let frame_dt = match r.u8()? {
+                    0 => None,
+                    1 => Some(r.f64()?),
+                    tag => return Err(invalid_tag("substepping", tag)),
+                };
+                Some(ExplicitSettings { integrator, damping, frame_dt })
+            },
+            tag => return Err(invalid_tag("solver settings", tag)),
+        };
+
+        let num_nodes = material_coords.nrows();
+        let node_lists = std::iter::once(&immovable_boundary)
+            .chain(constraints.iter().map(|constraint| &constraint.nodes))
+            .chain(tractions.iter().map(|traction| &traction.nodes));
+        let edge_nodes = edge_tags.values().flatten().flatten();
+        if node_lists.flatten().chain(edge_nodes).any(|&node_idx| node_idx >= num_nodes) {
+            return Err(CheckpointError::InvalidFormat("boundary with an unknown node".to_string()));
+        }
+
+        Ok(SimulatorState {
+            kind,
+            material_coords,
+            triangles,
+            edge_tags,
+            positions,
+            velocities,
+            t,
+            dt,
+            material,
+            model,
+            immovable_boundary,
+            constraints,
+            tractions,
+            body_forces,
+            explicit,
+        })
+    }
+}
+
+// Rebuilds a simulator of the kind stored in a checkpoint
+pub fn load_checkpoint<P: AsRef<Path>>(path: P) -> Result<Box<dyn Simulator>, CheckpointError> {
+    restore_simulator(SimulatorState::load(path)?)
+}
+
+// Loads a checkpoint to continue the run of the given simulator, e.g. the one built from the scenario
+// that wrote the checkpoint. The checkpoint must be of the same kind of simulator on the same mesh.
+pub fn resume_checkpoint<P: AsRef<Path>>(path: P,
+    kind: SimulatorKind,
+    sim: &dyn Simulator) -> Result<Box<dyn Simulator>, CheckpointError> {
+    let state = SimulatorState::load(path)?;
+    if state.kind != kind {
+        return Err(CheckpointError::Mismatch(format!("{:?} simulator instead of {:?}", state.kind, kind)));
+    }
+    if state.material_coords != sim.material_coords() || state.triangles != sim.mesh().triangles {
+        return Err(CheckpointError::Mismatch(format!("mesh of {} vertices and {} triangles instead of {} and {}",
+            state.material_coords.nrows(), state.triangles.nrows(),
+            sim.material_coords().nrows(), sim.mesh().triangles.nrows())));
+    }
+    restore_simulator(state)
+}
+
+fn restore_simulator(state: SimulatorState) -> Result<Box<dyn Simulator>, CheckpointError> {
+    Ok(match state.kind {
+        SimulatorKind::Fvm => Box::new(cauchy_fvm::CauchyFVM::from_checkpoint_state(state)?),
+        SimulatorKind::FvmSequential => Box::new(cauchy_fvm_seq::CauchyFVM::from_checkpoint_state(state)?),
+        SimulatorKind::Fem => Box::new(cauchy_fem::CauchyFEM::from_checkpoint_state(state)?),
+    })
+}
+
+fn invalid_tag(what: &str, tag: u8) -> CheckpointError {
+    CheckpointError::InvalidFormat(format!("unknown {} {}", what, tag))
+}
+
+#[derive(Default)]
+struct CheckpointWriter {
+    buffer: Vec<u8>,
+}
+
+impl CheckpointWriter {
+    fn bytes(&mut self, bytes: &[u8]) -> () {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn u8(&mut self, value: u8) -> () {
+        self.buffer.push(value);
+    }
+
+    fn u32(&mut self, value: u32) -> () {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn usize(&mut self, value: usize) -> () {
+        self.bytes(&(value as u64).to_le_bytes());
+    }
+
+    fn f64(&mut self, value: f64) -> () {
+        self.bytes(&value.to_bits().to_le_bytes());
+    }
+
+    fn string(&mut self, value: &str) -> () {
+        self.usize(value.len());
+        self.bytes(value.as_bytes());
+    }
+
+    fn usizes(&mut self, values: &[usize]) -> () {
+        self.usize(values.len());
+        for &value in values.iter() {
+            self.usize(value);
+        }
+    }
+
+    fn edges(&mut self, edges: &[[usize; 2]]) -> () {
+        self.usize(edges.len());
+        for edge in edges.iter() {
+            self.usize(edge[0]);
+            self.usize(edge[1]);
+        }
+    }
+
+    fn array1(&mut self, values: &Array1<f64>) -> () {
+        self.usize(values.len());
+        for &value in values.iter() {
+            self.f64(value);
+        }
+    }
+
+    fn array2(&mut self, values: &Array2<f64>) -> () {
+        self.usize(values.nrows());
+        self.usize(values.ncols());
+        for &value in values.iter() {
+            self.f64(value);
+        }
+    }
+
+    fn load_curve(&mut self, curve: &LoadCurve) -> Result<(), CheckpointError> {
+        match curve {
+            LoadCurve::Constant(value) => {
+                self.u8(0);
+                self.f64(*value);
+            },
+            LoadCurve::Table(points) => {
+                self.u8(1);
+                self.usize(points.len());
+                for &(t, value) in points.iter() {
+                    self.f64(t);
+                    self.f64(value);
+                }
+            },
+            LoadCurve::Ramp { start, end } => {
+                self.u8(2);
+                self.f64(*start);
+                self.f64(*end);
+            },
+            LoadCurve::Sine { amplitude, frequency, phase, offset } => {
+                self.u8(3);
+                self.f64(*amplitude);
+                self.f64(*frequency);
+                self.f64(*phase);
+                self.f64(*offset);
+            },
+            LoadCurve::Step { time } => {
+                self.u8(4);
+                self.f64(*time);
+            },
+            LoadCurve::Custom(_) => return Err(CheckpointError::NotSerializable("custom load curves".to_string())),
+        }
+        Ok(())
+    }
+}
+
+struct CheckpointReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> CheckpointReader<'a> {
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], CheckpointError> {
+        if len > self.remaining() {
+            return Err(CheckpointError::InvalidFormat("unexpected end of checkpoint".to_string()));
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, CheckpointError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, CheckpointError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn usize(&mut self) -> Result<usize, CheckpointError> {
+        let value = u64::from_le_bytes(self.bytes(8)?.try_into().unwrap());
+        usize::try_from(value).map_err(|_| CheckpointError::InvalidFormat(format!("size {} out of range", value)))
+    }
+
+    fn f64(&mut self) -> Result<f64, CheckpointError> {
+        Ok(f64::from_bits(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap())))
+    }
+
+    fn string(&mut self) -> Result<String, CheckpointError> {
+        let len = self.usize()?;
+        String::from_utf8(self.bytes(len)?.to_vec()).map_err(|e| CheckpointError::InvalidFormat(e.to_string()))
+    }
+
+    fn usizes(&mut self) -> Result<Vec<usize>, CheckpointError> {
+        let len = self.usize()?;
+        (0..len).map(|_| self.usize()).collect()
+    }
+
+    fn edges(&mut self) -> Result<Vec<[usize; 2]>, CheckpointError> {
+        let len = self.usize()?;
+        (0..len).map(|_| Ok([self.usize()?, self.usize()?])).collect()
+    }
+
+    fn array1(&mut self) -> Result<Array1<f64>, CheckpointError> {
+        let len = self.usize()?;
+        (0..len).map(|_| self.f64()).collect()
+    }
+
+    fn array2(&mut self) -> Result<Array2<f64>, CheckpointError> {
+        let (rows, cols) = (self.usize()?, self.usize()?);
+        let values = (0..rows.saturating_mul(cols)).map(|_| self.f64()).collect::<Result<Vec<f64>, CheckpointError>>()?;
+        Array2::from_shape_vec((rows, cols), values).map_err(|e| CheckpointError::InvalidFormat(e.to_string()))
+    }
+
+    fn load_curve(&mut self) -> Result<LoadCurve, CheckpointError> {
+        Ok(match self.u8()? {
+            0 => LoadCurve::Constant(self.f64()?),
+            1 => {
+                let len = self.usize()?;
+                LoadCurve::Table((0..len).map(|_| Ok((self.f64()?, self.f64()?))).collect::<Result<_, CheckpointError>>()?)
+            },
+            2 => LoadCurve::Ramp { start: self.f64()?, end: self.f64()? },
+            3 => LoadCurve::Sine { amplitude: self.f64()?, frequency: self.f64()?, phase: self.f64()?, offset: self.f64()? },
+            4 => LoadCurve::Step { time: self.f64()? },
+            tag => return Err(invalid_tag("load curve", tag)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::new_simulator;
+    use crate::sim::boundary::{Region, Side};
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("simulator_{}_{}.ckpt", name, std::process::id()))
+    }
+
+    // beam clamped on the left, pulled down on the right and with a prescribed motion of its top,
+    // with a timestep below the stable one of the explicit solvers
+    fn loaded_beam(kind: SimulatorKind) -> Box<dyn Simulator> {
+        let mesh = TriangleMesh::new_beam(6.0, 2.0, (12, 4));
+        let mut sim = new_simulator(kind, &mesh, "rubber", 2e-4).unwrap();
+        sim.check_timestep().unwrap();
+        sim.clear_tractions();
+        sim.set_immovable_boundary(&Region::Side(Side::Left)).unwrap();
+        sim.add_traction(&Region::Side(Side::Right), array![0.0, -2.5e4]).unwrap();
+        sim.add_constraint(&Region::Side(Side::Up), Axes::X,
+            Motion::displacement_curve([0.01, 0.0], LoadCurve::Ramp { start: 0.0, end: 0.05 })).unwrap();
+        sim
+    }
+
+    fn assert_bitwise_equal(a: &Array2<f64>, b: &Array2<f64>) -> () {
+        assert_eq!(a.dim(), b.dim());
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert_eq!(x.to_bits(), y.to_bits());
+        }
+    }
+
+    #[test]
+    fn restored_simulator_continues_bit_for_bit() {
+        for (kind, name) in [(SimulatorKind::Fvm, "fvm"), (SimulatorKind::FvmSequential, "fvm_seq"),
+            (SimulatorKind::Fem, "fem")] {
+            let mut sim = loaded_beam(kind);
+            for _ in 0..20 {
+                sim.update().unwrap();
+            }
+            let path = temp_path(name);
+            sim.save_checkpoint(&path).unwrap();
+            let mut restored = load_checkpoint(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+
+            for _ in 0..20 {
+                sim.update().unwrap();
+                restored.update().unwrap();
+            }
+            assert_eq!(sim.time().to_bits(), restored.time().to_bits());
+            assert_bitwise_equal(sim.positions(), restored.positions());
+            assert_bitwise_equal(sim.velocities(), restored.velocities());
+        }
+    }
+
+    #[test]
+    fn damaged_checkpoints_are_rejected() {
+        let mut sim = loaded_beam(SimulatorKind::Fvm);
+        sim.update().unwrap();
+        let path = temp_path("damaged");
+        sim.save_checkpoint(&path).unwrap();
+        let data = fs::read(&path).unwrap();
+
+        // truncated anywhere
+        for len in [0, 4, CHECKPOINT_MAGIC.len() + 4, data.len() / 2, data.len() - 1] {
+            fs::write(&path, &data[..len]).unwrap();
+            assert!(matches!(SimulatorState::load(&path), Err(CheckpointError::InvalidFormat(_))));
+        }
+        // wrong magic bytes
+        let mut corrupted = data.clone();
+        corrupted[0] ^= 0xff;
+        fs::write(&path, &corrupted).unwrap();
+        assert!(matches!(SimulatorState::load(&path), Err(CheckpointError::InvalidFormat(_))));
+        // newer format version
+        let mut corrupted = data.clone();
+        corrupted[CHECKPOINT_MAGIC.len()..CHECKPOINT_MAGIC.len() + 4].copy_from_slice(&(CHECKPOINT_VERSION + 1).to_le_bytes());
+        fs::write(&path, &corrupted).unwrap();
+        assert!(matches!(SimulatorState::load(&path), Err(CheckpointError::UnsupportedVersion { .. })));
+        // trailing data
+        let mut corrupted = data.clone();
+        corrupted.push(0);
+        fs::write(&path, &corrupted).unwrap();
+        assert!(matches!(SimulatorState::load(&path), Err(CheckpointError::InvalidFormat(_))));
+        // sampled field without samples
+        let mut state = sim.checkpoint_state().unwrap();
+        state.body_forces.fields.push(BodyForce::Grid(SampledField {
+            origin: [0.0, 0.0],
+            spacing: [1.0, 1.0],
+            values: Array3::zeros((3, 0, 2)),
+        }));
+        state.save(&path).unwrap();
+        assert!(matches!(SimulatorState::load(&path), Err(CheckpointError::InvalidFormat(_))));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn resume_rejects_other_runs() {
+        let sim = loaded_beam(SimulatorKind::Fem);
+        let path = temp_path("resume");
+        sim.save_checkpoint(&path).unwrap();
+
+        assert!(resume_checkpoint(&path, SimulatorKind::Fem, sim.as_ref()).is_ok());
+        assert!(matches!(resume_checkpoint(&path, SimulatorKind::Fvm, sim.as_ref()),
+            Err(CheckpointError::Mismatch(_))));
+        let other_mesh = TriangleMesh::new_beam(6.0, 2.0, (6, 2));
+        let other = new_simulator(SimulatorKind::Fem, &other_mesh, "rubber", 1e-3).unwrap();
+        assert!(matches!(resume_checkpoint(&path, SimulatorKind::Fem, other.as_ref()),
+            Err(CheckpointError::Mismatch(_))));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn closures_are_not_serializable() {
+        let mut sim = loaded_beam(SimulatorKind::FvmSequential);
+        sim.add_constraint(&Region::Side(Side::Down), Axes::Y, Motion::velocity(|t| [0.0, t])).unwrap();
+        let path = temp_path("closures");
+        assert!(matches!(sim.save_checkpoint(&path), Err(CheckpointError::NotSerializable(_))));
+        assert!(!path.exists());
+    }
+}
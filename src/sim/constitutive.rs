@@ -0,0 +1,192 @@
+use ndarray::prelude::*;
+use std::str::FromStr;
+
+// Hyperelastic material models, each giving the first Piola-Kirchhoff stress P(F) of a 2D
+// deformation gradient F. All models are parametrized by the Lamé coefficients of the material and
+// agree with linear elasticity for small strains.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConstitutiveModel {
+    StVenantKirchhoff, // S = lambda tr(E) I + 2 mu E, softens and inverts under strong compression
+    NeoHookean,        // compressible Neo-Hookean, undefined for inverted elements (J <= 0)
+    StableNeoHookean,  // Smith et al. 2018, well defined and restoring for inverted elements
+    CorotatedLinear,   // linear elasticity in the rotated frame of the polar decomposition F = RS
+    MooneyRivlin,      // compressible plane strain Mooney-Rivlin with C10 = C01 = mu / 4
+}
+
+impl FromStr for ConstitutiveModel {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "stvk" => Ok(ConstitutiveModel::StVenantKirchhoff),
+            "neo_hookean" => Ok(ConstitutiveModel::NeoHookean),
+            "stable_neo_hookean" => Ok(ConstitutiveModel::StableNeoHookean),
+            "corotated" => Ok(ConstitutiveModel::CorotatedLinear),
+            "mooney_rivlin" => Ok(ConstitutiveModel::MooneyRivlin),
+            _ => Err(format!("Unknown constitutive model '{}'! Models: \
+                {{ 'stvk', 'neo_hookean', 'stable_neo_hookean', 'corotated', 'mooney_rivlin' }}", name)),
+        }
+    }
+}
+
+impl ConstitutiveModel {
+    // whether the model is well defined and restoring for inverted elements (J <= 0), so that a
+    // simulation can go on and let them recover
+    pub fn allows_inversion(&self) -> bool {
+        *self == ConstitutiveModel::StableNeoHookean
+    }
+
+    // first Piola-Kirchhoff stress tensor P (2 x 2) for the deformation gradient F (2 x 2)
+    pub fn first_piola(&self, f: &Array2<f64>, lambda: f64, mu: f64) -> Array2<f64> {
+        let j = determinant(f);
+        match self {
+            ConstitutiveModel::StVenantKirchhoff => {
+                let e: Array2<f64> = 0.5 * (&f.t().dot(f) - Array::eye(2));
+                let tr_e = e[[0, 0]] + e[[1, 1]];
+                let s: Array2<f64> = (lambda * tr_e) * Array::eye(2) + (2.0 * mu * e);
+                f.dot(&s)
+            },
+            ConstitutiveModel::NeoHookean => {
+                // P = mu (F - F^-T) + lambda ln(J) F^-T
+                let f_inv_t = cofactor(f) / j;
+                mu * (f - &f_inv_t) + lambda * j.ln() * f_inv_t
+            },
+            ConstitutiveModel::StableNeoHookean => {
+                // Psi = mu/2 (I_C - 2) + lambda'/2 (J - alpha)^2, with lambda' and alpha chosen so that
+                // the rest state is stress free and the model linearizes to lambda and mu
+                let lambda_prime = lambda + mu;
+                let alpha = 1.0 + mu / lambda_prime;
+                mu * f + lambda_prime * (j - alpha) * cofactor(f)
+            },
+            ConstitutiveModel::CorotatedLinear => {
+                // P = 2 mu (F - R) + lambda tr(R^T F - I) R
+                let r = polar_rotation(f);
+                let tr = r.t().dot(f).diag().sum() - 2.0;
+                2.0 * mu * (f - &r) + lambda * tr * r
+            },
+            ConstitutiveModel::MooneyRivlin => {
+                // Plane strain invariants of C = diag(F^T F, 1), I_1 = tr(F^T F) + 1 and
+                // I_2 = tr(F^T F) + J^2, with
+                // Psi = C10 (I_1 - 3 - 2 ln J) + C01 (I_2 - 3 - 4 ln J) + kappa/2 (ln J)^2.
+                // Linearizing gives mu = 2 (C10 + C01) and lambda = kappa + 4 C01.
+                let c10 = 0.25 * mu;
+                let c01 = 0.25 * mu;
+                let kappa = lambda - 4.0 * c01;
+                let f_inv_t = cofactor(f) / j;
+                2.0 * c10 * (f - &f_inv_t)
+                    + 2.0 * c01 * (f + &((j * j - 2.0) * &f_inv_t))
+                    + kappa * j.ln() * f_inv_t
+            },
+        }
+    }
+
+    // strain energy density Psi(F) per unit reference area, whose derivative is first_piola and
+    // which is zero in the rest state
+    pub fn energy_density(&self, f: &Array2<f64>, lambda: f64, mu: f64) -> f64 {
+        let j = determinant(f);
+        let i_c = f.iter().map(|x| x * x).sum::<f64>(); // tr(F^T F)
+        match self {
+            ConstitutiveModel::StVenantKirchhoff => {
+                // Psi = mu E:E + lambda/2 tr(E)^2
+                let e: Array2<f64> = 0.5 * (&f.t().dot(f) - Array::eye(2));
+                let tr_e = e[[0, 0]] + e[[1, 1]];
+                mu * e.iter().map(|x| x * x).sum::<f64>() + 0.5 * lambda * tr_e * tr_e
+            },
+            ConstitutiveModel::NeoHookean => {
+                // Psi = mu/2 (I_C - 2) - mu ln(J) + lambda/2 ln(J)^2
+                let ln_j = j.ln();
+                0.5 * mu * (i_c - 2.0) - mu * ln_j + 0.5 * lambda * ln_j * ln_j
+            },
+            ConstitutiveModel::StableNeoHookean => {
+                // shifted by the energy of the rest state, lambda'/2 (1 - alpha)^2
+                let lambda_prime = lambda + mu;
+                let alpha = 1.0 + mu / lambda_prime;
+                0.5 * mu * (i_c - 2.0) + 0.5 * lambda_prime * ((j - alpha).powi(2) - (1.0 - alpha).powi(2))
+            },
+            ConstitutiveModel::CorotatedLinear => {
+                // Psi = mu |F - R|^2 + lambda/2 tr(R^T F - I)^2
+                let r = polar_rotation(f);
+                let tr = r.t().dot(f).diag().sum() - 2.0;
+                mu * (f - &r).iter().map(|x| x * x).sum::<f64>() + 0.5 * lambda * tr * tr
+            },
+            ConstitutiveModel::MooneyRivlin => {
+                let c10 = 0.25 * mu;
+                let c01 = 0.25 * mu;
+                let kappa = lambda - 4.0 * c01;
+                let ln_j = j.ln();
+                c10 * (i_c - 2.0 - 2.0 * ln_j) + c01 * (i_c + j * j - 3.0 - 4.0 * ln_j) + 0.5 * kappa * ln_j * ln_j
+            },
+        }
+    }
+}
+
+pub fn polar_rotation(f: &Array2<f64>) -> Array2<f64> {
+    // closed form rotation R of the 2D polar decomposition F = RS
+    let theta = (f[[1, 0]] - f[[0, 1]]).atan2(f[[0, 0]] + f[[1, 1]]);
+    let (s, c) = theta.sin_cos();
+    array![[c, -s],
+           [s, c]]
+}
+
+fn determinant(f: &Array2<f64>) -> f64 {
+    f[[0, 0]] * f[[1, 1]] - f[[0, 1]] * f[[1, 0]]
+}
+
+fn cofactor(f: &Array2<f64>) -> Array2<f64> {
+    // cofactor matrix J F^-T, which is also the derivative of J = det(F) with respect to F
+    array![[f[[1, 1]], -f[[1, 0]]],
+           [-f[[0, 1]], f[[0, 0]]]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODELS: [ConstitutiveModel; 5] = [ConstitutiveModel::StVenantKirchhoff, ConstitutiveModel::NeoHookean,
+        ConstitutiveModel::StableNeoHookean, ConstitutiveModel::CorotatedLinear, ConstitutiveModel::MooneyRivlin];
+    // Lamé coefficients of rubber
+    const LAMBDA: f64 = 4.2e6;
+    const MU: f64 = 1.4e6;
+
+    #[test]
+    fn rest_state_is_stress_free() {
+        let identity = Array2::<f64>::eye(2);
+        for model in MODELS {
+            let p = model.first_piola(&identity, LAMBDA, MU);
+            assert!(p.iter().all(|x| x.abs() < 1e-9 * MU), "{:?}: P(I) = {}", model, p);
+            assert!(model.energy_density(&identity, LAMBDA, MU).abs() < 1e-9 * MU, "{:?}", model);
+        }
+    }
+
+    #[test]
+    fn stress_is_the_derivative_of_the_energy() {
+        // rotated stretches, shears and compressions, and an inverted element for the model defined there
+        let rotation = |theta: f64| array![[theta.cos(), -theta.sin()], [theta.sin(), theta.cos()]];
+        let deformations = vec![
+            array![[1.2, 0.0], [0.0, 0.9]],
+            array![[1.0, 0.3], [0.0, 1.0]],
+            rotation(0.7).dot(&array![[0.8, 0.1], [0.1, 0.7]]),
+            rotation(-2.0).dot(&array![[1.5, -0.2], [0.3, 1.1]]),
+        ];
+        let h = 1e-6;
+        for model in MODELS {
+            let mut deformations = deformations.clone();
+            if model.allows_inversion() {
+                deformations.push(array![[-0.5, 0.2], [0.1, 0.8]]);
+            }
+            for f in deformations.iter() {
+                let p = model.first_piola(f, LAMBDA, MU);
+                for (i, j) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+                    let mut f_plus = f.clone();
+                    let mut f_minus = f.clone();
+                    f_plus[[i, j]] += h;
+                    f_minus[[i, j]] -= h;
+                    let derivative = (model.energy_density(&f_plus, LAMBDA, MU)
+                        - model.energy_density(&f_minus, LAMBDA, MU)) / (2.0 * h);
+                    assert!((derivative - p[[i, j]]).abs() < 1e-5 * MU,
+                        "{:?} at F = {}: dPsi/dF[{}, {}] = {} but P = {}", model, f, i, j, derivative, p[[i, j]]);
+                }
+            }
+        }
+    }
+}
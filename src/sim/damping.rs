@@ -0,0 +1,29 @@
+// Damping parameters of a simulator. Rayleigh damping gives the damping force
+// f_d = -(alpha M + beta K) v, and the Kelvin-Voigt term adds the viscous stress S_v = 2 eta dE/dt
+// to the elastic stress of every element.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Damping {
+    pub mass: f64,      // mass proportional Rayleigh coefficient alpha (1/s)
+    pub stiffness: f64, // stiffness proportional Rayleigh coefficient beta (s)
+    pub viscosity: f64, // Kelvin-Voigt shear viscosity eta (Pa s)
+}
+
+impl Damping {
+    pub fn none() -> Damping {
+        Damping::default()
+    }
+
+    pub fn rayleigh(mass: f64, stiffness: f64) -> Damping {
+        Damping { mass, stiffness, viscosity: 0.0 }
+    }
+
+    pub fn kelvin_voigt(viscosity: f64) -> Damping {
+        Damping { mass: 0.0, stiffness: 0.0, viscosity }
+    }
+
+    pub fn damping_ratio(&self, omega: f64, mu: f64) -> f64 {
+        // damping ratio of a mode with angular frequency omega. The viscous stress damps like
+        // stiffness proportional damping with beta = eta / mu.
+        0.5 * (self.mass / omega + (self.stiffness + self.viscosity / mu) * omega)
+    }
+}
@@ -0,0 +1,198 @@
+use ndarray::prelude::*;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use crate::sim::Simulator;
+use crate::sim::error::SimError;
+use crate::sim::fields::compute_deformation_gradients;
+
+// Energies and momenta of a simulator state, with the lumped nodal masses. The energies are zero in
+// the undeformed rest state. Tractions and body force fields other than gravity are not
+// conservative, so they enter through their power, which DiagnosticsRecorder integrates into the
+// external work.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostics {
+    pub time: f64,
+    pub kinetic_energy: f64,       // 1/2 sum m |v|^2
+    pub strain_energy: f64,        // sum of A_0 Psi(F) over all triangles
+    pub gravitational_energy: f64, // -sum m g . (x - X), with the gravity of the current time
+    pub external_power: f64,       // sum f . v of the tractions and body force fields
+    pub linear_momentum: [f64; 2], // sum m v
+    pub angular_momentum: f64,     // sum m (x cross v) about the origin
+}
+
+impl Diagnostics {
+    pub fn new<S: Simulator + ?Sized>(sim: &S) -> Diagnostics {
+        let t = sim.time();
+        let positions = sim.positions();
+        let material_coords = sim.material_coords();
+        let velocities = sim.velocities();
+        let masses = sim.lumped_masses();
+
+        let (lambda, mu) = sim.lame_parameters();
+        let model = sim.constitutive_model();
+        let strain_energy = compute_deformation_gradients(material_coords, positions, &sim.mesh().triangles)
+            .iter()
+            .map(|(f, area)| area * model.energy_density(f, lambda, mu))
+            .sum();
+
+        // nodal forces of the tractions and of the body force fields on top of gravity
+        let mut external_forces = Array2::<f64>::zeros(positions.raw_dim());
+        for traction in sim.tractions().iter() {
+            traction.add_nodal_forces(material_coords, positions, t, &mut external_forces);
+        }
+        let body_forces = sim.body_forces();
+        let gravity = body_forces.gravity_curve.value(t) * &body_forces.gravity;
+
+        let mut diagnostics = Diagnostics {
+            time: t,
+            kinetic_energy: 0.0,
+            strain_energy,
+            gravitational_energy: 0.0,
+            external_power: 0.0,
+            linear_momentum: [0.0, 0.0],
+            angular_momentum: 0.0,
+        };
+        for node_idx in 0..positions.nrows() {
+            let m = masses[node_idx];
+            let (x, y) = (positions[[node_idx, 0]], positions[[node_idx, 1]]);
+            let (vx, vy) = (velocities[[node_idx, 0]], velocities[[node_idx, 1]]);
+            let (mut fx, mut fy) = (external_forces[[node_idx, 0]], external_forces[[node_idx, 1]]);
+            for field in body_forces.fields.iter() {
+                let b = field.acceleration_at(x, y, t);
+                fx += m * b[0];
+                fy += m * b[1];
+            }
+
+            diagnostics.kinetic_energy += 0.5 * m * (vx * vx + vy * vy);
+            diagnostics.gravitational_energy -= m * (gravity[0] * (x - material_coords[[node_idx, 0]])
+                + gravity[1] * (y - material_coords[[node_idx, 1]]));
+            diagnostics.external_power += fx * vx + fy * vy;
+            diagnostics.linear_momentum[0] += m * vx;
+            diagnostics.linear_momentum[1] += m * vy;
+            diagnostics.angular_momentum += m * (x * vy - y * vx);
+        }
+        diagnostics
+    }
+
+    // kinetic, strain and gravitational energy
+    pub fn mechanical_energy(&self) -> f64 {
+        self.kinetic_energy + self.strain_energy + self.gravitational_energy
+    }
+}
+
+// Records the diagnostics of every step of a simulator as a CSV time series, writing every Nth row.
+// The external work is integrated from the power with the trapezoidal rule, so record has to be
+// called after every update. Without damping the mechanical energy minus the external work stays
+// at its initial value up to the error of the integrator, and its drift shows an instability long
+// before the state diverges. Damping dissipates energy and makes the drift negative. Prescribed
+// motions do work through reaction forces that are not known, so the balance only holds for fixed
+// constraints.
+pub struct DiagnosticsRecorder {
+    file: BufWriter<fs::File>,
+    every: usize,                 // writes every Nth call of record
+    num_recorded: usize,
+    last: Option<Diagnostics>,    // diagnostics of the last recorded state
+    external_work: f64,
+    initial_energy: f64,
+    energy_scale: f64,            // largest energy seen, relative drifts are measured against it
+}
+
+impl DiagnosticsRecorder {
+    const HEADER: &'static str = "time,kinetic_energy,strain_energy,gravitational_energy,external_work,\
+        energy_drift,linear_momentum_x,linear_momentum_y,angular_momentum";
+
+    pub fn new<P: AsRef<Path>>(path: P, every: usize) -> io::Result<DiagnosticsRecorder> {
+        let mut file = BufWriter::new(fs::File::create(path)?);
+        writeln!(file, "{}", Self::HEADER)?;
+        Ok(DiagnosticsRecorder {
+            file,
+            every: every.max(1),
+            num_recorded: 0,
+            last: None,
+            external_work: 0.0,
+            initial_energy: 0.0,
+            energy_scale: 0.0,
+        })
+    }
+
+    // records the current state, whose row is written if it is the first or every Nth after it.
+    // Returns whether the row was written.
+    pub fn record<S: Simulator + ?Sized>(&mut self, sim: &S) -> io::Result<bool> {
+        let diagnostics = sim.diagnostics();
+        match &self.last {
+            Some(last) => {
+                self.external_work += 0.5 * (last.external_power + diagnostics.external_power)
+                    * (diagnostics.time - last.time);
+            },
+            None => self.initial_energy = diagnostics.mechanical_energy(),
+        }
+        self.energy_scale = self.energy_scale
+            .max(diagnostics.kinetic_energy.abs())
+            .max(diagnostics.strain_energy.abs())
+            .max(diagnostics.gravitational_energy.abs())
+            .max(self.external_work.abs());
+        self.last = Some(diagnostics);
+
+        let write = self.num_recorded.is_multiple_of(self.every);
+        self.num_recorded += 1;
+        if write {
+            self.write_row()?;
+        }
+        Ok(write)
+    }
+
+    // writes the row of the last recorded state if it was skipped, e.g. at the end of a run
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.num_recorded > 0 && !(self.num_recorded - 1).is_multiple_of(self.every) {
+            self.write_row()?;
+        }
+        self.file.flush()
+    }
+
+    pub fn last(&self) -> Option<&Diagnostics> {
+        self.last.as_ref()
+    }
+
+    // work done by the tractions and body force fields since the first recorded state
+    pub fn external_work(&self) -> f64 {
+        self.external_work
+    }
+
+    // change of the mechanical energy that the external work does not account for
+    pub fn energy_drift(&self) -> f64 {
+        match &self.last {
+            Some(last) => last.mechanical_energy() - self.external_work - self.initial_energy,
+            None => 0.0,
+        }
+    }
+
+    // energy drift relative to the largest energy seen so far
+    pub fn relative_energy_drift(&self) -> f64 {
+        match self.energy_scale > 0.0 {
+            true => self.energy_drift() / self.energy_scale,
+            false => 0.0,
+        }
+    }
+
+    // fails if the integrator created more energy than the given fraction of the largest energy
+    pub fn check_energy_drift(&self, tolerance: f64) -> Result<(), SimError> {
+        let relative_drift = self.relative_energy_drift();
+        match relative_drift > tolerance || !relative_drift.is_finite() {
+            true => Err(SimError::EnergyDrift {
+                relative_drift,
+                time: self.last.as_ref().map_or(0.0, |last| last.time),
+            }),
+            false => Ok(()),
+        }
+    }
+
+    fn write_row(&mut self) -> io::Result<()> {
+        let energy_drift = self.energy_drift();
+        let Some(d) = &self.last else { return Ok(()) };
+        writeln!(self.file, "{},{},{},{},{},{},{},{},{}", d.time, d.kinetic_energy, d.strain_energy,
+            d.gravitational_energy, self.external_work, energy_drift, d.linear_momentum[0],
+            d.linear_momentum[1], d.angular_momentum)?;
+        self.file.flush()
+    }
+}
@@ -0,0 +1,123 @@
+use std::error::Error;
+use std::fmt;
+use ndarray::prelude::*;
+use crate::sim::constitutive::ConstitutiveModel;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimError {
+    // reference triangle with zero or negative area, whose D_0 matrix cannot be inverted
+    DegenerateTriangle { triangle: usize },
+    // material name missing from the MATERIALS table
+    UnknownMaterial { name: String },
+    // boundary region that does not contain any node of the mesh
+    EmptyRegion,
+    // traction region without any boundary edge to apply the load on
+    NoBoundaryEdges,
+    // traction index that no traction of the simulator has
+    InvalidTraction(usize),
+    // nodes whose position or velocity became NaN or infinite
+    NonFiniteState { nodes: Vec<usize>, time: f64 },
+    // triangles whose deformation gradient has det(F) <= 0, i.e. they were flipped
+    InvertedElements { triangles: Vec<usize>, time: f64 },
+    // energy created by the time integration above a tolerance, see diagnostics.rs
+    EnergyDrift { relative_drift: f64, time: f64 },
+    // timestep above the CFL bound of an explicit integrator, see CauchyFVM::stable_timestep
+    UnstableTimestep { dt: f64, stable_dt: f64 },
+    // linear solver that did not reach its tolerance, with the relative residual it stopped at
+    SolverDiverged { iterations: usize, residual: f64, time: f64 },
+    // Newton iteration of an implicit step that did not converge, with its relative residual
+    NewtonDiverged { iterations: usize, residual: f64, time: f64 },
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimError::DegenerateTriangle { triangle } =>
+                write!(f, "Triangle {} of the reference mesh is degenerate", triangle),
+            SimError::UnknownMaterial { name } =>
+                write!(f, "Unknown material '{}'", name),
+            SimError::EmptyRegion =>
+                write!(f, "Boundary region does not contain any node"),
+            SimError::NoBoundaryEdges =>
+                write!(f, "Traction region does not contain any boundary edge"),
+            SimError::InvalidTraction(traction_idx) =>
+                write!(f, "There is no traction with index {}", traction_idx),
+            SimError::NonFiniteState { nodes, time } =>
+                write!(f, "Simulation diverged at t={}: non-finite state at nodes {:?}", time, nodes),
+            SimError::InvertedElements { triangles, time } =>
+                write!(f, "Simulation failed at t={}: inverted triangles {:?}", time, triangles),
+            SimError::EnergyDrift { relative_drift, time } =>
+                write!(f, "Simulation became unstable at t={}: energy drifted by {:.3e} of the largest energy",
+                    time, relative_drift),
+            SimError::UnstableTimestep { dt, stable_dt } =>
+                write!(f, "dt = {:e} exceeds the stable timestep {:e} of the explicit integrator, \
+                    use a smaller dt or enable substepping", dt, stable_dt),
+            SimError::SolverDiverged { iterations, residual, time } =>
+                write!(f, "Linear solver did not converge at t={}: relative residual {:.3e} after {} iterations",
+                    time, residual, iterations),
+            SimError::NewtonDiverged { iterations, residual, time } =>
+                write!(f, "Implicit step did not converge at t={}: relative residual {:.3e} after {} Newton iterations",
+                    time, residual, iterations),
+        }
+    }
+}
+
+impl Error for SimError {}
+
+// Checks the current simulation state for divergence (NaN/Inf positions or velocities) and, unless
+// the constitutive model allows them, for inverted triangles. Since the reference triangles have
+// positive area, det(F) <= 0 exactly when the signed area of the deformed triangle is non-positive.
+pub fn validate_state(triangles: &Array2<usize>,
+    positions: &Array2<f64>,
+    velocities: &Array2<f64>,
+    model: ConstitutiveModel,
+    time: f64) -> Result<(), SimError> {
+    let nodes: Vec<usize> = (0..positions.nrows())
+        .filter(|&node_idx| {
+            positions.row(node_idx).iter()
+                .chain(velocities.row(node_idx).iter())
+                .any(|x| !x.is_finite())
+        })
+        .collect();
+    if !nodes.is_empty() {
+        return Err(SimError::NonFiniteState { nodes, time });
+    }
+    if model.allows_inversion() {
+        return Ok(());
+    }
+
+    let inverted: Vec<usize> = triangles.outer_iter().enumerate()
+        .filter(|(_, tri)| {
+            let (i, j, k) = (tri[0], tri[1], tri[2]);
+            let gij = &positions.row(j) - &positions.row(i);
+            let gik = &positions.row(k) - &positions.row(i);
+            gij[0] * gik[1] - gik[0] * gij[1] <= 0.0
+        })
+        .map(|(tri_idx, _)| tri_idx)
+        .collect();
+    if !inverted.is_empty() {
+        return Err(SimError::InvertedElements { triangles: inverted, time });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inverted_triangles_fail_unless_the_model_allows_them() {
+        let triangles = array![[0, 1, 2]];
+        let inverted = array![[0.0, 0.0], [0.0, 1.0], [1.0, 0.0]];
+        let velocities = Array2::<f64>::zeros((3, 2));
+        assert!(matches!(validate_state(&triangles, &inverted, &velocities, ConstitutiveModel::StVenantKirchhoff, 1.0),
+            Err(SimError::InvertedElements { .. })));
+        assert!(validate_state(&triangles, &inverted, &velocities, ConstitutiveModel::StableNeoHookean, 1.0).is_ok());
+
+        let mut diverged = inverted.clone();
+        diverged[[1, 0]] = f64::NAN;
+        assert!(matches!(validate_state(&triangles, &diverged, &velocities, ConstitutiveModel::StableNeoHookean, 1.0),
+            Err(SimError::NonFiniteState { .. })));
+    }
+}
@@ -0,0 +1,221 @@
+use ndarray::prelude::*;
+use crate::sim::Simulator;
+use crate::sim::constitutive::ConstitutiveModel;
+use std::str::FromStr;
+
+// Deformation and stress of a single triangle, which are constant over the triangle
+#[derive(Clone, Debug)]
+pub struct TriangleStress {
+    pub deformation_gradient: Array2<f64>, // F = D D_0^-1 (2 x 2)
+    pub green_strain: Array2<f64>,         // E = 1/2 (F^T F - I)
+    pub first_piola: Array2<f64>,          // P(F) of the constitutive model
+    pub cauchy: Array2<f64>,               // sigma = P F^T / J
+    pub cauchy_zz: f64,                    // out-of-plane normal stress, zero in plane stress
+    pub von_mises: f64,                    // of the Cauchy stress with its out-of-plane part
+    pub principal_stresses: [f64; 2],      // eigenvalues of the Cauchy stress, largest first
+    pub principal_angle: f64,              // angle of the largest principal direction to the x axis
+    pub area: f64,                         // reference area, the weight of the nodal averages
+}
+
+impl TriangleStress {
+    // In plane strain the body is held at its thickness by sigma_zz = nu (sigma_xx + sigma_yy), with
+    // the Poisson ratio nu of the plane strain Lamé coefficients, as in linear elasticity.
+    pub fn new(f: Array2<f64>, area: f64, model: ConstitutiveModel, lambda: f64, mu: f64, plane_strain: bool) -> TriangleStress {
+        let green_strain = 0.5 * (&f.t().dot(&f) - Array::eye(2));
+        let first_piola = model.first_piola(&f, lambda, mu);
+        let j = f[[0, 0]] * f[[1, 1]] - f[[0, 1]] * f[[1, 0]];
+        let cauchy = first_piola.dot(&f.t()) / j;
+        let cauchy_zz = match plane_strain {
+            true => lambda / (2.0 * (lambda + mu)) * (cauchy[[0, 0]] + cauchy[[1, 1]]),
+            false => 0.0,
+        };
+        let (principal_stresses, principal_angle) = principal_stresses(&cauchy);
+        TriangleStress {
+            deformation_gradient: f,
+            green_strain,
+            first_piola,
+            von_mises: von_mises(&cauchy, cauchy_zz),
+            cauchy,
+            cauchy_zz,
+            principal_stresses,
+            principal_angle,
+            area,
+        }
+    }
+}
+
+// Area weighted average of the stresses of the triangles around a node, a smooth field for plotting
+// that is continuous across the triangles
+#[derive(Clone, Debug)]
+pub struct NodalStress {
+    pub deformation_gradient: Array2<f64>,
+    pub green_strain: Array2<f64>,
+    pub first_piola: Array2<f64>,
+    pub cauchy: Array2<f64>,
+    pub cauchy_zz: f64,
+    pub von_mises: f64,               // of the averaged Cauchy stress
+    pub principal_stresses: [f64; 2], // of the averaged in-plane Cauchy stress
+    pub principal_angle: f64,
+}
+
+// von Mises stress of an in-plane Cauchy stress and its out-of-plane normal stress
+pub fn von_mises(cauchy: &Array2<f64>, cauchy_zz: f64) -> f64 {
+    let (sxx, syy, sxy) = (cauchy[[0, 0]], cauchy[[1, 1]], 0.5 * (cauchy[[0, 1]] + cauchy[[1, 0]]));
+    let szz = cauchy_zz;
+    (0.5 * ((sxx - syy).powi(2) + (syy - szz).powi(2) + (szz - sxx).powi(2)) + 3.0 * sxy * sxy).sqrt()
+}
+
+// in-plane principal stresses of a Cauchy stress, largest first, and the angle of the direction of the largest
+pub fn principal_stresses(cauchy: &Array2<f64>) -> ([f64; 2], f64) {
+    let (sxx, syy, sxy) = (cauchy[[0, 0]], cauchy[[1, 1]], 0.5 * (cauchy[[0, 1]] + cauchy[[1, 0]]));
+    let center = 0.5 * (sxx + syy);
+    let radius = (0.25 * (sxx - syy).powi(2) + sxy * sxy).sqrt();
+    ([center + radius, center - radius], 0.5 * (2.0 * sxy).atan2(sxx - syy))
+}
+
+pub fn compute_triangle_stresses(material_coords: &Array2<f64>,
+    positions: &Array2<f64>,
+    triangles: &Array2<usize>,
+    model: ConstitutiveModel,
+    lambda: f64,
+    mu: f64,
+    plane_strain: bool) -> Vec<TriangleStress> {
+    compute_deformation_gradients(material_coords, positions, triangles).into_iter()
+        .map(|(f, area)| TriangleStress::new(f, area, model, lambda, mu, plane_strain))
+        .collect()
+}
+
+// Deformation gradient and reference area of every triangle from its edge vectors in the reference
+// and current configuration, F = [x_j - x_i, x_k - x_i] [X_j - X_i, X_k - X_i]^-1
+pub fn compute_deformation_gradients(material_coords: &Array2<f64>,
+    positions: &Array2<f64>,
+    triangles: &Array2<usize>) -> Vec<(Array2<f64>, f64)> {
+    let edge_matrix = |coords: &Array2<f64>, tri: ArrayView1<usize>| {
+        let (i, j, k) = (tri[0], tri[1], tri[2]);
+        array![[coords[[j, 0]] - coords[[i, 0]], coords[[k, 0]] - coords[[i, 0]]],
+               [coords[[j, 1]] - coords[[i, 1]], coords[[k, 1]] - coords[[i, 1]]]]
+    };
+    triangles.outer_iter()
+        .map(|tri| {
+            let d0 = edge_matrix(material_coords, tri);
+            let det = d0[[0, 0]] * d0[[1, 1]] - d0[[0, 1]] * d0[[1, 0]];
+            let inv_d0 = array![[d0[[1, 1]], -d0[[0, 1]]],
+                                [-d0[[1, 0]], d0[[0, 0]]]] / det;
+            (edge_matrix(positions, tri).dot(&inv_d0), 0.5 * det.abs())
+        })
+        .collect()
+}
+
+// Averages the triangle stresses to the nodes, weighting every triangle by its reference area
+pub fn compute_nodal_stresses(stresses: &[TriangleStress],
+    triangles: &Array2<usize>,
+    num_nodes: usize) -> Vec<NodalStress> {
+    let mut weights = Array1::<f64>::zeros(num_nodes);
+    let mut cauchy_zz_sums = Array1::<f64>::zeros(num_nodes);
+    let mut sums = vec![[Array2::<f64>::zeros((2, 2)), Array2::zeros((2, 2)), Array2::zeros((2, 2)),
+        Array2::zeros((2, 2))]; num_nodes];
+    for (stress, tri) in stresses.iter().zip(triangles.outer_iter()) {
+        for &node_idx in tri.iter() {
+            weights[node_idx] += stress.area;
+            cauchy_zz_sums[node_idx] += stress.area * stress.cauchy_zz;
+            let sum = &mut sums[node_idx];
+            sum[0].scaled_add(stress.area, &stress.deformation_gradient);
+            sum[1].scaled_add(stress.area, &stress.green_strain);
+            sum[2].scaled_add(stress.area, &stress.first_piola);
+            sum[3].scaled_add(stress.area, &stress.cauchy);
+        }
+    }
+
+    sums.into_iter().zip(weights.iter()).zip(cauchy_zz_sums.iter())
+        .map(|(([f, e, p, cauchy], &weight), &cauchy_zz_sum)| {
+            // nodes without triangles keep zero stress
+            let weight = if weight > 0.0 { weight } else { 1.0 };
+            let cauchy = cauchy / weight;
+            let cauchy_zz = cauchy_zz_sum / weight;
+            let (principal_stresses, principal_angle) = principal_stresses(&cauchy);
+            NodalStress {
+                deformation_gradient: f / weight,
+                green_strain: e / weight,
+                first_piola: p / weight,
+                von_mises: von_mises(&cauchy, cauchy_zz),
+                cauchy,
+                cauchy_zz,
+                principal_stresses,
+                principal_angle,
+            }
+        })
+        .collect()
+}
+
+// Scalar nodal fields of a simulator state, e.g. for coloring the mesh in a viewer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScalarField {
+    VonMises,            // nodal average of the von Mises stress
+    StrainEnergyDensity, // nodal average of Psi(F) per unit reference area
+    Speed,
+    Displacement,        // magnitude of x - X
+}
+
+impl FromStr for ScalarField {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "von_mises" => Ok(ScalarField::VonMises),
+            "strain_energy" => Ok(ScalarField::StrainEnergyDensity),
+            "speed" => Ok(ScalarField::Speed),
+            "displacement" => Ok(ScalarField::Displacement),
+            _ => Err(format!("Unknown field '{}'! Fields: \
+                {{ 'von_mises', 'strain_energy', 'speed', 'displacement' }}", name)),
+        }
+    }
+}
+
+impl ScalarField {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ScalarField::VonMises => "von Mises stress [Pa]",
+            ScalarField::StrainEnergyDensity => "strain energy density [J/m^2]",
+            ScalarField::Speed => "speed [m/s]",
+            ScalarField::Displacement => "displacement [m]",
+        }
+    }
+
+    // the following field, for cycling through all of them
+    pub fn next(&self) -> ScalarField {
+        match self {
+            ScalarField::VonMises => ScalarField::StrainEnergyDensity,
+            ScalarField::StrainEnergyDensity => ScalarField::Speed,
+            ScalarField::Speed => ScalarField::Displacement,
+            ScalarField::Displacement => ScalarField::VonMises,
+        }
+    }
+
+    // value of the field at every node in the current state of a simulator
+    pub fn nodal_values<S: Simulator + ?Sized>(&self, sim: &S) -> Vec<f64> {
+        let norms = |vectors: &Array2<f64>| vectors.outer_iter().map(|v| v.dot(&v).sqrt()).collect();
+        match self {
+            ScalarField::VonMises => sim.nodal_stresses().iter().map(|s| s.von_mises).collect(),
+            ScalarField::StrainEnergyDensity => {
+                let (lambda, mu) = sim.lame_parameters();
+                let model = sim.constitutive_model();
+                let triangles = &sim.mesh().triangles;
+                let mut sums = vec![0.0; sim.positions().nrows()];
+                let mut weights = vec![0.0; sim.positions().nrows()];
+                let gradients = compute_deformation_gradients(sim.material_coords(), sim.positions(), triangles);
+                for ((f, area), tri) in gradients.iter().zip(triangles.outer_iter()) {
+                    let psi = model.energy_density(f, lambda, mu);
+                    for &node_idx in tri.iter() {
+                        sums[node_idx] += area * psi;
+                        weights[node_idx] += area;
+                    }
+                }
+                sums.iter().zip(weights.iter())
+                    .map(|(sum, &weight)| if weight > 0.0 { sum / weight } else { 0.0 })
+                    .collect()
+            },
+            ScalarField::Speed => norms(sim.velocities()),
+            ScalarField::Displacement => norms(&(sim.positions() - sim.material_coords())),
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use ndarray::prelude::*;
+use std::str::FromStr;
+use crate::sim::error::SimError;
+use crate::sim::sparse::bicgstab;
+
+// A system of lumped nodal masses driven by forces, which can be advanced in time by any Integrator
+pub trait DynamicSystem {
+    // total nodal forces (N, 2) for the given positions and velocities at time t, which time dependent
+    // loads are evaluated at
+    fn total_forces(&self, positions: &Array2<f64>, velocities: &Array2<f64>, t: f64) -> Array2<f64>;
+
+    // lumped mass of each node (N)
+    fn nodal_masses(&self) -> &Array1<f64>;
+
+    // 1.0 for every free degree of freedom and 0.0 for every constrained one (N, 2)
+    fn free_mask(&self) -> &Array2<f64>;
+
+    // prescribed velocities of the constrained degrees of freedom at time t (N, 2), the entries of
+    // free degrees of freedom are ignored
+    fn constrained_velocities(&self, _t: f64) -> Array2<f64> {
+        Array2::zeros(self.free_mask().raw_dim())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Integrator {
+    SymplecticEuler, // semi-implicit Euler, velocities first and then positions
+    VelocityVerlet,
+    RungeKutta4,
+    ImplicitEuler,   // backward Euler solved with Newton's method
+}
+
+impl FromStr for Integrator {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "symplectic_euler" => Ok(Integrator::SymplecticEuler),
+            "velocity_verlet" => Ok(Integrator::VelocityVerlet),
+            "rk4" => Ok(Integrator::RungeKutta4),
+            "implicit_euler" => Ok(Integrator::ImplicitEuler),
+            _ => Err(format!("Unknown integrator '{}'! Integrators: \
+                {{ 'symplectic_euler', 'velocity_verlet', 'rk4', 'implicit_euler' }}", name)),
+        }
+    }
+}
+
+// maximum number of Newton iterations per implicit step
+const NEWTON_MAX_ITERS: usize = 10;
+// relative tolerance of the Newton residual
+const NEWTON_TOLERANCE: f64 = 1e-6;
+// maximum number of BiCGSTAB iterations per Newton iteration
+const LINEAR_MAX_ITERS: usize = 500;
+
+impl Integrator {
+    // advances positions and velocities from time t by one timestep dt, returning the new positions
+    // and velocities. Only the implicit integrator can fail, when its solvers do not converge.
+    pub fn step<S: DynamicSystem>(&self,
+        system: &S,
+        positions: &Array2<f64>,
+        velocities: &Array2<f64>,
+        t: f64,
+        dt: f64) -> Result<(Array2<f64>, Array2<f64>), SimError> {
+        match self {
+            Integrator::SymplecticEuler => Ok(Self::symplectic_euler(system, positions, velocities, t, dt)),
+            Integrator::VelocityVerlet => Ok(Self::velocity_verlet(system, positions, velocities, t, dt)),
+            Integrator::RungeKutta4 => Ok(Self::runge_kutta4(system, positions, velocities, t, dt)),
+            Integrator::ImplicitEuler => Self::implicit_euler(system, positions, velocities, t, dt),
+        }
+    }
+
+    fn constrain<S: DynamicSystem>(system: &S, velocities: &Array2<f64>, t: f64) -> Array2<f64> {
+        // replaces the velocities of the constrained degrees of freedom by their prescribed ones
+        let mask = system.free_mask();
+        velocities * mask + &(system.constrained_velocities(t) * &(1.0 - mask))
+    }
+
+    fn accelerations<S: DynamicSystem>(system: &S,
+        positions: &Array2<f64>,
+        velocities: &Array2<f64>,
+        t: f64) -> Array2<f64> {
+        let forces = system.total_forces(positions, velocities, t);
+        let masses = system.nodal_masses().view().insert_axis(Axis(1));
+        (forces / &masses) * system.free_mask()
+    }
+
+    fn symplectic_euler<S: DynamicSystem>(system: &S,
+        positions: &Array2<f64>,
+        velocities: &Array2<f64>,
+        t: f64,
+        dt: f64) -> (Array2<f64>, Array2<f64>) {
+        let new_velocities = Self::constrain(system,
+            &(velocities + &(dt * Self::accelerations(system, positions, velocities, t))), t + dt);
+        let new_positions = positions + &(dt * &new_velocities);
+        (new_positions, new_velocities)
+    }
+
+    fn velocity_verlet<S: DynamicSystem>(system: &S,
+        positions: &Array2<f64>,
+        velocities: &Array2<f64>,
+        t: f64,
+        dt: f64) -> (Array2<f64>, Array2<f64>) {
+        // the constrained degrees of freedom move with their prescribed velocity at the midpoint
+        let velocities = &Self::constrain(system, velocities, t);
+        let position_velocities = Self::constrain(system, velocities, t + 0.5 * dt);
+        let a0 = Self::accelerations(system, positions, velocities, t);
+        let new_positions = positions + &(dt * &position_velocities) + &(0.5 * dt * dt * &a0);
+
+        // velocity dependent forces are evaluated with the first order velocity prediction
+        let predicted_velocities = velocities + &(dt * &a0);
+        let a1 = Self::accelerations(system, &new_positions, &predicted_velocities, t + dt);
+        let new_velocities = Self::constrain(system, &(velocities + &(0.5 * dt * (a0 + a1))), t + dt);
+        (new_positions, new_velocities)
+    }
+
+    fn runge_kutta4<S: DynamicSystem>(system: &S,
+        positions: &Array2<f64>,
+        velocities: &Array2<f64>,
+        t: f64,
+        dt: f64) -> (Array2<f64>, Array2<f64>) {
+        let k1x = Self::constrain(system, velocities, t);
+        let k1v = Self::accelerations(system, positions, &k1x, t);
+
+        let x2 = positions + &(0.5 * dt * &k1x);
+        let k2x = Self::constrain(system, &(&k1x + &(0.5 * dt * &k1v)), t + 0.5 * dt);
+        let k2v = Self::accelerations(system, &x2, &k2x, t + 0.5 * dt);
+
+        let x3 = positions + &(0.5 * dt * &k2x);
+        let k3x = Self::constrain(system, &(&k1x + &(0.5 * dt * &k2v)), t + 0.5 * dt);
+        let k3v = Self::accelerations(system, &x3, &k3x, t + 0.5 * dt);
+
+        let x4 = positions + &(dt * &k3x);
+        let k4x = Self::constrain(system, &(&k1x + &(dt * &k3v)), t + dt);
+        let k4v = Self::accelerations(system, &x4, &k4x, t + dt);
+
+        let new_positions = positions + &((dt / 6.0) * (&k1x + &(2.0 * k2x) + &(2.0 * k3x) + &k4x));
+        let new_velocities = Self::constrain(system,
+            &(&k1x + &((dt / 6.0) * (k1v + 2.0 * k2v + 2.0 * k3v + k4v))), t + dt);
+        (new_positions, new_velocities)
+    }
+
+    fn implicit_euler<S: DynamicSystem>(system: &S,
+        positions: &Array2<f64>,
+        velocities: &Array2<f64>,
+        t: f64,
+        dt: f64) -> Result<(Array2<f64>, Array2<f64>), SimError> {
+        // Solves g(v) = M (v - v_n) - dt f(x_n + dt v, v) = 0 for the new velocities v with Newton's
+        // method. The Jacobian J = M - dt (df/dx dt + df/dv) is never assembled; its products with a
+        // vector w are approximated with the finite difference of f in the direction (dt w, w).
+        // Follower pressures make J non-symmetric and St. Venant-Kirchhoff under compression makes
+        // it indefinite, so the Newton steps are solved with BiCGSTAB rather than CG.
+        // The constrained degrees of freedom keep their prescribed velocities at t + dt.
+        let num_nodes = positions.nrows();
+        let mask = system.free_mask();
+        let masses = system.nodal_masses().view().insert_axis(Axis(1)).to_owned();
+
+        let flatten = |a: Array2<f64>| -> Array1<f64> {
+            a.into_shape_with_order(2 * num_nodes).expect("Could not flatten array")
+        };
+        let unflatten = |a: Array1<f64>| -> Array2<f64> {
+            a.into_shape_with_order((num_nodes, 2)).expect("Could not unflatten array")
+        };
+
+        let mass_diagonal = flatten(&masses * &Array2::<f64>::ones((num_nodes, 2)));
+        let flat_mask = flatten(mask.clone());
+        // Jacobi preconditioner, using the mass diagonal as an approximation of the Jacobian diagonal
+        let inv_diagonal = mass_diagonal.mapv(|m| 1.0 / m);
+
+        let mut new_velocities = Self::constrain(system, velocities, t + dt);
+        let mut reference_norm: Option<f64> = None;
+
+        for iteration in 0..=NEWTON_MAX_ITERS {
+            let new_positions = positions + &(dt * &new_velocities);
+            let forces = system.total_forces(&new_positions, &new_velocities, t + dt);
+
+            let residual = flatten((&masses * &(&new_velocities - velocities) - dt * &forces) * mask);
+            let residual_norm = residual.dot(&residual).sqrt();
+            let reference_norm = *reference_norm.get_or_insert(residual_norm.max(f64::MIN_POSITIVE));
+            if residual_norm <= NEWTON_TOLERANCE * reference_norm {
+                break;
+            }
+            if iteration == NEWTON_MAX_ITERS || !residual_norm.is_finite() {
+                return Err(SimError::NewtonDiverged {
+                    iterations: iteration,
+                    residual: residual_norm / reference_norm,
+                    time: t + dt,
+                });
+            }
+
+            let position_norm = new_positions.iter().map(|x| x * x).sum::<f64>().sqrt();
+            let apply_jacobian = |w: &Array1<f64>| -> Array1<f64> {
+                let w_norm = w.dot(w).sqrt();
+                if w_norm == 0.0 {
+                    return Array1::zeros(w.len());
+                }
+                let eps = f64::EPSILON.sqrt() * (1.0 + position_norm) / w_norm;
+                let w_2d = unflatten(w * &flat_mask);
+                let perturbed_forces = system.total_forces(
+                    &(&new_positions + &(eps * dt * &w_2d)),
+                    &(&new_velocities + &(eps * &w_2d)),
+                    t + dt);
+                let force_derivative = flatten((perturbed_forces - &forces) / eps);
+                // constrained degrees of freedom keep an identity row
+                &flat_mask * &(&mass_diagonal * w - dt * force_derivative) + &((1.0 - &flat_mask) * w)
+            };
+
+            let result = bicgstab(apply_jacobian, &(-&residual), Array1::zeros(2 * num_nodes),
+                &inv_diagonal, NEWTON_TOLERANCE, LINEAR_MAX_ITERS);
+            if !result.converged {
+                return Err(SimError::SolverDiverged {
+                    iterations: result.iterations,
+                    residual: result.residual_norm,
+                    time: t + dt,
+                });
+            }
+            new_velocities = new_velocities + unflatten(result.solution) * mask;
+        }
+
+        let new_positions = positions + &(dt * &new_velocities);
+        Ok((new_positions, new_velocities))
+    }
+}
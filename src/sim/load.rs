@@ -0,0 +1,135 @@
+use std::fmt;
+use std::sync::Arc;
+
+// Scalar load factor as a function of time, which scales a load such as a traction force, gravity
+// or a prescribed displacement
+#[derive(Clone)]
+pub enum LoadCurve {
+    Constant(f64),
+    // piecewise linear interpolation of (time, value) points sorted by time, constant beyond the
+    // first and last point. A repeated time gives a jump.
+    Table(Vec<(f64, f64)>),
+    // 0 before start, rising linearly to 1 at end and 1 afterwards
+    Ramp { start: f64, end: f64 },
+    // offset + amplitude * sin(2 pi frequency t + phase)
+    Sine { amplitude: f64, frequency: f64, phase: f64, offset: f64 },
+    // 0 before time and 1 from time on
+    Step { time: f64 },
+    Custom(Arc<dyn Fn(f64) -> f64 + Send + Sync>),
+}
+
+impl fmt::Debug for LoadCurve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadCurve::Constant(value) => write!(f, "Constant({})", value),
+            LoadCurve::Table(points) => write!(f, "Table({:?})", points),
+            LoadCurve::Ramp { start, end } => write!(f, "Ramp {{ start: {}, end: {} }}", start, end),
+            LoadCurve::Sine { amplitude, frequency, phase, offset } =>
+                write!(f, "Sine {{ amplitude: {}, frequency: {}, phase: {}, offset: {} }}",
+                    amplitude, frequency, phase, offset),
+            LoadCurve::Step { time } => write!(f, "Step {{ time: {} }}", time),
+            LoadCurve::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl Default for LoadCurve {
+    fn default() -> LoadCurve {
+        LoadCurve::Constant(1.0)
+    }
+}
+
+impl LoadCurve {
+    pub fn custom<F: Fn(f64) -> f64 + Send + Sync + 'static>(curve: F) -> LoadCurve {
+        LoadCurve::Custom(Arc::new(curve))
+    }
+
+    // 1 from start until end and 0 otherwise
+    pub fn pulse(start: f64, end: f64) -> LoadCurve {
+        LoadCurve::Table(vec![(start, 0.0), (start, 1.0), (end, 1.0), (end, 0.0)])
+    }
+
+    pub fn value(&self, t: f64) -> f64 {
+        match self {
+            LoadCurve::Constant(value) => *value,
+            LoadCurve::Table(points) => {
+                match (points.first(), points.last()) {
+                    (Some(&(t_first, v_first)), Some(&(t_last, v_last))) => {
+                        if t < t_first {
+                            return v_first;
+                        }
+                        if t >= t_last {
+                            return v_last;
+                        }
+                        // the last segment starting at or before t, so that jumps take the later value
+                        points.windows(2)
+                            .filter(|segment| segment[0].0 <= t && t < segment[1].0)
+                            .map(|segment| {
+                                let ((t0, v0), (t1, v1)) = (segment[0], segment[1]);
+                                v0 + (v1 - v0) * (t - t0) / (t1 - t0)
+                            })
+                            .last()
+                            .unwrap_or(v_last)
+                    },
+                    _ => 0.0,
+                }
+            },
+            LoadCurve::Ramp { start, end } => {
+                if t <= *start {
+                    0.0
+                } else if t >= *end {
+                    1.0
+                } else {
+                    (t - start) / (end - start)
+                }
+            },
+            LoadCurve::Sine { amplitude, frequency, phase, offset } =>
+                offset + amplitude * (2.0 * std::f64::consts::PI * frequency * t + phase).sin(),
+            LoadCurve::Step { time } => if t >= *time { 1.0 } else { 0.0 },
+            LoadCurve::Custom(curve) => curve(t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_values(curve: &LoadCurve, expected: &[(f64, f64)]) -> () {
+        for &(t, value) in expected {
+            assert!((curve.value(t) - value).abs() < 1e-12, "{:?} at t = {} is {} instead of {}",
+                curve, t, curve.value(t), value);
+        }
+    }
+
+    #[test]
+    fn ramp_and_step() {
+        assert_values(&LoadCurve::Ramp { start: 1.0, end: 3.0 },
+            &[(-1.0, 0.0), (1.0, 0.0), (1.5, 0.25), (2.0, 0.5), (3.0, 1.0), (10.0, 1.0)]);
+        assert_values(&LoadCurve::Step { time: 2.0 }, &[(0.0, 0.0), (1.999, 0.0), (2.0, 1.0), (5.0, 1.0)]);
+        assert_values(&LoadCurve::Constant(0.3), &[(-1.0, 0.3), (1e6, 0.3)]);
+        assert_values(&LoadCurve::Sine { amplitude: 2.0, frequency: 0.5, phase: 0.0, offset: 1.0 },
+            &[(0.0, 1.0), (0.5, 3.0), (1.5, -1.0)]);
+        assert_values(&LoadCurve::custom(|t| t * t), &[(3.0, 9.0)]);
+    }
+
+    #[test]
+    fn table_interpolates_and_clamps() {
+        let curve = LoadCurve::Table(vec![(1.0, 2.0), (2.0, 4.0), (4.0, 0.0)]);
+        assert_values(&curve, &[
+            (-5.0, 2.0), (1.0, 2.0),               // clamped before the first point
+            (1.5, 3.0), (2.0, 4.0), (3.0, 2.0),     // linear between the points
+            (4.0, 0.0), (100.0, 0.0),              // clamped after the last point
+        ]);
+        assert_values(&LoadCurve::Table(vec![(1.0, 5.0)]), &[(0.0, 5.0), (1.0, 5.0), (2.0, 5.0)]);
+        assert_values(&LoadCurve::Table(Vec::new()), &[(0.0, 0.0)]);
+    }
+
+    #[test]
+    fn repeated_times_jump_to_the_later_value() {
+        assert_values(&LoadCurve::pulse(1.0, 2.0),
+            &[(0.5, 0.0), (0.999, 0.0), (1.0, 1.0), (1.5, 1.0), (1.999, 1.0), (2.0, 0.0), (3.0, 0.0)]);
+        assert_values(&LoadCurve::Table(vec![(0.0, 0.0), (1.0, 1.0), (1.0, 3.0), (2.0, 5.0)]),
+            &[(0.5, 0.5), (1.0, 3.0), (1.5, 4.0)]);
+    }
+}
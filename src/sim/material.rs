@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+use lazy_static::lazy_static;
+use crate::sim::error::SimError;
+
+#[derive(Clone)]
+pub struct Material {
+    pub young_modulus: f64, // Young's modulus, 
+    pub nu: f64,            // Poisson ratio
+    pub rho: f64,           // material density
+}
+
+impl Material {
+    pub fn from_name(name: &str) -> Result<Material, SimError> {
+        MATERIALS.get(name)
+            .cloned()
+            .ok_or_else(|| SimError::UnknownMaterial { name: name.to_string() })
+    }
+
+    pub fn lame_parameters(&self) -> (f64, f64) {
+        // returns the first and second Lamé coefficients (lambda, mu)
+        let lambda = (self.young_modulus * self.nu) / ((1.0+self.nu)*(1.0-2.0*self.nu));
+        let mu = self.young_modulus / (2.0 * (1.0+self.nu));
+        (lambda, mu)
+    }
+
+    pub fn plane_stress_lame_parameters(&self) -> (f64, f64) {
+        // Lamé coefficients of a thin plate, whose first coefficient is reduced to E nu / (1 - nu^2)
+        let lambda = (self.young_modulus * self.nu) / (1.0 - self.nu * self.nu);
+        let mu = self.young_modulus / (2.0 * (1.0+self.nu));
+        (lambda, mu)
+    }
+
+    pub fn p_wave_speed(&self) -> f64 {
+        // speed of the fastest (compressional) elastic wave, c_p = sqrt((lambda + 2 mu) / rho)
+        let (lambda, mu) = self.lame_parameters();
+        ((lambda + 2.0 * mu) / self.rho).sqrt()
+    }
+}
+
+lazy_static! {
+    pub static ref MATERIALS: HashMap<&'static str, Material> = HashMap::from([
+            ("default", Material {young_modulus: 10e5, nu: 0.3, rho: 1000.0}),
+            ("rubber", Material {young_modulus: 0.01e9, nu: 0.48, rho: 1050.0}),
+        ]);
+}
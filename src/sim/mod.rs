@@ -1,2 +1,194 @@
 pub mod cauchy_fvm;
-//pub mod cauchy_fem;
+pub mod cauchy_fvm_seq;
+pub mod cauchy_fem;
+pub mod material;
+pub mod boundary;
+pub mod sparse;
+pub mod integrator;
+pub mod error;
+pub mod constitutive;
+pub mod damping;
+pub mod statics;
+pub mod load;
+pub mod body_force;
+pub mod fields;
+pub mod recorder;
+pub mod checkpoint;
+pub mod diagnostics;
+
+use ndarray::{Array1, Array2};
+use crate::mesh::TriangleMesh;
+use crate::sim::error::SimError;
+use crate::sim::constitutive::ConstitutiveModel;
+use crate::sim::boundary::{Axes, Constraint, Motion, Region, Traction};
+use crate::sim::load::LoadCurve;
+use crate::sim::body_force::{BodyForce, BodyForces};
+use crate::sim::material::Material;
+use crate::sim::fields::{compute_nodal_stresses, compute_triangle_stresses, NodalStress, TriangleStress};
+use crate::sim::checkpoint::{CheckpointError, SimulatorState};
+use crate::sim::diagnostics::Diagnostics;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Instant;
+
+// Common interface of all simulators, so that viewers, examples and benchmarks can drive any
+// solver interchangeably
+pub trait Simulator: Send + Sync {
+    // advance the simulation by one timestep, failing if the state diverged or triangles got inverted
+    fn update(&mut self) -> Result<(), SimError>;
+
+    // current simulation time
+    fn time(&self) -> f64;
+
+    // simulation mesh holding the current (deformed) vertex positions
+    fn mesh(&self) -> &TriangleMesh;
+
+    // current vertex positions (N, 2)
+    fn positions(&self) -> &Array2<f64> {
+        &self.mesh().vertices
+    }
+
+    // undeformed vertex positions (N, 2)
+    fn material_coords(&self) -> &Array2<f64>;
+
+    // current nodal velocities (N, 2)
+    fn velocities(&self) -> &Array2<f64>;
+
+    // net nodal forces (N, 2) in the current state, i.e. the elastic, damping and external forces
+    fn forces(&self) -> Array2<f64>;
+
+    fn material(&self) -> &Material;
+
+    // lumped mass of every node (N)
+    fn lumped_masses(&self) -> Array1<f64>;
+
+    // fails with SimError::UnstableTimestep if the timestep exceeds the stable timestep of an
+    // explicit integrator. The simulation still runs, so frontends report it as a warning.
+    fn check_timestep(&self) -> Result<(), SimError> {
+        Ok(())
+    }
+
+    // Lamé coefficients (lambda, mu) of the elastic forces
+    fn lame_parameters(&self) -> (f64, f64) {
+        self.material().lame_parameters()
+    }
+
+    // whether the elastic forces are those of plane strain, with an out-of-plane stress, or of
+    // plane stress
+    fn plane_strain(&self) -> bool {
+        true
+    }
+
+    // deformation gradient, strain and stresses of every triangle in the current state
+    fn triangle_stresses(&self) -> Vec<TriangleStress> {
+        let (lambda, mu) = self.lame_parameters();
+        compute_triangle_stresses(self.material_coords(), self.positions(), &self.mesh().triangles,
+            self.constitutive_model(), lambda, mu, self.plane_strain())
+    }
+
+    // area weighted averages of the triangle stresses at every node
+    fn nodal_stresses(&self) -> Vec<NodalStress> {
+        compute_nodal_stresses(&self.triangle_stresses(), &self.mesh().triangles, self.positions().nrows())
+    }
+
+    fn set_material(&mut self, name: &str) -> Result<(), SimError>;
+
+    // hyperelastic model used to compute the stresses from the deformation gradients
+    fn set_constitutive_model(&mut self, model: ConstitutiveModel) -> ();
+    fn constitutive_model(&self) -> ConstitutiveModel;
+
+    // clamps all nodes of the region, replacing the previous immovable boundary
+    fn set_immovable_boundary(&mut self, region: &Region) -> Result<(), SimError>;
+    fn clear_immovable_boundary(&mut self) -> ();
+    fn immovable_boundary(&self) -> &[usize];
+
+    // adds a traction with a force per unit length on the boundary edges of a region, returning its index
+    fn add_traction(&mut self, region: &Region, force_vector: Array1<f64>) -> Result<usize, SimError>;
+    // adds a follower pressure normal to the deformed boundary edges of a region, returning its index
+    fn add_pressure(&mut self, region: &Region, pressure: f64) -> Result<usize, SimError>;
+    // changes the force of the traction with the given index, failing if there is no such traction
+    fn set_traction_force(&mut self, traction_idx: usize, force_vector: Array1<f64>) -> Result<(), SimError>;
+    fn set_traction_pressure(&mut self, traction_idx: usize, pressure: f64) -> Result<(), SimError>;
+    // scales the force of the traction region with the given index by a load curve over time
+    fn set_traction_curve(&mut self, traction_idx: usize, curve: LoadCurve) -> Result<(), SimError>;
+    fn clear_tractions(&mut self) -> ();
+    fn tractions(&self) -> &[Traction];
+
+    // gravitational acceleration, applied as rho * g * area to every node
+    fn set_gravity(&mut self, gravity: Array1<f64>) -> ();
+    fn gravity(&self) -> &Array1<f64>;
+    // scales gravity by a load curve over time
+    fn set_gravity_curve(&mut self, curve: LoadCurve) -> ();
+    // adds a body force field per unit mass on top of gravity, returning its index
+    fn add_body_force(&mut self, body_force: BodyForce) -> usize;
+    fn clear_body_forces(&mut self) -> ();
+    fn body_forces(&self) -> &BodyForces;
+
+    // constrains some axes of all nodes of the region to a prescribed motion, returning its index.
+    // Later constraints override earlier ones on the same nodes, the immovable boundary overrides all.
+    fn add_constraint(&mut self, region: &Region, axes: Axes, motion: Motion) -> Result<usize, SimError>;
+    fn clear_constraints(&mut self) -> ();
+    fn constraints(&self) -> &[Constraint];
+
+    // energies and momenta of the current state
+    fn diagnostics(&self) -> Diagnostics {
+        Diagnostics::new(self)
+    }
+
+    // everything needed to rebuild the simulator in its current state, failing if a load curve,
+    // body force or prescribed motion is a closure
+    fn checkpoint_state(&self) -> Result<SimulatorState, CheckpointError>;
+
+    // writes a checkpoint that checkpoint::load_checkpoint restores bit for bit
+    fn save_checkpoint(&self, path: &Path) -> Result<(), CheckpointError> {
+        self.checkpoint_state()?.save(path)
+    }
+
+    fn benchmark(&mut self, iters: usize) -> Result<(), SimError> {
+        // simple benchmarking function for checking how many updates per second we can get
+        let now = Instant::now();
+        let mut last_elapsed: u128 = 0;
+
+        for i in 0..iters {
+            self.update()?;
+
+            if i % 100 == 0 {
+                let elapsed_millisecs = now.elapsed().as_millis() - last_elapsed;
+                last_elapsed += elapsed_millisecs;
+                println!("updates/sec: {}", (1.0)/((elapsed_millisecs) as f64) * 100.0 * 1000.0);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimulatorKind {
+    Fvm,           // parallel explicit FVM
+    FvmSequential, // sequential explicit FVM
+    Fem,           // implicit corotated linear FEM
+}
+
+impl FromStr for SimulatorKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "fvm" => Ok(SimulatorKind::Fvm),
+            "fvm_seq" => Ok(SimulatorKind::FvmSequential),
+            "fem" => Ok(SimulatorKind::Fem),
+            _ => Err(format!("Unknown simulator '{}'! Simulators: {{ 'fvm', 'fvm_seq', 'fem' }}", name)),
+        }
+    }
+}
+
+pub fn new_simulator(kind: SimulatorKind,
+    mesh: &TriangleMesh,
+    material_name: &str,
+    dt: f64) -> Result<Box<dyn Simulator>, SimError> {
+    Ok(match kind {
+        SimulatorKind::Fvm => Box::new(cauchy_fvm::CauchyFVM::new(mesh, material_name, dt)?),
+        SimulatorKind::FvmSequential => Box::new(cauchy_fvm_seq::CauchyFVM::new(mesh, material_name, dt)?),
+        SimulatorKind::Fem => Box::new(cauchy_fem::CauchyFEM::new(mesh, material_name, dt)?),
+    })
+}
@@ -0,0 +1,183 @@
+use ndarray::prelude::*;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use crate::sim::Simulator;
+use crate::sim::fields::compute_nodal_stresses;
+
+// Writes every Nth recorded state of a simulator as a VTK XML unstructured grid (.vtu) and keeps a
+// ParaView collection (.pvd) of all written files with their simulation times, so that runs can be
+// post-processed as a time series
+pub struct VtkRecorder {
+    directory: PathBuf,
+    name: String,
+    every: usize,                  // writes every Nth call of record
+    num_recorded: usize,
+    snapshots: Vec<(f64, String)>, // simulation time and file name of every written state
+}
+
+impl VtkRecorder {
+    pub fn new<P: AsRef<Path>>(directory: P, name: &str, every: usize) -> io::Result<VtkRecorder> {
+        fs::create_dir_all(&directory)?;
+        Ok(VtkRecorder {
+            directory: directory.as_ref().to_path_buf(),
+            name: name.to_string(),
+            every: every.max(1),
+            num_recorded: 0,
+            snapshots: Vec::new(),
+        })
+    }
+
+    // records the current state, which is written if it is the first or every Nth after it. Returns
+    // whether the state was written.
+    pub fn record<S: Simulator + ?Sized>(&mut self, sim: &S) -> io::Result<bool> {
+        let write = self.num_recorded.is_multiple_of(self.every);
+        self.num_recorded += 1;
+        if write {
+            self.write_snapshot(sim)?;
+        }
+        Ok(write)
+    }
+
+    // writes the current state regardless of the recording interval, returning the path of the .vtu file
+    pub fn write_snapshot<S: Simulator + ?Sized>(&mut self, sim: &S) -> io::Result<PathBuf> {
+        let file_name = format!("{}_{:06}.vtu", self.name, self.snapshots.len());
+        let path = self.directory.join(&file_name);
+        write_vtu(&path, sim)?;
+        self.snapshots.push((sim.time(), file_name));
+
+        // the collection is rewritten every time, so it stays valid if the run is interrupted
+        self.write_collection()?;
+        Ok(path)
+    }
+
+    pub fn collection_path(&self) -> PathBuf {
+        self.directory.join(format!("{}.pvd", self.name))
+    }
+
+    pub fn num_snapshots(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    fn write_collection(&self) -> io::Result<()> {
+        let mut file = BufWriter::new(fs::File::create(self.collection_path())?);
+        writeln!(file, "<?xml version=\"1.0\"?>")?;
+        writeln!(file, "<VTKFile type=\"Collection\" version=\"0.1\" byte_order=\"LittleEndian\">")?;
+        writeln!(file, "  <Collection>")?;
+        for (time, file_name) in self.snapshots.iter() {
+            writeln!(file, "    <DataSet timestep=\"{}\" group=\"\" part=\"0\" file=\"{}\"/>", time, file_name)?;
+        }
+        writeln!(file, "  </Collection>")?;
+        writeln!(file, "</VTKFile>")?;
+        file.flush()
+    }
+}
+
+fn write_data_array<W: Write, I: Iterator<Item = f64>>(file: &mut W,
+    name: &str,
+    num_components: usize,
+    values: I) -> io::Result<()> {
+    writeln!(file, "        <DataArray type=\"Float64\" Name=\"{}\" NumberOfComponents=\"{}\" format=\"ascii\">",
+        name, num_components)?;
+    let values: Vec<String> = values.map(|value| value.to_string()).collect();
+    for row in values.chunks(num_components) {
+        writeln!(file, "          {}", row.join(" "))?;
+    }
+    writeln!(file, "        </DataArray>")
+}
+
+// 2D vectors as 3D vectors at z = 0
+fn vector_components(vectors: &Array2<f64>) -> impl Iterator<Item = f64> + '_ {
+    vectors.outer_iter().flat_map(|v| [v[0], v[1], 0.0])
+}
+
+// 2 x 2 tensors with their out-of-plane zz component as 3 x 3 tensors in row-major order, which
+// ParaView shows as tensors
+fn tensor_components(tensors: &[(&Array2<f64>, f64)]) -> impl Iterator<Item = f64> {
+    tensors.iter()
+        .flat_map(|&(t, zz)| [t[[0, 0]], t[[0, 1]], 0.0, t[[1, 0]], t[[1, 1]], 0.0, 0.0, 0.0, zz])
+        .collect::<Vec<f64>>()
+        .into_iter()
+}
+
+// Writes the current state of a simulator as an ASCII .vtu file on the deformed mesh, with the
+// displacement, velocity and force as point data and the Green strain, first Piola-Kirchhoff and
+// Cauchy stress, von Mises stress and principal stresses as cell data. The nodal averages of the
+// Cauchy and von Mises stress are added as point data for smooth plots.
+pub fn write_vtu<P: AsRef<Path>, S: Simulator + ?Sized>(path: P, sim: &S) -> io::Result<()> {
+    let positions = sim.positions();
+    let triangles = &sim.mesh().triangles;
+    let displacements = positions - sim.material_coords();
+    let forces = sim.forces();
+    let stresses = sim.triangle_stresses();
+    let nodal_stresses = compute_nodal_stresses(&stresses, triangles, positions.nrows());
+    let (num_points, num_cells) = (positions.nrows(), triangles.nrows());
+
+    let mut file = BufWriter::new(fs::File::create(path)?);
+    writeln!(file, "<?xml version=\"1.0\"?>")?;
+    writeln!(file, "<VTKFile type=\"UnstructuredGrid\" version=\"0.1\" byte_order=\"LittleEndian\">")?;
+    writeln!(file, "  <UnstructuredGrid>")?;
+    writeln!(file, "    <FieldData>")?;
+    writeln!(file, "      <DataArray type=\"Float64\" Name=\"TimeValue\" NumberOfTuples=\"1\" format=\"ascii\">{}</DataArray>", sim.time())?;
+    writeln!(file, "    </FieldData>")?;
+    writeln!(file, "    <Piece NumberOfPoints=\"{}\" NumberOfCells=\"{}\">", num_points, num_cells)?;
+
+    writeln!(file, "      <Points>")?;
+    write_data_array(&mut file, "Points", 3, vector_components(positions))?;
+    writeln!(file, "      </Points>")?;
+
+    writeln!(file, "      <Cells>")?;
+    writeln!(file, "        <DataArray type=\"Int64\" Name=\"connectivity\" format=\"ascii\">")?;
+    for tri in triangles.outer_iter() {
+        writeln!(file, "          {} {} {}", tri[0], tri[1], tri[2])?;
+    }
+    writeln!(file, "        </DataArray>")?;
+    writeln!(file, "        <DataArray type=\"Int64\" Name=\"offsets\" format=\"ascii\">")?;
+    for cell_idx in 0..num_cells {
+        writeln!(file, "          {}", 3 * (cell_idx + 1))?;
+    }
+    writeln!(file, "        </DataArray>")?;
+    // 5 is VTK_TRIANGLE
+    writeln!(file, "        <DataArray type=\"UInt8\" Name=\"types\" format=\"ascii\">")?;
+    for _ in 0..num_cells {
+        writeln!(file, "          5")?;
+    }
+    writeln!(file, "        </DataArray>")?;
+    writeln!(file, "      </Cells>")?;
+
+    writeln!(file, "      <PointData Vectors=\"displacement\">")?;
+    write_data_array(&mut file, "displacement", 3, vector_components(&displacements))?;
+    write_data_array(&mut file, "velocity", 3, vector_components(sim.velocities()))?;
+    write_data_array(&mut file, "force", 3, vector_components(&forces))?;
+    let nodal_cauchys: Vec<(&Array2<f64>, f64)> = nodal_stresses.iter().map(|s| (&s.cauchy, s.cauchy_zz)).collect();
+    write_data_array(&mut file, "nodal_cauchy_stress", 9, tensor_components(&nodal_cauchys))?;
+    write_data_array(&mut file, "nodal_von_mises", 1, nodal_stresses.iter().map(|s| s.von_mises))?;
+    writeln!(file, "      </PointData>")?;
+
+    // E_zz vanishes in plane strain and is left out in plane stress, and P_zz = J sigma_zz as F_zz = 1
+    // in plane strain and sigma_zz = 0 in plane stress
+    let green_strains: Vec<(&Array2<f64>, f64)> = stresses.iter().map(|s| (&s.green_strain, 0.0)).collect();
+    let first_piolas: Vec<(&Array2<f64>, f64)> = stresses.iter()
+        .map(|s| {
+            let f = &s.deformation_gradient;
+            (&s.first_piola, (f[[0, 0]] * f[[1, 1]] - f[[0, 1]] * f[[1, 0]]) * s.cauchy_zz)
+        })
+        .collect();
+    let cauchys: Vec<(&Array2<f64>, f64)> = stresses.iter().map(|s| (&s.cauchy, s.cauchy_zz)).collect();
+    writeln!(file, "      <CellData Scalars=\"von_mises\" Tensors=\"cauchy_stress\">")?;
+    write_data_array(&mut file, "green_strain", 9, tensor_components(&green_strains))?;
+    write_data_array(&mut file, "first_piola_stress", 9, tensor_components(&first_piolas))?;
+    write_data_array(&mut file, "cauchy_stress", 9, tensor_components(&cauchys))?;
+    write_data_array(&mut file, "von_mises", 1, stresses.iter().map(|s| s.von_mises))?;
+    write_data_array(&mut file, "max_principal_stress", 1, stresses.iter().map(|s| s.principal_stresses[0]))?;
+    write_data_array(&mut file, "min_principal_stress", 1, stresses.iter().map(|s| s.principal_stresses[1]))?;
+    // unit vector of the largest principal direction
+    write_data_array(&mut file, "principal_direction", 3,
+        stresses.iter().flat_map(|s| [s.principal_angle.cos(), s.principal_angle.sin(), 0.0]))?;
+    writeln!(file, "      </CellData>")?;
+
+    writeln!(file, "    </Piece>")?;
+    writeln!(file, "  </UnstructuredGrid>")?;
+    writeln!(file, "</VTKFile>")?;
+    file.flush()
+}
@@ -0,0 +1,307 @@
+use ndarray::prelude::*;
+use rayon::prelude::*;
+
+// Sparse matrix in compressed sparse row (CSR) format
+#[derive(Clone)]
+pub struct CsrMatrix {
+    nrows: usize,
+    ncols: usize,
+    indptr: Vec<usize>,  // (nrows + 1) offsets into indices/values for each row
+    indices: Vec<usize>, // column index of each stored entry
+    values: Vec<f64>,    // value of each stored entry
+}
+
+impl CsrMatrix {
+    // Builds a CSR matrix from (row, col, value) triplets. Duplicate entries are summed, which is
+    // exactly what is needed when scattering element matrices into a global matrix.
+    pub fn from_triplets(nrows: usize, ncols: usize, mut triplets: Vec<(usize, usize, f64)>) -> CsrMatrix {
+        triplets.par_sort_unstable_by_key(|&(row, col, _)| (row, col));
+
+        let mut indptr = vec![0; nrows + 1];
+        let mut indices = Vec::<usize>::with_capacity(triplets.len());
+        let mut values = Vec::<f64>::with_capacity(triplets.len());
+
+        let mut last: Option<(usize, usize)> = None;
+        for (row, col, value) in triplets {
+            if last == Some((row, col)) {
+                *values.last_mut().unwrap() += value;
+            } else {
+                indices.push(col);
+                values.push(value);
+                indptr[row + 1] += 1;
+                last = Some((row, col));
+            }
+        }
+
+        // turn the row counts into offsets
+        for row in 0..nrows {
+            indptr[row + 1] += indptr[row];
+        }
+
+        CsrMatrix { nrows, ncols, indptr, indices, values }
+    }
+
+    pub fn nrows(&self) -> usize {
+        self.nrows
+    }
+
+    pub fn ncols(&self) -> usize {
+        self.ncols
+    }
+
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    // computes the matrix-vector product Ax in parallel across rows
+    pub fn dot(&self, x: &Array1<f64>) -> Array1<f64> {
+        let result: Vec<f64> = (0..self.nrows).into_par_iter()
+            .map(|row| {
+                (self.indptr[row]..self.indptr[row + 1])
+                    .map(|idx| self.values[idx] * x[self.indices[idx]])
+                    .sum()
+            })
+            .collect();
+        Array1::from_vec(result)
+    }
+
+    pub fn diagonal(&self) -> Array1<f64> {
+        let mut diagonal = Array1::<f64>::zeros(self.nrows.min(self.ncols));
+        for row in 0..diagonal.len() {
+            for idx in self.indptr[row]..self.indptr[row + 1] {
+                if self.indices[idx] == row {
+                    diagonal[row] += self.values[idx];
+                }
+            }
+        }
+        diagonal
+    }
+}
+
+// result of the iterative linear solvers
+pub struct CgResult {
+    pub solution: Array1<f64>,
+    pub iterations: usize,
+    pub residual_norm: f64, // norm of the final residual relative to the norm of b
+    pub converged: bool,
+}
+
+// Solves Ax = b with the Jacobi preconditioned conjugate gradient method, for a symmetric positive
+// definite operator A given as a function computing the product Ax. The preconditioner is given by
+// the inverse of the diagonal of A.
+pub fn conjugate_gradient<F>(apply: F,
+    b: &Array1<f64>,
+    x0: Array1<f64>,
+    inv_diagonal: &Array1<f64>,
+    tolerance: f64,
+    max_iters: usize) -> CgResult
+where
+    F: Fn(&Array1<f64>) -> Array1<f64>,
+{
+    let b_norm = b.dot(b).sqrt();
+    if b_norm == 0.0 {
+        return CgResult { solution: Array1::zeros(b.len()), iterations: 0, residual_norm: 0.0, converged: true };
+    }
+
+    let mut x = x0;
+    let mut r = b - &apply(&x);
+    let mut z = inv_diagonal * &r;
+    let mut p = z.clone();
+    let mut rz = r.dot(&z);
+
+    let mut residual_norm = r.dot(&r).sqrt() / b_norm;
+    let mut iterations = 0;
+
+    while residual_norm > tolerance && iterations < max_iters {
+        let ap = apply(&p);
+        let p_ap = p.dot(&ap);
+        if p_ap <= 0.0 {
+            // the operator is not positive definite in this direction, so CG cannot continue
+            break;
+        }
+        let alpha = rz / p_ap;
+
+        x.scaled_add(alpha, &p);
+        r.scaled_add(-alpha, &ap);
+
+        z = inv_diagonal * &r;
+        let rz_new = r.dot(&z);
+        let beta = rz_new / rz;
+        rz = rz_new;
+
+        p = &z + &(beta * &p);
+
+        residual_norm = r.dot(&r).sqrt() / b_norm;
+        iterations += 1;
+    }
+
+    CgResult { solution: x, iterations, converged: residual_norm <= tolerance, residual_norm }
+}
+
+// Solves Ax = b with the Jacobi preconditioned BiCGSTAB method, for a general nonsingular operator
+// A, e.g. a Jacobian that is not symmetric or not positive definite. Every iteration costs two
+// products with A.
+pub fn bicgstab<F>(apply: F,
+    b: &Array1<f64>,
+    x0: Array1<f64>,
+    inv_diagonal: &Array1<f64>,
+    tolerance: f64,
+    max_iters: usize) -> CgResult
+where
+    F: Fn(&Array1<f64>) -> Array1<f64>,
+{
+    let b_norm = b.dot(b).sqrt();
+    if b_norm == 0.0 {
+        return CgResult { solution: Array1::zeros(b.len()), iterations: 0, residual_norm: 0.0, converged: true };
+    }
+
+    let mut x = x0;
+    let mut r = b - &apply(&x);
+    let r_hat = r.clone();
+    let mut p = Array1::<f64>::zeros(b.len());
+    let mut v = Array1::<f64>::zeros(b.len());
+    let (mut rho, mut alpha, mut omega) = (1.0, 1.0, 1.0);
+
+    let mut residual_norm = r.dot(&r).sqrt() / b_norm;
+    let mut iterations = 0;
+
+    while residual_norm > tolerance && iterations < max_iters {
+        let rho_new = r_hat.dot(&r);
+        if rho_new == 0.0 {
+            // breakdown, the residual became orthogonal to the shadow residual
+            break;
+        }
+        let beta = (rho_new / rho) * (alpha / omega);
+        rho = rho_new;
+        p = &r + &(beta * (&p - &(omega * &v)));
+
+        let y = inv_diagonal * &p;
+        v = apply(&y);
+        let r_hat_v = r_hat.dot(&v);
+        if r_hat_v == 0.0 {
+            break;
+        }
+        alpha = rho / r_hat_v;
+        x.scaled_add(alpha, &y);
+        r.scaled_add(-alpha, &v);
+        iterations += 1;
+
+        residual_norm = r.dot(&r).sqrt() / b_norm;
+        if residual_norm <= tolerance {
+            break;
+        }
+
+        let z = inv_diagonal * &r;
+        let t = apply(&z);
+        let t_t = t.dot(&t);
+        if t_t == 0.0 {
+            break;
+        }
+        omega = t.dot(&r) / t_t;
+        x.scaled_add(omega, &z);
+        r.scaled_add(-omega, &t);
+
+        residual_norm = r.dot(&r).sqrt() / b_norm;
+        if omega == 0.0 {
+            break;
+        }
+    }
+
+    CgResult { solution: x, iterations, converged: residual_norm <= tolerance, residual_norm }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // tridiagonal matrix with constant diagonals, symmetric if lower == upper
+    fn tridiagonal(n: usize, lower: f64, diagonal: f64, upper: f64) -> CsrMatrix {
+        let mut triplets = Vec::new();
+        for i in 0..n {
+            triplets.push((i, i, diagonal));
+            if i > 0 {
+                triplets.push((i, i - 1, lower));
+            }
+            if i + 1 < n {
+                triplets.push((i, i + 1, upper));
+            }
+        }
+        CsrMatrix::from_triplets(n, n, triplets)
+    }
+
+    fn max_difference(a: &Array1<f64>, b: &Array1<f64>) -> f64 {
+        (a - b).iter().fold(0.0_f64, |max, x| max.max(x.abs()))
+    }
+
+    #[test]
+    fn triplets_are_sorted_and_summed() {
+        let a = CsrMatrix::from_triplets(3, 4, vec![
+            (2, 3, 1.0), (0, 1, 2.0), (2, 0, 4.0), (0, 1, 3.0), (0, 0, -1.0), (2, 3, 0.5),
+        ]);
+        assert_eq!((a.nrows(), a.ncols(), a.nnz()), (3, 4, 4));
+        assert_eq!(a.indptr, vec![0, 2, 2, 4]);
+        assert_eq!(a.indices, vec![0, 1, 0, 3]);
+        assert_eq!(a.values, vec![-1.0, 5.0, 4.0, 1.5]);
+        assert_eq!(a.dot(&array![1.0, 2.0, 3.0, 4.0]), array![9.0, 0.0, 10.0]);
+        assert_eq!(a.diagonal(), array![-1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn conjugate_gradient_solves_spd_systems() {
+        // 1D Laplacian
+        let n = 30;
+        let a = tridiagonal(n, -1.0, 2.0, -1.0);
+        let x_true = Array1::from_shape_fn(n, |i| (i as f64 * 0.3).sin());
+        let b = a.dot(&x_true);
+        let inv_diagonal = a.diagonal().mapv(|d| 1.0 / d);
+
+        let result = conjugate_gradient(|x| a.dot(x), &b, Array1::zeros(n), &inv_diagonal, 1e-12, 2 * n);
+        assert!(result.converged && result.residual_norm <= 1e-12);
+        assert!(result.iterations <= n);
+        assert!(max_difference(&result.solution, &x_true) < 1e-9);
+
+        let result = conjugate_gradient(|x| a.dot(x), &b, Array1::zeros(n), &inv_diagonal, 1e-12, 3);
+        assert!(!result.converged);
+        assert_eq!(result.iterations, 3);
+
+        let result = conjugate_gradient(|x| a.dot(x), &Array1::zeros(n), Array1::ones(n), &inv_diagonal, 1e-12, 3);
+        assert!(result.converged);
+        assert_eq!(result.solution, Array1::<f64>::zeros(n));
+    }
+
+    #[test]
+    fn conjugate_gradient_stops_on_indefinite_systems() {
+        let a = CsrMatrix::from_triplets(2, 2, vec![(0, 0, 1.0), (1, 1, -1.0)]);
+        let result = conjugate_gradient(|x| a.dot(x), &array![1.0, 1.0], Array1::zeros(2), &array![1.0, -1.0], 1e-12, 10);
+        assert!(!result.converged);
+        assert_eq!(result.iterations, 0);
+    }
+
+    #[test]
+    fn bicgstab_solves_nonsymmetric_systems() {
+        // 1D convection-diffusion
+        let n = 30;
+        let a = tridiagonal(n, -1.5, 2.0, -0.5);
+        let x_true = Array1::from_shape_fn(n, |i| 1.0 + (i as f64 * 0.2).cos());
+        let b = a.dot(&x_true);
+        let inv_diagonal = a.diagonal().mapv(|d| 1.0 / d);
+
+        let result = bicgstab(|x| a.dot(x), &b, Array1::zeros(n), &inv_diagonal, 1e-12, 4 * n);
+        assert!(result.converged && result.residual_norm <= 1e-12);
+        assert!(max_difference(&result.solution, &x_true) < 1e-9);
+
+        let result = bicgstab(|x| a.dot(x), &b, Array1::zeros(n), &inv_diagonal, 1e-12, 2);
+        assert!(!result.converged);
+        assert_eq!(result.iterations, 2);
+    }
+
+    #[test]
+    fn bicgstab_stops_on_breakdown() {
+        // a rotation by 90 degrees maps the initial residual orthogonal to itself
+        let a = CsrMatrix::from_triplets(2, 2, vec![(0, 1, 1.0), (1, 0, -1.0)]);
+        let result = bicgstab(|x| a.dot(x), &array![1.0, 0.0], Array1::zeros(2), &array![1.0, 1.0], 1e-12, 10);
+        assert!(!result.converged);
+        assert_eq!(result.iterations, 0);
+        assert!(result.solution.iter().all(|x| x.is_finite()));
+    }
+}
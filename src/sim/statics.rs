@@ -0,0 +1,88 @@
+use ndarray::prelude::*;
+use crate::sim::integrator::DynamicSystem;
+
+pub struct StaticSolution {
+    pub vertices: Array2<f64>, // equilibrium vertex positions (N, 2)
+    pub residual_norm: f64,    // norm of the free residual forces relative to the reference force norm
+    pub iterations: usize,
+    pub converged: bool,
+}
+
+// Finds the static equilibrium of a system with dynamic relaxation. The system is advanced with
+// symplectic Euler steps from rest, and kinetic damping zeroes all velocities whenever the kinetic
+// energy drops, i.e. just after the system passed its (approximate) energy minimum along the current
+// trajectory. The loads are held at their value at time t. The residual is normalized by the largest
+// of the norms of the given external forces on the free degrees of freedom, the internal forces and
+// the reactions of the constrained degrees of freedom. Without external loads, e.g. with only
+// prescribed displacements, the internal forces and reactions still give the scale of the problem.
+pub fn dynamic_relaxation<S: DynamicSystem>(system: &S,
+    positions: &Array2<f64>,
+    t: f64,
+    dt: f64,
+    external_forces: &Array2<f64>,
+    tolerance: f64,
+    max_iters: usize) -> StaticSolution {
+    let mask = system.free_mask();
+    let masses = system.nodal_masses().view().insert_axis(Axis(1)).to_owned();
+    // nodes of no triangle have no mass and no forces, so they are left in place
+    let inv_masses = masses.mapv(|m| if m > 0.0 { 1.0 / m } else { 0.0 });
+    let norm = |forces: &Array2<f64>| forces.iter().map(|f| f * f).sum::<f64>().sqrt();
+    let external_force_norm = norm(&(external_forces * mask));
+
+    let mut vertices = positions.clone();
+    let mut velocities = Array2::<f64>::zeros(positions.raw_dim());
+    let zero_velocities = Array2::<f64>::zeros(positions.raw_dim());
+    let mut kinetic_energy = 0.0;
+    let mut residual_norm = f64::INFINITY;
+    let mut iterations = 0;
+
+    while iterations < max_iters {
+        // static forces only, so velocity dependent damping forces are left out
+        let forces = system.total_forces(&vertices, &zero_velocities, t);
+        let reference_force_norm = external_force_norm
+            .max(norm(&(&forces - external_forces)))
+            .max(norm(&(&forces * &(1.0 - mask))))
+            .max(f64::MIN_POSITIVE);
+        let residual = forces * mask;
+        residual_norm = norm(&residual) / reference_force_norm;
+        if residual_norm <= tolerance {
+            break;
+        }
+        iterations += 1;
+
+        let new_velocities = &velocities + &(dt * (residual * &inv_masses));
+        let new_kinetic_energy = 0.5 * (&masses * &new_velocities * &new_velocities).sum();
+
+        if new_kinetic_energy < kinetic_energy {
+            // passed a kinetic energy peak, restart from rest at the current positions
+            velocities.fill(0.0);
+            kinetic_energy = 0.0;
+        } else {
+            vertices = vertices + &(dt * &new_velocities);
+            velocities = new_velocities;
+            kinetic_energy = new_kinetic_energy;
+        }
+    }
+
+    StaticSolution { vertices, residual_norm, iterations, converged: residual_norm <= tolerance }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::concatenate;
+    use crate::mesh::TriangleMesh;
+    use crate::sim::cauchy_fvm::CauchyFVM;
+
+    #[test]
+    fn nodes_of_no_triangle_stay_in_place() {
+        let beam = TriangleMesh::new_beam(6.0, 2.0, (12, 4));
+        let vertices = concatenate![Axis(0), beam.vertices, array![[10.0, 10.0]]];
+        let mesh = TriangleMesh::from_triangles(vertices, beam.triangles.clone(), beam.edge_tags.clone()).unwrap();
+        let isolated = mesh.vertices.nrows() - 1;
+        let mut sim = CauchyFVM::new(&mesh, "rubber", 1e-4).unwrap();
+        let solution = sim.solve_static(1e-6, 200_000).unwrap();
+        assert!(solution.converged);
+        assert_eq!(solution.vertices.row(isolated), array![10.0, 10.0]);
+    }
+}
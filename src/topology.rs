@@ -0,0 +1,234 @@
+use ndarray::prelude::*;
+use std::collections::HashMap;
+
+// Connectivity of a triangle mesh. It only depends on the triangles, so it stays valid while the
+// vertices move. Triangles are counter-clockwise, and edge i of triangle t goes from t[i] to t[i+1].
+#[derive(Clone, Debug)]
+pub struct MeshTopology {
+    pub edges: Vec<[usize; 2]>,                   // unique edges (a, b) with a < b
+    pub edge_triangles: Vec<[Option<usize>; 2]>,  // triangles on both sides of each edge
+    pub triangle_edges: Vec<[usize; 3]>,          // edge index of edge i of each triangle
+    pub triangle_neighbors: Vec<[Option<usize>; 3]>, // triangle across edge i of each triangle
+    pub boundary_edges: Vec<[usize; 2]>,          // edges of a single triangle, oriented like it
+    pub boundary_loops: Vec<Vec<usize>>,          // closed vertex loops, counter-clockwise outside and clockwise around holes
+    pub is_boundary_vertex: Vec<bool>,            // (N)
+}
+
+impl MeshTopology {
+    pub fn new(num_vertices: usize, triangles: &Array2<usize>) -> MeshTopology {
+        let mut edges = Vec::<[usize; 2]>::new();
+        let mut edge_triangles = Vec::<[Option<usize>; 2]>::new();
+        let mut triangle_edges = Vec::<[usize; 3]>::with_capacity(triangles.nrows());
+        let mut edge_index = HashMap::<[usize; 2], usize>::new();
+
+        for (triangle_idx, tri) in triangles.outer_iter().enumerate() {
+            let mut tri_edges = [0; 3];
+            for i in 0..3 {
+                let (a, b) = (tri[i], tri[(i + 1) % 3]);
+                let key = [a.min(b), a.max(b)];
+                let edge_idx = *edge_index.entry(key).or_insert_with(|| {
+                    edges.push(key);
+                    edge_triangles.push([None, None]);
+                    edges.len() - 1
+                });
+                // an edge of more than two triangles is non-manifold, only its first two are kept
+                let sides = &mut edge_triangles[edge_idx];
+                if sides[0].is_none() {
+                    sides[0] = Some(triangle_idx);
+                } else if sides[1].is_none() {
+                    sides[1] = Some(triangle_idx);
+                }
+                tri_edges[i] = edge_idx;
+            }
+            triangle_edges.push(tri_edges);
+        }
+
+        let triangle_neighbors = triangle_edges.iter()
+            .enumerate()
+            .map(|(triangle_idx, tri_edges)| tri_edges.map(|edge_idx| {
+                let [t0, t1] = edge_triangles[edge_idx];
+                if t0 == Some(triangle_idx) { t1 } else { t0 }
+            }))
+            .collect();
+
+        let boundary_edges: Vec<[usize; 2]> = triangles.outer_iter()
+            .zip(triangle_edges.iter())
+            .flat_map(|(tri, tri_edges)| (0..3)
+                .filter(|&i| edge_triangles[tri_edges[i]][1].is_none())
+                .map(move |i| [tri[i], tri[(i + 1) % 3]])
+                .collect::<Vec<_>>())
+            .collect();
+
+        let mut is_boundary_vertex = vec![false; num_vertices];
+        for edge in boundary_edges.iter() {
+            is_boundary_vertex[edge[0]] = true;
+            is_boundary_vertex[edge[1]] = true;
+        }
+
+        let boundary_loops = Self::compute_boundary_loops(&boundary_edges);
+
+        MeshTopology {
+            edges,
+            edge_triangles,
+            triangle_edges,
+            triangle_neighbors,
+            boundary_edges,
+            boundary_loops,
+            is_boundary_vertex,
+        }
+    }
+
+    fn compute_boundary_loops(boundary_edges: &[[usize; 2]]) -> Vec<Vec<usize>> {
+        // follows the oriented boundary edges from vertex to vertex until each loop closes. A vertex
+        // where the boundary touches itself has several outgoing edges, which are used in turn.
+        let mut outgoing = HashMap::<usize, Vec<usize>>::new();
+        for &[a, b] in boundary_edges.iter() {
+            outgoing.entry(a).or_default().push(b);
+        }
+
+        let mut loops = Vec::<Vec<usize>>::new();
+        for &[start, _] in boundary_edges.iter() {
+            let mut boundary_loop = Vec::<usize>::new();
+            let mut vertex = start;
+            while let Some(next) = outgoing.get_mut(&vertex).and_then(|targets| targets.pop()) {
+                boundary_loop.push(vertex);
+                vertex = next;
+                if vertex == start {
+                    break;
+                }
+            }
+            if !boundary_loop.is_empty() {
+                loops.push(boundary_loop);
+            }
+        }
+        loops
+    }
+
+    pub fn num_vertices(&self) -> usize {
+        self.is_boundary_vertex.len()
+    }
+
+    pub fn num_triangles(&self) -> usize {
+        self.triangle_edges.len()
+    }
+
+    // V - E + F, i.e. the number of connected components minus the number of holes. Vertices without
+    // any triangle are not part of the surface and left out.
+    pub fn euler_characteristic(&self) -> i64 {
+        let mut is_used = vec![false; self.num_vertices()];
+        for edge in self.edges.iter() {
+            is_used[edge[0]] = true;
+            is_used[edge[1]] = true;
+        }
+        let num_used = is_used.iter().filter(|&&used| used).count();
+        num_used as i64 - self.edges.len() as i64 + self.num_triangles() as i64
+    }
+
+    // number of edge connected pieces of the mesh
+    pub fn num_components(&self) -> usize {
+        let mut component = vec![usize::MAX; self.num_triangles()];
+        let mut num_components = 0;
+        for seed in 0..self.num_triangles() {
+            if component[seed] != usize::MAX {
+                continue;
+            }
+            let mut stack = vec![seed];
+            component[seed] = num_components;
+            while let Some(triangle_idx) = stack.pop() {
+                for neighbor in self.triangle_neighbors[triangle_idx].iter().flatten() {
+                    if component[*neighbor] == usize::MAX {
+                        component[*neighbor] = num_components;
+                        stack.push(*neighbor);
+                    }
+                }
+            }
+            num_components += 1;
+        }
+        num_components
+    }
+
+    // number of holes
+    pub fn num_holes(&self) -> usize {
+        (self.num_components() as i64 - self.euler_characteristic()).max(0) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // counter-clockwise triangles of a grid of n x n unit cells, leaving out the given cells
+    fn grid(n: usize, skipped_cells: &[(usize, usize)]) -> (Array2<f64>, Array2<usize>) {
+        let vertices = Array2::from_shape_fn(((n + 1) * (n + 1), 2),
+            |(v, axis)| if axis == 0 { (v % (n + 1)) as f64 } else { (v / (n + 1)) as f64 });
+        let mut triangles = Vec::<usize>::new();
+        for j in 0..n {
+            for i in 0..n {
+                if skipped_cells.contains(&(i, j)) {
+                    continue;
+                }
+                let v = j * (n + 1) + i;
+                triangles.extend([v, v + 1, v + n + 2, v, v + n + 2, v + n + 1]);
+            }
+        }
+        let triangles = Array2::from_shape_vec((triangles.len() / 3, 3), triangles).unwrap();
+        (vertices, triangles)
+    }
+
+    fn signed_area(vertices: &Array2<f64>, boundary_loop: &[usize]) -> f64 {
+        (0..boundary_loop.len())
+            .map(|i| {
+                let (a, b) = (boundary_loop[i], boundary_loop[(i + 1) % boundary_loop.len()]);
+                0.5 * (vertices[[a, 0]] * vertices[[b, 1]] - vertices[[b, 0]] * vertices[[a, 1]])
+            })
+            .sum()
+    }
+
+    #[test]
+    fn single_triangle() {
+        let topology = MeshTopology::new(3, &array![[0, 1, 2]]);
+        assert_eq!(topology.edges.len(), 3);
+        assert_eq!(topology.euler_characteristic(), 1);
+        assert_eq!((topology.num_components(), topology.num_holes()), (1, 0));
+        assert_eq!(topology.triangle_neighbors, vec![[None, None, None]]);
+        assert_eq!(topology.boundary_edges.len(), 3);
+        assert_eq!(topology.boundary_loops.len(), 1);
+        let mut boundary_loop = topology.boundary_loops[0].clone();
+        let start = boundary_loop.iter().position(|&v| v == 0).unwrap();
+        boundary_loop.rotate_left(start);
+        assert_eq!(boundary_loop, vec![0, 1, 2]);
+        assert!(topology.is_boundary_vertex.iter().all(|&boundary| boundary));
+    }
+
+    #[test]
+    fn square_with_hole() {
+        let (vertices, triangles) = grid(3, &[(1, 1)]);
+        let topology = MeshTopology::new(vertices.nrows(), &triangles);
+        assert_eq!(topology.euler_characteristic(), 0);
+        assert_eq!((topology.num_components(), topology.num_holes()), (1, 1));
+        assert_eq!(topology.boundary_edges.len(), 16);
+        assert!(topology.is_boundary_vertex.iter().all(|&boundary| boundary));
+
+        // the outer loop runs counter-clockwise around the square and the hole loop clockwise
+        let mut loops: Vec<(usize, f64)> = topology.boundary_loops.iter()
+            .map(|boundary_loop| (boundary_loop.len(), signed_area(&vertices, boundary_loop)))
+            .collect();
+        loops.sort_by_key(|&(len, _)| len);
+        assert_eq!(loops, vec![(4, -1.0), (12, 9.0)]);
+    }
+
+    #[test]
+    fn disjoint_components() {
+        // two triangles and a vertex of none, which is not part of the surface
+        let topology = MeshTopology::new(7, &array![[0, 1, 2], [3, 4, 5]]);
+        assert_eq!(topology.euler_characteristic(), 2);
+        assert_eq!((topology.num_components(), topology.num_holes()), (2, 0));
+        assert_eq!(topology.boundary_loops.len(), 2);
+        assert!(!topology.is_boundary_vertex[6]);
+
+        let (vertices, triangles) = grid(3, &[(1, 0), (1, 1), (1, 2)]);
+        let topology = MeshTopology::new(vertices.nrows(), &triangles);
+        assert_eq!((topology.num_components(), topology.num_holes()), (2, 0));
+        assert_eq!(topology.boundary_loops.iter().map(Vec::len).collect::<Vec<usize>>(), vec![8, 8]);
+    }
+}
@@ -0,0 +1,675 @@
+// Bevy viewer that runs a simulator and draws its mesh as a wireframe or filled, colored by a scalar
+// field with a legend. F cycles the field, C the colormap and W toggles the wireframe.
+//use bevy::prelude::*;
+
+use bevy::{
+    asset::weak_handle,
+    color::palettes::basic::YELLOW,
+    core_pipeline::core_2d::{Transparent2d, CORE_2D_DEPTH_FORMAT},
+    math::{ops, FloatOrd},
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        mesh::{Indices, MeshVertexAttribute, RenderMesh, VertexAttributeValues},
+        render_asset::{RenderAssetUsages, RenderAssets},
+        render_phase::{
+            AddRenderCommand, DrawFunctions, PhaseItemExtraIndex, SetItemPipeline,
+            ViewSortedRenderPhases,
+        },
+        render_resource::{
+            BlendState, ColorTargetState, ColorWrites, CompareFunction, DepthBiasState,
+            DepthStencilState, Face, FragmentState, FrontFace, MultisampleState, PipelineCache,
+            PolygonMode, PrimitiveState, PrimitiveTopology, RenderPipelineDescriptor,
+            SpecializedRenderPipeline, SpecializedRenderPipelines, StencilFaceState, StencilState,
+            TextureFormat, VertexBufferLayout, VertexFormat, VertexState, VertexStepMode,
+        },
+        sync_component::SyncComponentPlugin,
+        sync_world::{MainEntityHashMap, RenderEntity},
+        view::{ExtractedView, RenderVisibleEntities, ViewTarget},
+        Extract, Render, RenderApp, RenderSet,
+    },
+    sprite::{
+        extract_mesh2d, DrawMesh2d, Material2dBindGroupId, Mesh2dPipeline, Mesh2dPipelineKey,
+        Mesh2dTransforms, MeshFlags, RenderMesh2dInstance, SetMesh2dBindGroup,
+        SetMesh2dViewBindGroup,
+    },
+    window::WindowResolution,
+};
+use crate::colormap::Colormap;
+use crate::mesh::TriangleMesh;
+use crate::sim::fields::ScalarField;
+use crate::sim::Simulator;
+use crate::scenario::{EventSchedule, ScenarioError};
+use std::f32::consts::PI;
+
+// custom vertex attribute of the packed RGBA vertex colors, see the shader
+const ATTRIBUTE_VERTEX_COLOR: MeshVertexAttribute = MeshVertexAttribute::new("Vertex_Color", 1, VertexFormat::Uint32);
+
+// number of color swatches of the legend bar
+const LEGEND_STEPS: usize = 32;
+
+// What the viewer shows, changed with the keyboard while running
+#[derive(Resource, Clone, Copy, Debug, ExtractResource)]
+pub struct ViewSettings {
+    pub field: ScalarField,
+    pub colormap: Colormap,
+    pub filled: bool, // filled triangles instead of a wireframe
+}
+
+impl Default for ViewSettings {
+    fn default() -> ViewSettings {
+        ViewSettings { field: ScalarField::VonMises, colormap: Colormap::Viridis, filled: false }
+    }
+}
+
+pub fn view_bevy(sim: Box<dyn Simulator>, schedule: EventSchedule, settings: ViewSettings) -> () {
+    let tmesh = sim.mesh().clone();
+
+    App::new()
+        .add_plugins((DefaultPlugins
+                .set(WindowPlugin {
+                primary_window: Some(Window {
+                    resolution: WindowResolution::new(640.,480.).with_scale_factor_override(1.0),
+                    ..default() 
+                }),
+                ..default()
+            }),
+            ColoredMesh2dPlugin))
+        .insert_resource(TriangleMeshResource(tmesh))
+        .insert_resource(settings)
+        .init_resource::<FieldRange>()
+        .insert_resource(PendingSimulator(Some(MeshSimulator { sim, schedule, error: None })))
+        .insert_resource(Time::<Fixed>::from_hz(1200.0))
+        //.insert_resource(SimulationTimer(Timer::from_seconds(0.001, TimerMode::Repeating)))
+        .add_systems(Startup, beam)
+        .add_systems(Startup, create_simulator)
+        .add_systems(Startup, legend)
+        .add_systems(FixedUpdate, update_simulator)
+        .add_systems(Update, change_view_settings)
+        .add_systems(Update, (set_new_vertices_with_simulator, update_legend).chain())
+        .run(); 
+}
+
+fn beam(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    beam_mesh: Res<TriangleMeshResource>,
+) {
+    // create Bevy Mesh
+    let mut beam = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::all(), // RENDER_WORLD // maybe needs all() ?
+    );
+
+    // add vertex positions from TriangleMesh struct
+    let mut v_pos: Vec<[f32; 3]> = Vec::new();
+    for vertex in beam_mesh.0.vertices.rows() {
+        let x = vertex[[0]] as f32;
+        let y = vertex[[1]] as f32;
+        let z = 0.0;
+        v_pos.push([x,y,z]);
+    }
+    beam.insert_attribute(Mesh::ATTRIBUTE_POSITION, v_pos.clone());
+    
+    // vertex colors, set from the scalar field every frame
+    beam.insert_attribute(ATTRIBUTE_VERTEX_COLOR, vec![LinearRgba::WHITE.as_u32(); v_pos.len()]);
+
+    // triangle list
+    let mut indices: Vec<u32> = Vec::new();
+    for triangle in beam_mesh.0.triangles.rows() {
+        let i = triangle[[0]] as u32;
+        let j = triangle[[1]] as u32;
+        let k = triangle[[2]] as u32;
+        indices.extend_from_slice(&[i,j,k]);
+    }
+    beam.insert_indices(Indices::U32(indices));
+    
+    // spawn entities for star and camera
+    commands.spawn((
+            ColoredMesh2d,
+            Mesh2d(meshes.add(beam))
+    ));
+    //commands.spawn(Camera2d);
+    // the camera is centered on the mesh and shows twice its extent, leaving room for its motion
+    let vertices = &beam_mesh.0.vertices;
+    let bounds = |axis: usize| vertices.column(axis).fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    let ((min_x, max_x), (min_y, max_y)) = (bounds(0), bounds(1));
+    let scale = (2.0 * (max_x - min_x) / 640.0).max(2.0 * (max_y - min_y) / 480.0) as f32;
+    commands.spawn((
+        Camera2d::default(),
+        Projection::from(OrthographicProjection {
+            scale,
+            ..OrthographicProjection::default_2d()
+            },
+        ),
+        Transform::from_xyz((0.5 * (min_x + max_x)) as f32, (0.5 * (min_y + max_y)) as f32, 0.0),
+    ));
+}
+
+
+#[derive(Resource)]
+struct SimulationTimer(Timer);
+
+#[derive(Resource)]
+struct TriangleMeshResource(TriangleMesh);
+
+// simulator built from the scenario, moved into its entity on startup
+#[derive(Resource)]
+struct PendingSimulator(Option<MeshSimulator>);
+
+#[derive(Component)]
+pub struct MeshSimulator {
+    // wraps any simulator
+    sim: Box<dyn Simulator>,
+    // events of the scenario that are still to come
+    schedule: EventSchedule,
+    // set once an update failed, after which the simulation is frozen
+    error: Option<ScenarioError>,
+}
+
+fn create_simulator(mut commands: Commands, mut pending: ResMut<PendingSimulator>) {
+    if let Some(simulator) = pending.0.take() {
+        commands.spawn(simulator);
+    }
+}
+
+fn update_simulator(
+    //time: Res<Time>,
+    //mut timer: ResMut<SimulationTimer>,
+    mut query: Query<&mut MeshSimulator>) {
+    //if timer.0.tick(time.delta()).just_finished() {
+        for mut simulator in &mut query {
+            if simulator.error.is_some() {
+                continue;
+            }
+            let MeshSimulator { sim, schedule, .. } = &mut *simulator;
+            if let Err(e) = schedule.step(sim.as_mut()) {
+                eprintln!("{}", e);
+                simulator.error = Some(e);
+            }
+        }
+    //} 
+}
+
+// range of the shown field in the current frame, the ends of the legend
+#[derive(Resource, Default)]
+struct FieldRange {
+    min: f64,
+    max: f64,
+}
+
+fn set_new_vertices_with_simulator(query: Query<&MeshSimulator>,
+    shape: Single<&Mesh2d>,
+    settings: Res<ViewSettings>,
+    mut range: ResMut<FieldRange>,
+    mut meshes: ResMut<Assets<Mesh>>) {
+    let Ok(simulator) = query.single() else { return; };
+    let Some(mesh) = meshes.get_mut(*shape) else { return; };
+    if let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+    {
+        let sim_vertices = simulator.sim.positions();
+
+        for (idx, position) in positions.iter_mut().enumerate() {
+            let v = sim_vertices.row(idx);
+            position[0] = v[0] as f32;
+            position[1] = v[1] as f32;
+        }
+    }
+
+    // colors of the field scaled to its range in this frame
+    let values = settings.field.nodal_values(simulator.sim.as_ref());
+    let finite = values.iter().copied().filter(|value| value.is_finite());
+    range.min = finite.clone().fold(f64::INFINITY, f64::min);
+    range.max = finite.fold(f64::NEG_INFINITY, f64::max);
+    if let Some(VertexAttributeValues::Uint32(colors)) = mesh.attribute_mut(ATTRIBUTE_VERTEX_COLOR) {
+        for (color, &value) in colors.iter_mut().zip(values.iter()) {
+            let [r, g, b] = settings.colormap.color_in_range(value, range.min, range.max);
+            *color = LinearRgba::from(Color::srgb(r, g, b)).as_u32();
+        }
+    }
+}
+
+fn change_view_settings(keys: Res<ButtonInput<KeyCode>>, mut settings: ResMut<ViewSettings>) {
+    if keys.just_pressed(KeyCode::KeyF) {
+        settings.field = settings.field.next();
+    }
+    if keys.just_pressed(KeyCode::KeyC) {
+        settings.colormap = settings.colormap.next();
+    }
+    if keys.just_pressed(KeyCode::KeyW) {
+        settings.filled = !settings.filled;
+    }
+}
+
+// texts of the legend, updated every frame
+#[derive(Component)]
+enum LegendLabel {
+    Title,
+    Max,
+    Min,
+}
+
+// color swatch of the legend bar, the first is the top one showing the maximum
+#[derive(Component)]
+struct LegendSwatch(usize);
+
+fn legend(mut commands: Commands) {
+    let font = TextFont { font_size: 14.0, ..default() };
+    commands
+        .spawn(Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            ..default()
+        })
+        .with_children(|legend| {
+            legend.spawn((LegendLabel::Title, Text::new(""), font.clone()));
+            legend.spawn((LegendLabel::Max, Text::new(""), font.clone()));
+            legend
+                .spawn(Node { flex_direction: FlexDirection::Column, ..default() })
+                .with_children(|bar| {
+                    for step in 0..LEGEND_STEPS {
+                        bar.spawn((
+                            LegendSwatch(step),
+                            Node { width: Val::Px(20.0), height: Val::Px(6.0), ..default() },
+                            BackgroundColor(Color::WHITE),
+                        ));
+                    }
+                });
+            legend.spawn((LegendLabel::Min, Text::new(""), font));
+        });
+}
+
+fn update_legend(settings: Res<ViewSettings>,
+    range: Res<FieldRange>,
+    mut labels: Query<(&LegendLabel, &mut Text)>,
+    mut swatches: Query<(&LegendSwatch, &mut BackgroundColor)>) {
+    for (label, mut text) in &mut labels {
+        text.0 = match label {
+            LegendLabel::Title => settings.field.label().to_string(),
+            LegendLabel::Max => format!("{:.3e}", range.max),
+            LegendLabel::Min => format!("{:.3e}", range.min),
+        };
+    }
+    if settings.is_changed() {
+        for (swatch, mut background) in &mut swatches {
+            let t = 1.0 - swatch.0 as f64 / (LEGEND_STEPS - 1) as f64;
+            let [r, g, b] = settings.colormap.color(t);
+            background.0 = Color::srgb(r, g, b);
+        }
+    }
+}
+
+/// A marker component for colored 2d meshes
+#[derive(Component, Default)]
+pub struct ColoredMesh2d;
+
+/// Custom pipeline for 2d meshes with vertex colors
+#[derive(Resource)]
+pub struct ColoredMesh2dPipeline {
+    // this struct wraps standard Mesh2dPipeline
+    mesh2d_pipeline: Mesh2dPipeline,
+}
+
+impl FromWorld for ColoredMesh2dPipeline {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            mesh2d_pipeline: Mesh2dPipeline::from_world(world),
+        }
+    }
+}
+
+// Implement `SpecializedPipeline` to customize the default rendering from `Mesh2dPipeline`
+/// Key of the specialized pipelines, the standard 2d mesh key and whether triangles are filled
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ColoredMesh2dPipelineKey {
+    mesh_key: Mesh2dPipelineKey,
+    filled: bool,
+}
+
+impl SpecializedRenderPipeline for ColoredMesh2dPipeline {
+    type Key = ColoredMesh2dPipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        let ColoredMesh2dPipelineKey { mesh_key: key, filled } = key;
+        // Customize how to store the meshes' vertex attributes in the vertex buffer
+        // Our meshes only have position and color
+        let formats = vec![
+            VertexFormat::Float32x3, // position
+            VertexFormat::Uint32, // color
+        ];
+
+       let vertex_layout = VertexBufferLayout::from_vertex_formats(VertexStepMode::Vertex, formats);
+
+       let format = match key.contains(Mesh2dPipelineKey::HDR) {
+            true => ViewTarget::TEXTURE_FORMAT_HDR,
+            false => TextureFormat::bevy_default(),
+       };
+    
+       RenderPipelineDescriptor {
+           vertex: VertexState {
+           // use custom shader
+           shader: COLORED_MESH2D_SHADER_HANDLE,
+           entry_point: "vertex".into(),
+           shader_defs: vec![],
+           // use our custom vertex buffer
+           buffers: vec![vertex_layout],
+           },
+           fragment: Some(FragmentState {
+                // use custom shader
+                shader: COLORED_MESH2D_SHADER_HANDLE,
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+           }),
+           
+           // use 2 standard uniforms for 2d meshes
+           layout: vec![
+                // bind group 0 is the view uniform
+                self.mesh2d_pipeline.view_layout.clone(),
+                // bind group 1 is the mesh uniform
+                self.mesh2d_pipeline.mesh_layout.clone(),
+           ],
+           push_constant_ranges: vec![],
+           
+           primitive: PrimitiveState {
+                front_face: FrontFace::Ccw,
+                cull_mode: Some(Face::Back),
+                unclipped_depth: false,
+                polygon_mode: if filled { PolygonMode::Fill } else { PolygonMode::Line },
+                conservative: false,
+                topology: key.primitive_topology(),
+                strip_index_format: None,
+           },
+
+           depth_stencil: Some(DepthStencilState {
+                format: CORE_2D_DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::GreaterEqual,
+                stencil: StencilState {
+                    front: StencilFaceState::IGNORE,
+                    back: StencilFaceState::IGNORE,
+                    read_mask: 0,
+                    write_mask: 0,
+                },
+                bias: DepthBiasState {
+                    constant: 0,
+                    slope_scale: 0.0,
+                    clamp: 0.0,
+                },
+           }),
+
+           multisample: MultisampleState {
+                count: key.msaa_samples(),
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+           },
+
+           label: Some("colored_mesh2d_pipeline".into()),
+
+           zero_initialize_workgroup_memory: false,
+       }
+    }
+}
+
+/// Our custom pipeline needs its own instance storage
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct RenderColoredMesh2dInstances(MainEntityHashMap<RenderMesh2dInstance>);
+
+// This specifies how to render a colored 2d mesh
+type DrawColoredMesh2d = (
+    SetItemPipeline,
+    SetMesh2dViewBindGroup<0>,
+    SetMesh2dBindGroup<1>,
+    DrawMesh2d,
+);
+
+/// Plugin that renders [`ColoredMesh2d`]s
+pub struct ColoredMesh2dPlugin;
+
+impl Plugin for ColoredMesh2dPlugin {
+    fn build(&self, app: &mut App) {
+        // load our custom shader
+        let mut shaders = app.world_mut().resource_mut::<Assets<Shader>>();
+        shaders.insert(
+            &COLORED_MESH2D_SHADER_HANDLE,
+            Shader::from_wgsl(COLORED_MESH2D_SHADER, file!()),
+        );
+        app.add_plugins(SyncComponentPlugin::<ColoredMesh2d>::default());
+        // the fill mode of the view settings selects the pipeline
+        app.add_plugins(ExtractResourcePlugin::<ViewSettings>::default());
+
+        // Register our custom draw functions and add our render systems
+        app.get_sub_app_mut(RenderApp)
+            .unwrap()
+            .add_render_command::<Transparent2d, DrawColoredMesh2d>()
+            .init_resource::<SpecializedRenderPipelines<ColoredMesh2dPipeline>>()
+            .init_resource::<RenderColoredMesh2dInstances>()
+            .add_systems(
+                ExtractSchedule,
+                extract_colored_mesh2d.after(extract_mesh2d),
+            )
+            .add_systems(
+                Render,
+                queue_colored_mesh2d.in_set(RenderSet::QueueMeshes),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        // register our custom pipeline
+        app.get_sub_app_mut(RenderApp)
+            .unwrap()
+            .init_resource::<ColoredMesh2dPipeline>();
+    }
+}
+
+/// Extract the [`ColoredMesh2d`] marker component into the render app
+pub fn extract_colored_mesh2d(
+    mut commands: Commands,
+    mut previous_len: Local<usize>,
+    // When extracting, you must use `Extract` to mark the `SystemParam`s
+    // which should be taken from the main world.
+    query: Extract<
+        Query<
+            (
+                Entity,
+                RenderEntity,
+                &ViewVisibility,
+                &GlobalTransform,
+                &Mesh2d,
+            ),
+            With<ColoredMesh2d>
+        >,
+    >,
+    mut render_mesh_instances: ResMut<RenderColoredMesh2dInstances>,
+    ) {
+    let mut values = Vec::with_capacity(*previous_len);
+    for (entity, render_entity, view_visibility, transform, handle) in &query {
+        if !view_visibility.get() {
+            continue;
+        }
+
+        let transforms = Mesh2dTransforms {
+            world_from_local: (&transform.affine()).into(),
+            flags: MeshFlags::empty().bits(),
+        };
+
+        values.push((render_entity, ColoredMesh2d));
+        render_mesh_instances.insert(
+            entity.into(),
+            RenderMesh2dInstance {
+                mesh_asset_id: handle.0.id(),
+                transforms,
+                material_bind_group_id: Material2dBindGroupId::default(),
+                automatic_batching: false,
+                tag: 0,
+            },
+        );
+    }
+    *previous_len = values.len();
+    commands.try_insert_batch(values);
+}
+
+/// Queue the 2d meshes marked with [`ColoredMesh2d`] using our custom pipeline and draw function
+pub fn queue_colored_mesh2d(
+    transparent_draw_functions: Res<DrawFunctions<Transparent2d>>,
+    colored_mesh2d_pipeline: Res<ColoredMesh2dPipeline>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<ColoredMesh2dPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    render_meshes: Res<RenderAssets<RenderMesh>>,
+    render_mesh_instances: Res<RenderColoredMesh2dInstances>,
+    mut transparent_render_phases: ResMut<ViewSortedRenderPhases<Transparent2d>>,
+    views: Query<(&RenderVisibleEntities, &ExtractedView, &Msaa)>,
+    settings: Option<Res<ViewSettings>>,
+) {
+    if render_mesh_instances.is_empty() {
+        return;
+    }
+    // Iterate each view (a camera is a view)
+    for (visible_entities, view, msaa) in &views {
+        let Some(transparent_phase) = transparent_render_phases.get_mut(&view.retained_view_entity)
+        else {
+            continue;
+        };
+
+        let draw_colored_mesh2d = transparent_draw_functions.read().id::<DrawColoredMesh2d>();
+
+        let mesh_key = Mesh2dPipelineKey::from_msaa_samples(msaa.samples())
+            | Mesh2dPipelineKey::from_hdr(view.hdr);
+
+        // Queue all entities visible to that view
+        for (render_entity, visible_entity) in visible_entities.iter::<Mesh2d>() {
+            if let Some(mesh_instance) = render_mesh_instances.get(visible_entity) {
+                let mesh2d_handle = mesh_instance.mesh_asset_id;
+                let mesh2d_transforms = &mesh_instance.transforms;
+                // Get our specialized pipeline
+                let mut mesh2d_key = mesh_key;
+                let Some(mesh) = render_meshes.get(mesh2d_handle) else {
+                    continue;
+                };
+                mesh2d_key |= Mesh2dPipelineKey::from_primitive_topology(mesh.primitive_topology());
+
+                let key = ColoredMesh2dPipelineKey {
+                    mesh_key: mesh2d_key,
+                    filled: settings.as_ref().is_some_and(|settings| settings.filled),
+                };
+                let pipeline_id =
+                    pipelines.specialize(&pipeline_cache, &colored_mesh2d_pipeline, key);
+
+                let mesh_z = mesh2d_transforms.world_from_local.translation.z;
+                transparent_phase.add(Transparent2d {
+                    entity: (*render_entity, *visible_entity),
+                    draw_function: draw_colored_mesh2d,
+                    pipeline: pipeline_id,
+                    // The 2d render items are sorted according to their z value before rendering,
+                    // in order to get correct transparency
+                    sort_key: FloatOrd(mesh_z),
+                    // This material is not batched
+                    batch_range: 0..1,
+                    extra_index: PhaseItemExtraIndex::None,
+                    extracted_index: usize::MAX,
+                    indexed: mesh.indexed(),
+                });
+            }
+        }
+    }
+}
+
+// The custom shader can be inline like here, included from another file at build time
+// using `include_str!()`, or loaded like any other asset with `asset_server.load()`.
+const COLORED_MESH2D_SHADER: &str = r"
+// Import the standard 2d mesh uniforms and set their bind groups
+#import bevy_sprite::mesh2d_functions
+
+// The structure of the vertex buffer is as specified in `specialize()`
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) color: u32,
+};
+
+struct VertexOutput {
+    // The vertex shader must set the on-screen position of the vertex
+    @builtin(position) clip_position: vec4<f32>,
+    // We pass the vertex color to the fragment shader in location 0
+    @location(0) color: vec4<f32>,
+};
+
+/// Entry point for the vertex shader
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    // Project the world position of the mesh into screen position
+    let model = mesh2d_functions::get_world_from_local(vertex.instance_index);
+    out.clip_position = mesh2d_functions::mesh2d_position_local_to_clip(model, vec4<f32>(vertex.position, 1.0));
+    // Unpack the `u32` from the vertex buffer into the `vec4<f32>` used by the fragment shader
+    out.color = vec4<f32>((vec4<u32>(vertex.color) >> vec4<u32>(0u, 8u, 16u, 24u)) & vec4<u32>(255u)) / 255.0;
+    return out;
+}
+
+// The input of the fragment shader must correspond to the output of the vertex shader for all `location`s
+struct FragmentInput {
+    // The color is interpolated between vertices by default
+    @location(0) color: vec4<f32>,
+};
+
+/// Entry point for the fragment shader
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    return in.color;
+}
+";
+
+/// Handle to the custom shader with a unique random ID
+pub const COLORED_MESH2D_SHADER_HANDLE: Handle<Shader> =
+    weak_handle!("f48b148f-7373-4638-9900-392b3b3ccc66");
+
+fn star(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    let mut star = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD
+    );
+
+    let mut v_pos = vec![[0.0, 0.0, 0.0]];
+    for i in 0..10 {
+        let a = i as f32 * PI / 5.0;
+        let r = (1 - i % 2) as f32 * 100.0 + 100.0;
+        v_pos.push([r * ops::sin(a), r * ops::cos(a), 0.0])
+    }
+
+    // Set the position attribute
+    star.insert_attribute(Mesh::ATTRIBUTE_POSITION, v_pos);
+
+    // And a RGB color attribute as well. A built-in `Mesh::ATTRIBUTE_COLOR` exists, but we
+    // use a custom vertex attribute here for demonstration purposes.
+    let mut v_color: Vec<u32> = vec![LinearRgba::BLACK.as_u32()];
+    v_color.extend_from_slice(&[LinearRgba::from(YELLOW).as_u32(); 10]);
+
+    star.insert_attribute(
+        MeshVertexAttribute::new("Vertex_Color", 1, VertexFormat::Uint32),
+        v_color
+    );
+    
+    // triangle list
+    let mut indices = vec![0, 1, 10];
+    for i in 2..=10 {
+        indices.extend_from_slice(&[0, i, i-1]);
+    }
+    star.insert_indices(Indices::U32(indices));
+
+    // spawn entities for star and camera
+    commands.spawn((
+            ColoredMesh2d,
+            Mesh2d(meshes.add(star))
+    ));
+    
+    commands.spawn(Camera2d);
+}
+
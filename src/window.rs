@@ -4,13 +4,14 @@ use gtk4 as gtk;
 use gtk::prelude::*;
 use gtk::{glib, Application, ApplicationWindow};
 use plotters_gtk4::Paintable;
-use crate::sim::cauchy_fvm::CauchyFVM;
+use crate::sim::{new_simulator, Simulator, SimulatorKind};
+use crate::sim::boundary::{Region, Side};
 use crate::mesh::TriangleMesh;
 use crate::plotting;
 use ndarray::array;
 use std::sync::{Arc, Mutex};
 
-pub fn create_sim_window_threaded(sim: Arc<Mutex<CauchyFVM>>) -> glib::ExitCode {
+pub fn create_sim_window_threaded(sim: Arc<Mutex<Box<dyn Simulator>>>) -> glib::ExitCode {
     let app = Application::builder().
         application_id("org.example.Simulator").
         build();
@@ -34,14 +35,15 @@ pub fn create_sim_window_threaded(sim: Arc<Mutex<CauchyFVM>>) -> glib::ExitCode
         // 16ms = 60fps
         glib::timeout_add_local(std::time::Duration::from_millis(33), move || {
             let sim = sim_clone.lock().unwrap();
-            sim.display_on_paintable(&paintable); 
+            plotting::draw_simulation_on_paintable(sim.as_ref(), &paintable); 
             glib::ControlFlow::Continue
         });
 
         // show window
         window.present();
     });  
-    app.run()
+    // the command line is parsed by clap, GTK would reject its arguments
+    app.run_with_args::<&str>(&[])
 }
 
 pub fn create_sim_window() -> glib::ExitCode {
@@ -65,25 +67,33 @@ pub fn create_sim_window() -> glib::ExitCode {
         let tmesh = TriangleMesh::new_beam(6.0, 2.0, (12, 4));
         
         // Create refcell simulator
-        let sim = Rc::new(RefCell::new(CauchyFVM::new(&tmesh, "rubber", 1e-3)));
-        
+        let sim = Rc::new(RefCell::new(new_simulator(SimulatorKind::Fvm, &tmesh, "rubber", 1e-3)
+            .expect("Could not create simulator")));
+        if let Err(e) = sim.borrow().check_timestep() {
+            eprintln!("Warning: {}", e);
+        }
+
         {
             let paintable = paintable.clone();
             let sim = sim.clone();
            
+            let mut loaded = false;
+           
             // draw when ready
             glib::idle_add_local(move || {
-                sim.borrow_mut().update();
-                sim.borrow_mut().display_on_paintable(&paintable);
-                if sim.borrow_mut().t > 5.0 {
-                    sim.borrow_mut().
-                        set_immovable_boundary("leftright");
-                    sim.borrow_mut().
-                        set_traction_boundary("down");
-                    sim.borrow_mut().
-                        set_traction_force(array![0.0, -1e6]);
-
-
+                if let Err(e) = sim.borrow_mut().update() {
+                    eprintln!("{}", e);
+                    return glib::ControlFlow::Break;
+                }
+                plotting::draw_simulation_on_paintable(sim.borrow().as_ref(), &paintable);
+                if sim.borrow().time() > 5.0 && !loaded {
+                    // clamp both ends and push down on the bottom side
+                    let mut sim = sim.borrow_mut();
+                    let clamp = Region::Union(vec![Region::Side(Side::Left), Region::Side(Side::Right)]);
+                    sim.set_immovable_boundary(&clamp).unwrap();
+                    sim.clear_tractions();
+                    sim.add_traction(&Region::Side(Side::Down), array![0.0, -2.5e5]).unwrap();
+                    loaded = true;
                 } 
                 glib::ControlFlow::Continue
             });